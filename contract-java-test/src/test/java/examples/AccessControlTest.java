@@ -8,6 +8,11 @@ import com.partisiablockchain.language.abicodegen.AccessControl;
 import com.partisiablockchain.language.junit.ContractBytes;
 import com.partisiablockchain.language.junit.ContractTest;
 import com.partisiablockchain.language.junit.JunitContractTest;
+import com.secata.stream.SafeDataInputStream;
+import java.util.ArrayList;
+import java.util.LinkedHashMap;
+import java.util.List;
+import java.util.Map;
 
 /** Tests. */
 public final class AccessControlTest extends JunitContractTest {
@@ -19,6 +24,13 @@ public final class AccessControlTest extends JunitContractTest {
   private BlockchainAddress account4;
   private BlockchainAddress accessControlContract;
 
+  private static final int ADMIN_DISCRIMINANT = 0;
+  private static final int MODERATOR_A_DISCRIMINANT = 1;
+  private static final int MODERATOR_B_DISCRIMINANT = 2;
+
+  private static final int BORROWED_DISCRIMINANT = 0;
+  private static final int RETURNED_DISCRIMINANT = 1;
+
   /** Setup for all the other tests. Deploys the contract. */
   @ContractTest
   void setup() {
@@ -71,6 +83,23 @@ public final class AccessControlTest extends JunitContractTest {
     assertThat(state.currentlyHeldBy().data()).isEqualTo(null);
   }
 
+  /** Borrowing and returning the object returns custody events with the user and block time. */
+  @ContractTest(previous = "setup")
+  void borrowAndReturnEmitCustodyEvents() {
+    blockchain.waitForBlockProductionTime(10_000);
+    CustodyEvent borrowEvent = sendCustodyAction(account2, AccessControl.borrowObject());
+    blockchain.waitForBlockProductionTime(20_000);
+    CustodyEvent returnEvent = sendCustodyAction(account2, AccessControl.returnObject());
+
+    assertThat(borrowEvent.kind()).isEqualTo(BORROWED_DISCRIMINANT);
+    assertThat(borrowEvent.user()).isEqualTo(account2);
+    assertThat(borrowEvent.blockTime()).isBetween(10_000L, 20_000L);
+
+    assertThat(returnEvent.kind()).isEqualTo(RETURNED_DISCRIMINANT);
+    assertThat(returnEvent.user()).isEqualTo(account2);
+    assertThat(returnEvent.blockTime()).isGreaterThanOrEqualTo(20_000L);
+  }
+
   /** Can't return the object when it is not lent out. */
   @ContractTest(previous = "setup")
   void cantReturnWhenNotLent() {
@@ -245,6 +274,60 @@ public final class AccessControlTest extends JunitContractTest {
         .hasMessageContaining("Only 'Admin' can update level");
   }
 
+  /** Admin can list the levels of all users, also when paging past the end of the map. */
+  @ContractTest(previous = "updateUserLevels")
+  void adminListsAllLevels() {
+    Map<BlockchainAddress, Integer> levels = allLevels(0);
+    assertThat(levels)
+        .containsOnly(
+            Map.entry(account1, ADMIN_DISCRIMINANT),
+            Map.entry(account2, MODERATOR_A_DISCRIMINANT),
+            Map.entry(account3, MODERATOR_B_DISCRIMINANT));
+
+    AccessControl.ContractState state =
+        AccessControl.ContractState.deserialize(blockchain.getContractState(accessControlContract));
+    assertThat(levels.keySet()).containsExactlyElementsOf(state.accessMap().map().keySet());
+
+    assertThat(allLevels(1)).containsOnlyKeys(List.copyOf(levels.keySet()).subList(1, 3));
+    assertThat(allLevels(3)).isEmpty();
+  }
+
+  /** The levels are listed in pages of at most 100 users. */
+  @ContractTest(previous = "setup")
+  void adminListsAllLevelsInPages() {
+    List<AccessControl.UserLevelUpdate> updates = new ArrayList<>();
+    for (int i = 0; i < 99; i++) {
+      updates.add(
+          new AccessControl.UserLevelUpdate(
+              blockchain.newAccount(100 + i), new AccessControl.SecurityLevelImplModeratorB()));
+    }
+    blockchain.sendAction(
+        account1, accessControlContract, AccessControl.batchUpdateUserLevels(updates));
+
+    assertThat(allLevels(0)).hasSize(100);
+    assertThat(allLevels(99)).hasSize(1);
+    assertThat(allLevels(100)).isEmpty();
+
+    blockchain.sendAction(
+        account1,
+        accessControlContract,
+        AccessControl.updateUserLevel(account2, new AccessControl.SecurityLevelImplModeratorA()));
+
+    assertThat(allLevels(0)).hasSize(100);
+    assertThat(allLevels(100)).hasSize(1);
+    assertThat(allLevels(101)).isEmpty();
+  }
+
+  /** Users below Admin cannot list the levels of all users. */
+  @ContractTest(previous = "updateUserLevels")
+  void nonAdminCannotListAllLevels() {
+    byte[] payload = AccessControl.allLevels(0);
+    assertThatThrownBy(() -> blockchain.sendAction(account2, accessControlContract, payload))
+        .hasMessageContaining("Only 'Admin' can list all user levels");
+    assertThatThrownBy(() -> blockchain.sendAction(account4, accessControlContract, payload))
+        .hasMessageContaining("Only 'Admin' can list all user levels");
+  }
+
   /** User cannot borrow an object, that is already borrowed. */
   @ContractTest(previous = "setup")
   void objectBorrowTwiceByDifferentUsers() {
@@ -258,4 +341,242 @@ public final class AccessControlTest extends JunitContractTest {
     assertThatThrownBy(() -> blockchain.sendAction(account4, accessControlContract, payload))
         .hasMessageContaining("Object is already lent out");
   }
+
+  /** Admin can create several keyed items protected by different levels. */
+  @ContractTest(previous = "updateUserLevels")
+  void createProtectedItems() {
+    blockchain.sendAction(
+        account1,
+        accessControlContract,
+        AccessControl.createProtected(
+            "location", new AccessControl.SecurityLevelImplUser(), "Shelf 1"));
+    blockchain.sendAction(
+        account1,
+        accessControlContract,
+        AccessControl.createProtected(
+            "condition", new AccessControl.SecurityLevelImplModeratorA(), "Good"));
+    blockchain.sendAction(
+        account1,
+        accessControlContract,
+        AccessControl.createProtected(
+            "price", new AccessControl.SecurityLevelImplAdmin(), "100"));
+
+    AccessControl.ContractState state =
+        AccessControl.ContractState.deserialize(blockchain.getContractState(accessControlContract));
+    assertThat(state.protectedItems()).hasSize(3);
+    assertThat(state.protectedItems().get("location").data()).isEqualTo("Shelf 1");
+    assertThat(state.protectedItems().get("location").level().discriminant())
+        .isEqualTo(AccessControl.SecurityLevelImplD.USER);
+    assertThat(state.protectedItems().get("condition").level().discriminant())
+        .isEqualTo(AccessControl.SecurityLevelImplD.MODERATOR_A);
+    assertThat(state.protectedItems().get("price").level().discriminant())
+        .isEqualTo(AccessControl.SecurityLevelImplD.ADMIN);
+  }
+
+  /** Keyed items can only be updated by users with a high enough level for that item. */
+  @ContractTest(previous = "createProtectedItems")
+  void updateProtectedItems() {
+    blockchain.sendAction(
+        account4, accessControlContract, AccessControl.updateProtected("location", "Shelf 2"));
+    blockchain.sendAction(
+        account2, accessControlContract, AccessControl.updateProtected("condition", "Worn"));
+
+    byte[] updateConditionByModeratorB = AccessControl.updateProtected("condition", "Torn");
+    assertThatThrownBy(
+            () ->
+                blockchain.sendAction(
+                    account3, accessControlContract, updateConditionByModeratorB))
+        .hasMessageContaining(
+            "User with level 'ModeratorB' does not have the privilege to update data with level"
+                + " 'ModeratorA'");
+
+    byte[] updatePriceByModeratorA = AccessControl.updateProtected("price", "0");
+    assertThatThrownBy(
+            () -> blockchain.sendAction(account2, accessControlContract, updatePriceByModeratorA))
+        .hasMessageContaining(
+            "User with level 'ModeratorA' does not have the privilege to update data with level"
+                + " 'Admin'");
+
+    AccessControl.ContractState state =
+        AccessControl.ContractState.deserialize(blockchain.getContractState(accessControlContract));
+    assertThat(state.protectedItems().get("location").data()).isEqualTo("Shelf 2");
+    assertThat(state.protectedItems().get("condition").data()).isEqualTo("Worn");
+    assertThat(state.protectedItems().get("price").data()).isEqualTo("100");
+  }
+
+  /** Admin can change the level of a keyed item, such that other users can update it. */
+  @ContractTest(previous = "createProtectedItems")
+  void setProtectedLevel() {
+    blockchain.sendAction(
+        account1,
+        accessControlContract,
+        AccessControl.setProtectedLevel("price", new AccessControl.SecurityLevelImplModeratorB()));
+    blockchain.sendAction(
+        account3, accessControlContract, AccessControl.updateProtected("price", "80"));
+
+    byte[] setLevelByModerator =
+        AccessControl.setProtectedLevel("price", new AccessControl.SecurityLevelImplUser());
+    assertThatThrownBy(
+            () -> blockchain.sendAction(account3, accessControlContract, setLevelByModerator))
+        .hasMessageContaining("Only 'Admin' can update level");
+
+    AccessControl.ContractState state =
+        AccessControl.ContractState.deserialize(blockchain.getContractState(accessControlContract));
+    assertThat(state.protectedItems().get("price").data()).isEqualTo("80");
+    assertThat(state.protectedItems().get("price").level().discriminant())
+        .isEqualTo(AccessControl.SecurityLevelImplD.MODERATOR_B);
+  }
+
+  /** Only Admin can create keyed items, and keys must be unique. */
+  @ContractTest(previous = "createProtectedItems")
+  void cannotCreateProtectedItem() {
+    byte[] createByModerator =
+        AccessControl.createProtected(
+            "owner", new AccessControl.SecurityLevelImplUser(), "Library");
+    assertThatThrownBy(
+            () -> blockchain.sendAction(account2, accessControlContract, createByModerator))
+        .hasMessageContaining("Only 'Admin' can create protected data");
+
+    byte[] createExisting =
+        AccessControl.createProtected("price", new AccessControl.SecurityLevelImplUser(), "0");
+    assertThatThrownBy(() -> blockchain.sendAction(account1, accessControlContract, createExisting))
+        .hasMessageContaining("Protected data with key 'price' already exists");
+  }
+
+  /** Keyed items that have not been created cannot be updated. */
+  @ContractTest(previous = "setup")
+  void updateUnknownProtectedItem() {
+    byte[] payload = AccessControl.updateProtected("unknown", "data");
+    assertThatThrownBy(() -> blockchain.sendAction(account1, accessControlContract, payload))
+        .hasMessageContaining("No protected data with key 'unknown'");
+  }
+
+  /** An Admin cannot demote themselves, as users can only update users below their own level. */
+  @ContractTest(previous = "setup")
+  void adminCannotDemoteSelf() {
+    byte[] payload =
+        AccessControl.updateUserLevel(account1, new AccessControl.SecurityLevelImplUser());
+    assertThatThrownBy(() -> blockchain.sendAction(account1, accessControlContract, payload))
+        .hasMessageContaining("Sender level 'Admin' cannot update user with level 'Admin'");
+
+    AccessControl.ContractState state =
+        AccessControl.ContractState.deserialize(blockchain.getContractState(accessControlContract));
+    assertThat(state.accessMap().map().get(account1).discriminant())
+        .isEqualTo(AccessControl.SecurityLevelImplD.ADMIN);
+  }
+
+  /** Admins cannot demote each other, so an Admin always remains. */
+  @ContractTest(previous = "setup")
+  void adminsCannotDemoteEachOther() {
+    byte[] promote =
+        AccessControl.updateUserLevel(account2, new AccessControl.SecurityLevelImplAdmin());
+    blockchain.sendAction(account1, accessControlContract, promote);
+
+    byte[] demoteSecond =
+        AccessControl.updateUserLevel(account2, new AccessControl.SecurityLevelImplUser());
+    assertThatThrownBy(() -> blockchain.sendAction(account1, accessControlContract, demoteSecond))
+        .hasMessageContaining("Sender level 'Admin' cannot update user with level 'Admin'");
+
+    byte[] demoteFirst =
+        AccessControl.updateUserLevel(account1, new AccessControl.SecurityLevelImplUser());
+    assertThatThrownBy(() -> blockchain.sendAction(account2, accessControlContract, demoteFirst))
+        .hasMessageContaining("Sender level 'Admin' cannot update user with level 'Admin'");
+
+    AccessControl.ContractState state =
+        AccessControl.ContractState.deserialize(blockchain.getContractState(accessControlContract));
+    assertThat(state.accessMap().map().get(account1).discriminant())
+        .isEqualTo(AccessControl.SecurityLevelImplD.ADMIN);
+    assertThat(state.accessMap().map().get(account2).discriminant())
+        .isEqualTo(AccessControl.SecurityLevelImplD.ADMIN);
+  }
+
+  /** A user cannot change their own level. */
+  @ContractTest(previous = "updateUserLevels")
+  void moderatorCannotUpdateOwnLevel() {
+    byte[] lower =
+        AccessControl.updateUserLevel(account2, new AccessControl.SecurityLevelImplUser());
+    assertThatThrownBy(() -> blockchain.sendAction(account2, accessControlContract, lower))
+        .hasMessageContaining(
+            "Sender level 'ModeratorA' cannot update user with level 'ModeratorA'");
+
+    AccessControl.ContractState state =
+        AccessControl.ContractState.deserialize(blockchain.getContractState(accessControlContract));
+    assertThat(state.accessMap().map().get(account2).discriminant())
+        .isEqualTo(AccessControl.SecurityLevelImplD.MODERATOR_A);
+  }
+
+  /** An Admin can update the levels of several users in one transaction. */
+  @ContractTest(previous = "setup")
+  void batchUpdateUserLevels() {
+    byte[] payload =
+        AccessControl.batchUpdateUserLevels(
+            List.of(
+                new AccessControl.UserLevelUpdate(
+                    account2, new AccessControl.SecurityLevelImplModeratorA()),
+                new AccessControl.UserLevelUpdate(
+                    account3, new AccessControl.SecurityLevelImplModeratorB()),
+                new AccessControl.UserLevelUpdate(
+                    account4, new AccessControl.SecurityLevelImplAdmin())));
+    blockchain.sendAction(account1, accessControlContract, payload);
+
+    AccessControl.ContractState state =
+        AccessControl.ContractState.deserialize(blockchain.getContractState(accessControlContract));
+    assertThat(state.accessMap().map()).hasSize(4);
+    assertThat(state.accessMap().map().get(account2).discriminant())
+        .isEqualTo(AccessControl.SecurityLevelImplD.MODERATOR_A);
+    assertThat(state.accessMap().map().get(account3).discriminant())
+        .isEqualTo(AccessControl.SecurityLevelImplD.MODERATOR_B);
+    assertThat(state.accessMap().map().get(account4).discriminant())
+        .isEqualTo(AccessControl.SecurityLevelImplD.ADMIN);
+  }
+
+  /** A batch containing a single unauthorized update is rejected entirely. */
+  @ContractTest(previous = "updateUserLevels")
+  void batchWithUnauthorizedUpdateIsRejected() {
+    byte[] payload =
+        AccessControl.batchUpdateUserLevels(
+            List.of(
+                new AccessControl.UserLevelUpdate(
+                    account4, new AccessControl.SecurityLevelImplModeratorA()),
+                new AccessControl.UserLevelUpdate(
+                    account3, new AccessControl.SecurityLevelImplUser())));
+    assertThatThrownBy(() -> blockchain.sendAction(account2, accessControlContract, payload))
+        .hasMessageContaining(
+            "Sender level 'ModeratorA' cannot update user with level 'ModeratorB'");
+
+    AccessControl.ContractState state =
+        AccessControl.ContractState.deserialize(blockchain.getContractState(accessControlContract));
+    assertThat(state.accessMap().map()).hasSize(3);
+    assertThat(state.accessMap().map().get(account4)).isNull();
+    assertThat(state.accessMap().map().get(account3).discriminant())
+        .isEqualTo(AccessControl.SecurityLevelImplD.MODERATOR_B);
+  }
+
+  /**
+   * List the levels of the users as returned by {@code all_levels}, keeping the returned order.
+   * Levels are given by the discriminant of the level.
+   */
+  private Map<BlockchainAddress, Integer> allLevels(int offset) {
+    SafeDataInputStream page =
+        ReturnData.of(
+            blockchain.sendAction(
+                account1, accessControlContract, AccessControl.allLevels(offset)));
+    int numberOfUsers = page.readInt();
+    Map<BlockchainAddress, Integer> levels = new LinkedHashMap<>();
+    for (int i = 0; i < numberOfUsers; i++) {
+      levels.put(BlockchainAddress.read(page), page.readUnsignedByte());
+    }
+    return levels;
+  }
+
+  /** Send an action changing who holds the object, and read the custody event it returns. */
+  private CustodyEvent sendCustodyAction(BlockchainAddress sender, byte[] rpc) {
+    SafeDataInputStream event =
+        ReturnData.of(blockchain.sendAction(sender, accessControlContract, rpc));
+    return new CustodyEvent(
+        event.readUnsignedByte(), BlockchainAddress.read(event), event.readLong());
+  }
+
+  /** A change of who holds the object, as returned when borrowing or returning it. */
+  private record CustodyEvent(int kind, BlockchainAddress user, long blockTime) {}
 }
@@ -7,6 +7,8 @@ import com.partisiablockchain.language.junit.ContractBytes;
 import com.partisiablockchain.language.junit.ContractTest;
 import com.partisiablockchain.language.junit.JunitContractTest;
 import com.partisiablockchain.language.junit.exceptions.ActionFailureException;
+import com.secata.stream.SafeDataInputStream;
+import java.util.List;
 import java.util.Locale;
 import org.assertj.core.api.Assertions;
 
@@ -32,7 +34,7 @@ public final class DnsTest extends JunitContractTest {
     voter = blockchain.newAccount(2);
     admin = blockchain.newAccount(3);
 
-    byte[] initDnsRpc = Dns.initialize();
+    byte[] initDnsRpc = Dns.initialize(List.of("Partisia"));
     dnsAddress = blockchain.deployContract(voter, DNS_CONTRACT_BYTES, initDnsRpc);
     dnsContract = new Dns(getStateClient(), dnsAddress);
 
@@ -149,7 +151,7 @@ public final class DnsTest extends JunitContractTest {
     Assertions.assertThatThrownBy(() -> blockchain.sendAction(voter, dnsAddress, updateRpc))
         .isInstanceOf(ActionFailureException.class)
         .hasMessageContaining(
-            "Only the owner of the domain can modify it. Owner: "
+            "Only the owner or the resolver of the domain can modify it. Owner: "
                 + admin.writeAsString().toUpperCase(Locale.getDefault())
                 + ", Sender: "
                 + voter.writeAsString().toUpperCase(Locale.getDefault()));
@@ -159,4 +161,317 @@ public final class DnsTest extends JunitContractTest {
 
     Assertions.assertThat(records.get("domainname").address()).isEqualTo(testAddress1);
   }
+
+  /** A resolver set by the owner can update the address of the domain. */
+  @ContractTest(previous = "setUp")
+  public void resolverUpdatesDomain() {
+    BlockchainAddress newAddress = blockchain.newAccount(5);
+    blockchain.sendAction(admin, dnsAddress, Dns.registerDomain("domainname", testAddress1));
+    blockchain.sendAction(admin, dnsAddress, Dns.setResolver("domainname", voter));
+
+    blockchain.sendAction(voter, dnsAddress, Dns.updateDomain("domainname", newAddress));
+
+    Dns.DnsEntry entry = dnsContract.getState().records().get("domainname");
+    Assertions.assertThat(entry.address()).isEqualTo(newAddress);
+    Assertions.assertThat(entry.owner()).isEqualTo(admin);
+    Assertions.assertThat(entry.resolver()).isEqualTo(voter);
+  }
+
+  /** A resolver can neither remove the domain nor transfer its ownership. */
+  @ContractTest(previous = "setUp")
+  public void resolverCannotRemoveOrTransferDomain() {
+    blockchain.sendAction(admin, dnsAddress, Dns.registerDomain("domainname", testAddress1));
+    blockchain.sendAction(admin, dnsAddress, Dns.setResolver("domainname", voter));
+
+    Assertions.assertThatThrownBy(
+            () -> blockchain.sendAction(voter, dnsAddress, Dns.removeDomain("domainname")))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Only the owner of the domain can delete it.");
+    Assertions.assertThatThrownBy(
+            () -> blockchain.sendAction(voter, dnsAddress, Dns.offerDomain("domainname", voter)))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Only the owner of the domain can transfer it.");
+
+    Dns.DnsState state = dnsContract.getState();
+    Assertions.assertThat(state.records().get("domainname").owner()).isEqualTo(admin);
+    Assertions.assertThat(state.domainCount()).isEqualTo(1L);
+  }
+
+  /** Only the owner of a domain can set its resolver. */
+  @ContractTest(previous = "setUp")
+  public void setResolverByNonOwner() {
+    blockchain.sendAction(admin, dnsAddress, Dns.registerDomain("domainname", testAddress1));
+
+    Assertions.assertThatThrownBy(
+            () -> blockchain.sendAction(voter, dnsAddress, Dns.setResolver("domainname", voter)))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Only the owner of the domain can set its resolver.");
+    Assertions.assertThat(dnsContract.getState().records().get("domainname").resolver()).isNull();
+  }
+
+  /** The owner can remove the resolver, after which it can no longer update the domain. */
+  @ContractTest(previous = "setUp")
+  public void removeResolver() {
+    blockchain.sendAction(admin, dnsAddress, Dns.registerDomain("domainname", testAddress1));
+    blockchain.sendAction(admin, dnsAddress, Dns.setResolver("domainname", voter));
+    blockchain.sendAction(admin, dnsAddress, Dns.setResolver("domainname", null));
+
+    Assertions.assertThatThrownBy(
+            () ->
+                blockchain.sendAction(
+                    voter, dnsAddress, Dns.updateDomain("domainname", testAddress2)))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Only the owner or the resolver of the domain can modify it.");
+  }
+
+  /** Registering a domain returns an event with the registered address. */
+  @ContractTest(previous = "setUp")
+  public void registerEmitsEvent() {
+    DomainEvent event = sendDomainAction(Dns.registerDomain("domainname", testAddress1));
+
+    Assertions.assertThat(event).isEqualTo(new DomainEvent("domainname", testAddress1));
+  }
+
+  /** Updating a domain returns an event with the new address. */
+  @ContractTest(previous = "setUp")
+  public void updateEmitsEvent() {
+    BlockchainAddress newAddress =
+        BlockchainAddress.fromString("02A000000000000000000000000000000000000001");
+    blockchain.sendAction(admin, dnsAddress, Dns.registerDomain("domainname", testAddress1));
+    DomainEvent event = sendDomainAction(Dns.updateDomain("domainname", newAddress));
+
+    Assertions.assertThat(event).isEqualTo(new DomainEvent("domainname", newAddress));
+  }
+
+  /** Removing a domain returns an event without an address. */
+  @ContractTest(previous = "setUp")
+  public void removeEmitsEvent() {
+    blockchain.sendAction(admin, dnsAddress, Dns.registerDomain("domainname", testAddress1));
+    DomainEvent event = sendDomainAction(Dns.removeDomain("domainname"));
+
+    Assertions.assertThat(event).isEqualTo(new DomainEvent("domainname", null));
+  }
+
+  /** The owner can offer a domain to a new owner, who becomes the owner by claiming it. */
+  @ContractTest(previous = "setUp")
+  public void offerAndClaimDomain() {
+    blockchain.sendAction(admin, dnsAddress, Dns.registerDomain("domainname", testAddress1));
+    blockchain.sendAction(admin, dnsAddress, Dns.offerDomain("domainname", voter));
+
+    Assertions.assertThat(dnsContract.getState().pendingOffers().get("domainname"))
+        .isEqualTo(voter);
+
+    blockchain.sendAction(voter, dnsAddress, Dns.claimDomain("domainname"));
+
+    Dns.DnsState state = dnsContract.getState();
+    Assertions.assertThat(state.records().get("domainname").owner()).isEqualTo(voter);
+    Assertions.assertThat(state.records().get("domainname").address()).isEqualTo(testAddress1);
+    Assertions.assertThat(state.pendingOffers().size()).isEqualTo(0);
+
+    blockchain.sendAction(voter, dnsAddress, Dns.updateDomain("domainname", testAddress2));
+    Assertions.assertThatThrownBy(
+            () -> blockchain.sendAction(admin, dnsAddress, Dns.removeDomain("domainname")))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Only the owner of the domain can delete it.");
+  }
+
+  /** The owner can cancel a pending offer, after which the domain can no longer be claimed. */
+  @ContractTest(previous = "setUp")
+  public void cancelOffer() {
+    blockchain.sendAction(admin, dnsAddress, Dns.registerDomain("domainname", testAddress1));
+    blockchain.sendAction(admin, dnsAddress, Dns.offerDomain("domainname", voter));
+    blockchain.sendAction(admin, dnsAddress, Dns.cancelOffer("domainname"));
+
+    Assertions.assertThat(dnsContract.getState().pendingOffers().size()).isEqualTo(0);
+    Assertions.assertThatThrownBy(
+            () -> blockchain.sendAction(voter, dnsAddress, Dns.claimDomain("domainname")))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("No pending offer for the domain.");
+    Assertions.assertThat(dnsContract.getState().records().get("domainname").owner())
+        .isEqualTo(admin);
+  }
+
+  /** Only the address the domain was offered to can claim it. */
+  @ContractTest(previous = "setUp")
+  public void wrongClaimantIsRejected() {
+    BlockchainAddress other = blockchain.newAccount(4);
+    blockchain.sendAction(admin, dnsAddress, Dns.registerDomain("domainname", testAddress1));
+    blockchain.sendAction(admin, dnsAddress, Dns.offerDomain("domainname", voter));
+
+    Assertions.assertThatThrownBy(
+            () -> blockchain.sendAction(other, dnsAddress, Dns.claimDomain("domainname")))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Only the offered address can claim the domain.");
+
+    Dns.DnsState state = dnsContract.getState();
+    Assertions.assertThat(state.records().get("domainname").owner()).isEqualTo(admin);
+    Assertions.assertThat(state.pendingOffers().get("domainname")).isEqualTo(voter);
+  }
+
+  /** Only the owner of a domain can offer it to a new owner. */
+  @ContractTest(previous = "setUp")
+  public void offerByNonOwner() {
+    blockchain.sendAction(admin, dnsAddress, Dns.registerDomain("domainname", testAddress1));
+
+    Assertions.assertThatThrownBy(
+            () -> blockchain.sendAction(voter, dnsAddress, Dns.offerDomain("domainname", voter)))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Only the owner of the domain can transfer it.");
+  }
+
+  /** Removing a domain also removes any pending offer for it. */
+  @ContractTest(previous = "setUp")
+  public void removeDomainDiscardsOffer() {
+    blockchain.sendAction(admin, dnsAddress, Dns.registerDomain("domainname", testAddress1));
+    blockchain.sendAction(admin, dnsAddress, Dns.offerDomain("domainname", voter));
+    blockchain.sendAction(admin, dnsAddress, Dns.removeDomain("domainname"));
+
+    Assertions.assertThat(dnsContract.getState().pendingOffers().size()).isEqualTo(0);
+  }
+
+  /** Domains are case-insensitive, and are stored in lowercase. */
+  @ContractTest(previous = "setUp")
+  public void registerIsCaseInsensitive() {
+    blockchain.sendAction(admin, dnsAddress, Dns.registerDomain("Example", testAddress1));
+
+    Dns.DnsState state = dnsContract.getState();
+    Assertions.assertThat(state.records().get("example").address()).isEqualTo(testAddress1);
+    Assertions.assertThat(state.records().get("Example")).isNull();
+
+    Assertions.assertThatThrownBy(
+            () ->
+                blockchain.sendAction(
+                    voter, dnsAddress, Dns.registerDomain("EXAMPLE", testAddress2)))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Domain already registered");
+  }
+
+  /** Updating and removing a domain normalizes the domain to lowercase. */
+  @ContractTest(previous = "setUp")
+  public void updateAndRemoveAreCaseInsensitive() {
+    blockchain.sendAction(admin, dnsAddress, Dns.registerDomain("example", testAddress1));
+
+    Assertions.assertThat(sendDomainAction(Dns.updateDomain("eXample", testAddress2)))
+        .isEqualTo(new DomainEvent("example", testAddress2));
+    Assertions.assertThat(dnsContract.getState().records().get("example").address())
+        .isEqualTo(testAddress2);
+
+    Assertions.assertThat(sendDomainAction(Dns.removeDomain("EXAMPLE")))
+        .isEqualTo(new DomainEvent("example", null));
+    Assertions.assertThat(dnsContract.getState().records().size()).isEqualTo(0);
+  }
+
+  /** Domains containing characters other than letters, digits, '-' and '.' are rejected. */
+  @ContractTest(previous = "setUp")
+  public void registerInvalidDomain() {
+    Assertions.assertThatThrownBy(
+            () ->
+                blockchain.sendAction(
+                    admin, dnsAddress, Dns.registerDomain("my domain", testAddress1)))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining(
+            "Invalid domain 'my domain'. Domains may only contain letters, digits, '-' and '.'");
+
+    Assertions.assertThat(dnsContract.getState().records().size()).isEqualTo(0);
+  }
+
+  /** The domain count follows registrations and removals, but not updates. */
+  @ContractTest(previous = "setUp")
+  public void domainCountFollowsRegistrations() {
+    Assertions.assertThat(dnsContract.getState().domainCount()).isEqualTo(0L);
+
+    blockchain.sendAction(admin, dnsAddress, Dns.registerDomain("domain1", testAddress1));
+    blockchain.sendAction(admin, dnsAddress, Dns.registerDomain("domain2", testAddress1));
+    Assertions.assertThat(dnsContract.getState().domainCount()).isEqualTo(2L);
+
+    blockchain.sendAction(admin, dnsAddress, Dns.updateDomain("domain1", testAddress2));
+    Assertions.assertThat(dnsContract.getState().domainCount()).isEqualTo(2L);
+
+    blockchain.sendAction(admin, dnsAddress, Dns.removeDomain("domain2"));
+    Assertions.assertThat(dnsContract.getState().domainCount()).isEqualTo(1L);
+    Assertions.assertThat(dnsContract.getState().records().size()).isEqualTo(1);
+  }
+
+  /** Failed registrations and removals do not change the domain count. */
+  @ContractTest(previous = "setUp")
+  public void failedMutationsDoNotChangeDomainCount() {
+    blockchain.sendAction(admin, dnsAddress, Dns.registerDomain("domainname", testAddress1));
+    Assertions.assertThatThrownBy(
+            () ->
+                blockchain.sendAction(
+                    voter, dnsAddress, Dns.registerDomain("domainname", testAddress2)))
+        .isInstanceOf(ActionFailureException.class);
+    Assertions.assertThatThrownBy(
+            () -> blockchain.sendAction(voter, dnsAddress, Dns.removeDomain("domainname")))
+        .isInstanceOf(ActionFailureException.class);
+
+    Assertions.assertThat(dnsContract.getState().domainCount()).isEqualTo(1L);
+  }
+
+  /** Reserved names cannot be registered, while other names can. */
+  @ContractTest(previous = "setUp")
+  public void registerReservedName() {
+    Assertions.assertThat(dnsContract.getState().reservedNames()).containsExactly("partisia");
+
+    byte[] registerReservedRpc = Dns.registerDomain("PARTISIA", testAddress1);
+    Assertions.assertThatThrownBy(
+            () -> blockchain.sendAction(admin, dnsAddress, registerReservedRpc))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Domain 'partisia' is reserved");
+
+    blockchain.sendAction(admin, dnsAddress, Dns.registerDomain("domainname", testAddress1));
+    Assertions.assertThat(dnsContract.getState().records().size()).isEqualTo(1);
+  }
+
+  /** The deployer can reserve a name, and later make it available for registration again. */
+  @ContractTest(previous = "setUp")
+  public void addAndRemoveReservedName() {
+    blockchain.sendAction(voter, dnsAddress, Dns.addReservedName("brand"));
+    byte[] registerRpc = Dns.registerDomain("brand", testAddress1);
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(admin, dnsAddress, registerRpc))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Domain 'brand' is reserved");
+
+    blockchain.sendAction(voter, dnsAddress, Dns.removeReservedName("brand"));
+    blockchain.sendAction(admin, dnsAddress, registerRpc);
+    Assertions.assertThat(dnsContract.getState().records().get("brand").owner()).isEqualTo(admin);
+  }
+
+  /** Only the deployer of the DNS can manage the reserved names. */
+  @ContractTest(previous = "setUp")
+  public void nonDeployerManagesReservedNames() {
+    byte[] addRpc = Dns.addReservedName("brand");
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(admin, dnsAddress, addRpc))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Only the deployer of the DNS can manage reserved names.");
+
+    byte[] removeRpc = Dns.removeReservedName("partisia");
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(admin, dnsAddress, removeRpc))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Only the deployer of the DNS can manage reserved names.");
+  }
+
+  /** A name that is not reserved cannot be removed from the reserved names. */
+  @ContractTest(previous = "setUp")
+  public void removeUnreservedName() {
+    byte[] removeRpc = Dns.removeReservedName("domainname");
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(voter, dnsAddress, removeRpc))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Name 'domainname' is not reserved");
+  }
+
+  /**
+   * Send an action changing a domain as the admin, and read the domain event it returns.
+   *
+   * @param rpc The action changing the domain.
+   * @return The returned domain event.
+   */
+  private DomainEvent sendDomainAction(byte[] rpc) {
+    SafeDataInputStream event = ReturnData.of(blockchain.sendAction(admin, dnsAddress, rpc));
+    String domain = event.readString();
+    return new DomainEvent(domain, ReturnData.readOption(event, BlockchainAddress::read));
+  }
+
+  /** A change to a domain, as returned by the actions changing domains. */
+  private record DomainEvent(String domain, BlockchainAddress address) {}
 }
@@ -38,7 +38,7 @@ public final class DnsVotingClientTest extends JunitContractTest {
     voter = blockchain.newAccount(2);
     admin = blockchain.newAccount(3);
 
-    byte[] initDnsRpc = Dns.initialize();
+    byte[] initDnsRpc = Dns.initialize(List.of());
     dnsAddress = blockchain.deployContract(voter, DNS_CONTRACT_BYTES, initDnsRpc);
 
     byte[] initDnsClientRpc = DnsVotingClient.initialize(dnsAddress);
@@ -88,6 +88,16 @@ public final class DnsVotingClientTest extends JunitContractTest {
     Assertions.assertThat(castVotes).isEmpty();
   }
 
+  /** The voting domain is resolved regardless of its case. */
+  @ContractTest(previous = "setUp")
+  public void voteWithDifferentlyCasedDomain() {
+    byte[] voteRpc = DnsVotingClient.vote("Voting", true);
+    blockchain.sendAction(voter, dnsVotingClientAddress, voteRpc);
+
+    Map<BlockchainAddress, Boolean> castVotes = votingContract.getState().votes();
+    Assertions.assertThat(castVotes).isEqualTo(Map.of(dnsVotingClientAddress, true));
+  }
+
   /** A user cannot cast a vote if the voting domain is not registered in the DNS. */
   @ContractTest(previous = "setUp")
   public void voteBadDomain() {
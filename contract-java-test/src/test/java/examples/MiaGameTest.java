@@ -2,6 +2,7 @@ package examples;
 
 import com.partisiablockchain.BlockchainAddress;
 import com.partisiablockchain.language.abicodegen.MiaGame;
+import com.partisiablockchain.language.abicodegen.Token;
 import com.partisiablockchain.language.junit.ContractBytes;
 import com.partisiablockchain.language.junit.ContractTest;
 import com.partisiablockchain.language.junit.JunitContractTest;
@@ -10,9 +11,12 @@ import com.partisiablockchain.language.junit.exceptions.ActionFailureException;
 import com.partisiablockchain.language.junit.exceptions.SecretInputFailureException;
 import com.secata.stream.BitOutput;
 import com.secata.stream.CompactBitArray;
+import com.secata.stream.SafeDataInputStream;
+import java.math.BigInteger;
 import java.util.ArrayList;
 import java.util.Arrays;
 import java.util.List;
+import java.util.stream.Stream;
 import org.assertj.core.api.Assertions;
 import org.junit.jupiter.params.ParameterizedTest;
 import org.junit.jupiter.params.provider.MethodSource;
@@ -23,6 +27,35 @@ public final class MiaGameTest extends JunitContractTest {
   /** {@link MiaGame} contract bytes. */
   private static final ContractBytes MIA_CONTRACT = ContractBytesLoader.forContract("mia_game");
 
+  /** Maximum number of players used when deploying the game. */
+  private static final int MAX_NR_OF_PLAYERS = 8;
+
+  /** Token configured for games without an entry fee. No contract is deployed at the address. */
+  private static final BlockchainAddress ENTRY_TOKEN =
+      BlockchainAddress.fromString("02A000000000000000000000000000000000000001");
+
+  /** Entry fee used for games without stakes. */
+  private static final BigInteger NO_ENTRY_FEE = BigInteger.ZERO;
+
+  /** Entry fee used for games with stakes. */
+  private static final BigInteger ENTRY_FEE = BigInteger.valueOf(100);
+
+  /** {@link Token} contract bytes, used for games with stakes. */
+  private static final ContractBytes TOKEN_CONTRACT = ContractBytesLoader.forContract("token");
+
+  /** Entry tokens initially held by each player in games with stakes. */
+  private static final BigInteger PLAYER_TOKENS = BigInteger.valueOf(1000);
+
+  /** Discriminants of the throw categories returned by {@code score_of}. */
+  private static final int CATEGORY_ORDINARY = 0;
+
+  private static final int CATEGORY_PAIR = 1;
+  private static final int CATEGORY_LITTLE_MIA = 2;
+  private static final int CATEGORY_MIA = 3;
+
+  /** Number of faces on standard dice. */
+  private static final byte DIE_FACES = 6;
+
   private static final DiceThrowPoints THIRTY_TWO =
       new DiceThrowPoints(new MiaGame.DiceThrow((byte) 1, (byte) 2), 3);
   private static final DiceThrowPoints FORTY_ONE =
@@ -91,6 +124,8 @@ public final class MiaGameTest extends JunitContractTest {
               MIA,
               LITTLE_MIA));
   private BlockchainAddress game;
+  private BlockchainAddress entryToken;
+  private BlockchainAddress tokenIssuer;
   private BlockchainAddress player1;
   private BlockchainAddress player2;
   private BlockchainAddress player3;
@@ -107,7 +142,7 @@ public final class MiaGameTest extends JunitContractTest {
 
     blockchain.addRealv1MpcNodes();
 
-    byte[] initRpc = MiaGame.initialize(List.of(player1, player2, player3));
+    byte[] initRpc = initialize(List.of(player1, player2, player3));
 
     game = blockchain.deployZkContract(player1, MIA_CONTRACT, initRpc);
 
@@ -140,7 +175,7 @@ public final class MiaGameTest extends JunitContractTest {
     BlockchainAddress player7 = blockchain.newAccount(7);
 
     byte[] initRpc =
-        MiaGame.initialize(List.of(player1, player2, player3, player4, player5, player6, player7));
+        initialize(List.of(player1, player2, player3, player4, player5, player6, player7));
 
     game = blockchain.deployZkContract(player1, MIA_CONTRACT, initRpc);
 
@@ -149,6 +184,68 @@ public final class MiaGameTest extends JunitContractTest {
     assertCurrentGamePhase(MiaGame.GamePhaseD.START);
   }
 
+  /**
+   * A game with the maximum number of players can be played down to a winner, and the turn passes
+   * to the next remaining player whenever a player is removed from the game.
+   */
+  @ContractTest
+  void maxNumberOfPlayersPlayDownToWinner() {
+    List<BlockchainAddress> allPlayers = new ArrayList<>();
+    for (int i = 1; i <= 7; i++) {
+      allPlayers.add(blockchain.newAccount(i));
+    }
+
+    blockchain.addRealv1MpcNodes();
+    game =
+        blockchain.deployZkContract(
+            allPlayers.get(0),
+            MIA_CONTRACT,
+            MiaGame.initialize(
+                allPlayers, allPlayers.size(), ENTRY_TOKEN, NO_ENTRY_FEE, DIE_FACES));
+    blockchain.sendAction(allPlayers.get(0), game, MiaGame.startRound());
+
+    // Each round the player in turn throws Mia, and the next player loses two lives by calling out.
+    List<BlockchainAddress> removedPlayers = new ArrayList<>();
+    while (getState().players().size() > 1) {
+      MiaGame.MiaState stateBeforeRound = getState();
+      List<BlockchainAddress> seatedPlayers = stateBeforeRound.players();
+      int throwerIndex = stateBeforeRound.playerThrowing();
+      BlockchainAddress thrower = seatedPlayers.get(throwerIndex);
+      BlockchainAddress loser = seatedPlayers.get((throwerIndex + 1) % seatedPlayers.size());
+
+      throwMia();
+      callThrowDice(thrower);
+      announceDiceValues(thrower, 0, 1);
+      calloutPlayer(loser);
+
+      List<BlockchainAddress> remainingPlayers = getState().players();
+      if (remainingPlayers.contains(loser)) {
+        assertPlayerInTurn(loser);
+      } else {
+        removedPlayers.add(loser);
+        if (remainingPlayers.size() > 1) {
+          BlockchainAddress seatedAfterLoser =
+              seatedPlayers.get((throwerIndex + 2) % seatedPlayers.size());
+          assertPlayerInTurn(seatedAfterLoser);
+        }
+      }
+    }
+
+    // Players are removed both when seated after the player in turn, and when seated first while
+    // the player in turn is seated last.
+    Assertions.assertThat(removedPlayers)
+        .containsExactly(
+            allPlayers.get(1),
+            allPlayers.get(3),
+            allPlayers.get(5),
+            allPlayers.get(0),
+            allPlayers.get(4),
+            allPlayers.get(2));
+    MiaGame.MiaState state = getState();
+    Assertions.assertThat(state.gamePhase().discriminant()).isEqualTo(MiaGame.GamePhaseD.DONE);
+    Assertions.assertThat(state.winner()).isEqualTo(allPlayers.get(6));
+  }
+
   /**
    * All players can add randomness, and the game phase is 'Throw' when all players have contributed
    * to the randomness.
@@ -304,6 +401,20 @@ public final class MiaGameTest extends JunitContractTest {
     Assertions.assertThat(state.players()).doesNotContain(player2);
   }
 
+  /** A player who is out of the game cannot add randomness to the throws of the others. */
+  @ContractTest(previous = "playerLoses")
+  void eliminatedPlayerCannotAddRandomness() {
+    assertCurrentGamePhase(MiaGame.GamePhaseD.ADD_RANDOMNESS);
+
+    Assertions.assertThatThrownBy(() -> addRandomness(player2, 0, 0))
+        .isInstanceOf(SecretInputFailureException.class)
+        .hasMessageContaining("Only players remaining in the game can add randomness.");
+
+    addRandomness(player3, 0, 0);
+    addRandomness(player1, 1, 0);
+    assertCurrentGamePhase(MiaGame.GamePhaseD.THROW);
+  }
+
   /** A player wins the game when only he or she has more lives left. */
   @ContractTest(previous = "playerLoses")
   void playerWins() {
@@ -314,14 +425,15 @@ public final class MiaGameTest extends JunitContractTest {
     announceDiceValues(player3, 0, 1);
     calloutPlayer(player1);
 
-    assertPlayersNumberOfLivesLeft(player1, 4);
+    // Player 1 gave up on a Mia in the previous game, and so has one life less.
+    assertPlayersNumberOfLivesLeft(player1, 3);
 
     specificThrow(0, 3);
     callThrowDice(player1);
     announceDiceValues(player1, 0, 1);
     calloutPlayer(player3);
 
-    assertPlayersNumberOfLivesLeft(player1, 2);
+    assertPlayersNumberOfLivesLeft(player1, 1);
 
     throwMia();
     callThrowDice(player3);
@@ -338,32 +450,430 @@ public final class MiaGameTest extends JunitContractTest {
     Assertions.assertThat(state.winner()).isEqualTo(player3);
   }
 
+  /** A game deployed without an entry fee has an empty pot, and no player has to pay. */
+  @ContractTest(previous = "deploy")
+  void gameWithoutEntryFee() {
+    MiaGame.MiaState state =
+        MiaGame.ZkStateImmutable.deserialize(blockchain.getContractState(game)).openState();
+    Assertions.assertThat(state.entryFee()).isEqualTo(NO_ENTRY_FEE);
+    Assertions.assertThat(state.pot()).isEqualTo(BigInteger.ZERO);
+    Assertions.assertThat(state.paidPlayers()).isEmpty();
+  }
+
+  /** A game with an entry fee cannot start before all players have paid the fee. */
+  @ContractTest
+  void deployWithEntryFee() {
+    player1 = blockchain.newAccount(1);
+    player2 = blockchain.newAccount(2);
+    player3 = blockchain.newAccount(3);
+
+    tokenIssuer = blockchain.newAccount(8);
+    entryToken =
+        blockchain.deployContract(
+            tokenIssuer,
+            TOKEN_CONTRACT,
+            Token.initialize(
+                "Entry",
+                "ENT",
+                (byte) 0,
+                PLAYER_TOKENS.multiply(BigInteger.valueOf(3)),
+                List.of()));
+    for (BlockchainAddress player : List.of(player1, player2, player3)) {
+      blockchain.sendAction(tokenIssuer, entryToken, Token.transfer(player, PLAYER_TOKENS));
+    }
+
+    blockchain.addRealv1MpcNodes();
+
+    byte[] initRpc =
+        MiaGame.initialize(
+            List.of(player1, player2, player3),
+            MAX_NR_OF_PLAYERS,
+            entryToken,
+            ENTRY_FEE,
+            DIE_FACES);
+    game = blockchain.deployZkContract(player1, MIA_CONTRACT, initRpc);
+
+    MiaGame.MiaState state =
+        MiaGame.ZkStateImmutable.deserialize(blockchain.getContractState(game)).openState();
+    Assertions.assertThat(state.entryToken()).isEqualTo(entryToken);
+    Assertions.assertThat(state.entryFee()).isEqualTo(ENTRY_FEE);
+    Assertions.assertThat(state.paidPlayers()).isEmpty();
+
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(player1, game, MiaGame.startRound()))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("All players must pay the entry fee before the game can start.");
+  }
+
+  /** A player whose entry fee transfer fails is not marked as paid, and the pot stays empty. */
+  @ContractTest(previous = "deployWithEntryFee")
+  void failedEntryFeeTransfer() {
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(player1, game, MiaGame.payEntryFee()))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Insufficient allowance");
+
+    MiaGame.MiaState state =
+        MiaGame.ZkStateImmutable.deserialize(blockchain.getContractState(game)).openState();
+    Assertions.assertThat(state.paidPlayers()).isEmpty();
+    Assertions.assertThat(state.pot()).isEqualTo(BigInteger.ZERO);
+    Assertions.assertThat(tokenBalance(player1)).isEqualTo(PLAYER_TOKENS);
+  }
+
+  /**
+   * Players pay the entry fee by approving the game to transfer it. The fees are moved to the game
+   * and added to the pot, and the game can start once all players have paid.
+   */
+  @ContractTest(previous = "deployWithEntryFee")
+  void allPlayersPayEntryFee() {
+    for (BlockchainAddress player : List.of(player1, player2, player3)) {
+      blockchain.sendAction(player, entryToken, Token.approve(game, ENTRY_FEE));
+      blockchain.sendAction(player, game, MiaGame.payEntryFee());
+    }
+
+    MiaGame.MiaState state =
+        MiaGame.ZkStateImmutable.deserialize(blockchain.getContractState(game)).openState();
+    Assertions.assertThat(state.paidPlayers()).containsExactlyInAnyOrder(player1, player2, player3);
+    Assertions.assertThat(state.pot()).isEqualTo(ENTRY_FEE.multiply(BigInteger.valueOf(3)));
+    Assertions.assertThat(tokenBalance(game)).isEqualTo(ENTRY_FEE.multiply(BigInteger.valueOf(3)));
+    for (BlockchainAddress player : List.of(player1, player2, player3)) {
+      Assertions.assertThat(tokenBalance(player)).isEqualTo(PLAYER_TOKENS.subtract(ENTRY_FEE));
+    }
+
+    blockchain.sendAction(player1, game, MiaGame.startRound());
+    assertCurrentGamePhase(MiaGame.GamePhaseD.ADD_RANDOMNESS);
+  }
+
+  /**
+   * The winner of a game with stakes receives the whole pot, including the stakes of the players
+   * who lost.
+   */
+  @ContractTest(previous = "allPlayersPayEntryFee")
+  void winnerClaimsWholePot() {
+    MiaGame.MiaState state = playDownToWinner();
+
+    BlockchainAddress winner = state.winner();
+    List<BlockchainAddress> losers =
+        Stream.of(player1, player2, player3).filter(player -> !player.equals(winner)).toList();
+    Assertions.assertThat(tokenBalance(winner)).isEqualTo(PLAYER_TOKENS.subtract(ENTRY_FEE));
+
+    blockchain.sendAction(winner, game, MiaGame.claimWinnings());
+
+    state = MiaGame.ZkStateImmutable.deserialize(blockchain.getContractState(game)).openState();
+    Assertions.assertThat(state.pot()).isEqualTo(BigInteger.ZERO);
+    Assertions.assertThat(tokenBalance(game)).isEqualTo(BigInteger.ZERO);
+    Assertions.assertThat(tokenBalance(winner))
+        .isEqualTo(PLAYER_TOKENS.add(ENTRY_FEE.multiply(BigInteger.TWO)));
+    for (BlockchainAddress loser : losers) {
+      Assertions.assertThat(tokenBalance(loser)).isEqualTo(PLAYER_TOKENS.subtract(ENTRY_FEE));
+    }
+  }
+
+  /**
+   * If the transfer of the winnings fails, the pot is restored, such that the winner can claim it
+   * again.
+   */
+  @ContractTest(previous = "allPlayersPayEntryFee")
+  void failedWinningsTransfer() {
+    BlockchainAddress winner = playDownToWinner().winner();
+    BigInteger pot = ENTRY_FEE.multiply(BigInteger.valueOf(3));
+
+    blockchain.sendAction(tokenIssuer, entryToken, Token.pause());
+    Assertions.assertThatThrownBy(
+            () -> blockchain.sendAction(winner, game, MiaGame.claimWinnings()))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("The token is paused");
+
+    Assertions.assertThat(getState().pot()).isEqualTo(pot);
+    Assertions.assertThat(tokenBalance(game)).isEqualTo(pot);
+
+    blockchain.sendAction(tokenIssuer, entryToken, Token.unpause());
+    blockchain.sendAction(winner, game, MiaGame.claimWinnings());
+
+    Assertions.assertThat(getState().pot()).isEqualTo(BigInteger.ZERO);
+    Assertions.assertThat(tokenBalance(winner))
+        .isEqualTo(PLAYER_TOKENS.add(ENTRY_FEE.multiply(BigInteger.TWO)));
+  }
+
+  /** The winnings can only be claimed once. */
+  @ContractTest(previous = "winnerClaimsWholePot")
+  void claimWinningsTwice() {
+    MiaGame.MiaState state =
+        MiaGame.ZkStateImmutable.deserialize(blockchain.getContractState(game)).openState();
+    BlockchainAddress winner = state.winner();
+    Assertions.assertThatThrownBy(
+            () -> blockchain.sendAction(winner, game, MiaGame.claimWinnings()))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("There are no winnings to claim.");
+  }
+
+  /** Only players in the game can pay the entry fee. */
+  @ContractTest(previous = "deployWithEntryFee")
+  void nonPlayerPaysEntryFee() {
+    BlockchainAddress outsider = blockchain.newAccount(9);
+
+    Assertions.assertThatThrownBy(
+            () -> blockchain.sendAction(outsider, game, MiaGame.payEntryFee()))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Only players in the game can pay the entry fee.");
+  }
+
+  /** Players who have already paid cannot pay the entry fee again. */
+  @ContractTest(previous = "allPlayersPayEntryFee")
+  void payEntryFeeTwice() {
+    blockchain.sendAction(player2, entryToken, Token.approve(game, ENTRY_FEE));
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(player2, game, MiaGame.payEntryFee()))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("The entry fee has already been paid.");
+    Assertions.assertThat(tokenBalance(player2)).isEqualTo(PLAYER_TOKENS.subtract(ENTRY_FEE));
+  }
+
+  /** No entry fee can be paid in a game without an entry fee. */
+  @ContractTest(previous = "deploy")
+  void payEntryFeeWithoutEntryFee() {
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(player2, game, MiaGame.payEntryFee()))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("No entry fee is required for this game.");
+  }
+
+  /** The entry fee cannot be paid once the game has started. */
+  @ContractTest(previous = "startTheGame")
+  void payEntryFeeAfterStart() {
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(player2, game, MiaGame.payEntryFee()))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("The entry fee can only be paid before the game has started.");
+  }
+
+  /** The winnings cannot be claimed before the game is done. */
+  @ContractTest(previous = "startTheGame")
+  void claimWinningsBeforeDone() {
+    Assertions.assertThatThrownBy(
+            () -> blockchain.sendAction(player1, game, MiaGame.claimWinnings()))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("The winnings can only be claimed when the game is done.");
+  }
+
+  /** Only the winner can claim the winnings. */
+  @ContractTest(previous = "playerWins")
+  void nonWinnerClaimsWinnings() {
+    Assertions.assertThatThrownBy(
+            () -> blockchain.sendAction(player1, game, MiaGame.claimWinnings()))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Only the winner can claim the winnings.");
+  }
+
+  /**
+   * The winner can restart a finished game, which seats all starting players with full lives, and
+   * the new game can be played.
+   */
+  @ContractTest(previous = "playerWins")
+  void restartFinishedGame() {
+    blockchain.sendAction(player3, game, MiaGame.restartGame());
+
+    MiaGame.MiaState state =
+        MiaGame.ZkStateImmutable.deserialize(blockchain.getContractState(game)).openState();
+    Assertions.assertThat(state.players()).containsExactly(player1, player2, player3);
+    Assertions.assertThat(state.winner()).isNull();
+    Assertions.assertThat(state.throwToBeat()).isNull();
+    Assertions.assertThat(state.statedThrow()).isNull();
+    Assertions.assertThat(state.throwResultId()).isNull();
+    assertCurrentGamePhase(MiaGame.GamePhaseD.START);
+    assertPlayerInTurn(player1);
+    assertPlayersNumberOfLivesLeft(player1, 6);
+    assertPlayersNumberOfLivesLeft(player2, 6);
+    assertPlayersNumberOfLivesLeft(player3, 6);
+    Assertions.assertThat(blockchain.getContractStateJson(game).getNode("/variables").size())
+        .isEqualTo(0);
+
+    blockchain.sendAction(player1, game, MiaGame.startRound());
+    specificThrow(4, 4);
+    callThrowDice(player1);
+    announceDiceValues(player1, 4, 4);
+    calloutPlayer(player2);
+
+    assertRevealedThrow(4, 4);
+    assertPlayersNumberOfLivesLeft(player2, 5);
+  }
+
+  /** Only the winner can restart a finished game. */
+  @ContractTest(previous = "playerWins")
+  void nonWinnerRestartsGame() {
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(player1, game, MiaGame.restartGame()))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Only the winner can restart the game.");
+  }
+
+  /** A game cannot be restarted before it is done. */
+  @ContractTest(previous = "startTheGame")
+  void restartUnfinishedGame() {
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(player1, game, MiaGame.restartGame()))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("The game can only be restarted when it is done.");
+  }
+
+  /**
+   * Any player can get the score of the special throws and of ordinary throws, without changing the
+   * state of the game.
+   */
+  @ContractTest(previous = "startTheGame")
+  void scoreOfThrows() {
+    assertScoreOf(MIA.diceThrow(), MIA.throwValue(), CATEGORY_MIA);
+    assertScoreOf(LITTLE_MIA.diceThrow(), LITTLE_MIA.throwValue(), CATEGORY_LITTLE_MIA);
+    assertScoreOf(PAIR_SIX.diceThrow(), PAIR_SIX.throwValue(), CATEGORY_PAIR);
+    assertScoreOf(PAIR_ONE.diceThrow(), PAIR_ONE.throwValue(), CATEGORY_PAIR);
+    assertScoreOf(SIXTY_FIVE.diceThrow(), SIXTY_FIVE.throwValue(), CATEGORY_ORDINARY);
+    assertScoreOf(FORTY_THREE.diceThrow(), FORTY_THREE.throwValue(), CATEGORY_ORDINARY);
+    assertScoreOf(THIRTY_TWO.diceThrow(), THIRTY_TWO.throwValue(), CATEGORY_ORDINARY);
+
+    assertCurrentGamePhase(MiaGame.GamePhaseD.ADD_RANDOMNESS);
+    assertPlayerInTurn(player1);
+    Assertions.assertThat(blockchain.getContractStateJson(game).getNode("/variables").size())
+        .isEqualTo(0);
+  }
+
+  /** Dice values outside the faces of the dice are reduced, like announced throws. */
+  @ContractTest(previous = "deploy")
+  void scoreOfUnreducedThrow() {
+    assertScoreOf(new MiaGame.DiceThrow((byte) 6, (byte) 7), MIA.throwValue(), CATEGORY_MIA);
+
+    assertCurrentGamePhase(MiaGame.GamePhaseD.START);
+  }
+
+  /** The winner of a game without stakes has nothing to claim. */
+  @ContractTest(previous = "playerWins")
+  void winnerClaimsEmptyPot() {
+    Assertions.assertThatThrownBy(
+            () -> blockchain.sendAction(player3, game, MiaGame.claimWinnings()))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("There are no winnings to claim.");
+  }
+
   /** The contract cannot be deployed with less than 3 players. */
   @ContractTest
   void deployNotEnoughPlayers() {
     player1 = blockchain.newAccount(1);
     player2 = blockchain.newAccount(2);
 
-    byte[] initRpc = MiaGame.initialize(List.of(player1, player2));
+    byte[] initRpc = initialize(List.of(player1, player2));
 
     Assertions.assertThatThrownBy(() -> blockchain.deployZkContract(player1, MIA_CONTRACT, initRpc))
         .isInstanceOf(ActionFailureException.class)
         .hasMessageContaining("There must be at least 3 players to play Mia.");
   }
 
+  /**
+   * The contract cannot be deployed with more players than the maximum number of players, even if
+   * there is only a single player too many.
+   */
+  @ContractTest
+  void deployTooManyPlayers() {
+    List<BlockchainAddress> allPlayers = new ArrayList<>();
+    for (int i = 1; i <= 8; i++) {
+      allPlayers.add(blockchain.newAccount(i));
+    }
+
+    byte[] initRpc = MiaGame.initialize(allPlayers, 7, ENTRY_TOKEN, NO_ENTRY_FEE, DIE_FACES);
+
+    Assertions.assertThatThrownBy(
+            () -> blockchain.deployZkContract(allPlayers.get(0), MIA_CONTRACT, initRpc))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("There can be at most 7 players in this game of Mia.");
+  }
+
   /** The same player cannot join the same game more than once. */
   @ContractTest
   void deploySamePlayerTwice() {
     player1 = blockchain.newAccount(1);
     player2 = blockchain.newAccount(2);
 
-    byte[] initRpc = MiaGame.initialize(List.of(player1, player2, player2));
+    byte[] initRpc = initialize(List.of(player1, player2, player2));
 
     Assertions.assertThatThrownBy(() -> blockchain.deployZkContract(player1, MIA_CONTRACT, initRpc))
         .isInstanceOf(ActionFailureException.class)
         .hasMessageContaining("No duplicates in players.");
   }
 
+  /**
+   * A game can be played with dice of eight faces, where throws with the faces 6 and 7 are valid
+   * and rank above the throws of standard dice.
+   */
+  @ContractTest
+  void playWithEightFacedDice() {
+    player1 = blockchain.newAccount(1);
+    player2 = blockchain.newAccount(2);
+    player3 = blockchain.newAccount(3);
+
+    blockchain.addRealv1MpcNodes();
+    game =
+        blockchain.deployZkContract(
+            player1, MIA_CONTRACT, initialize(List.of(player1, player2, player3), 8));
+
+    MiaGame.MiaState state =
+        MiaGame.ZkStateImmutable.deserialize(blockchain.getContractState(game)).openState();
+    Assertions.assertThat(state.dieFaces()).isEqualTo((byte) 8);
+
+    blockchain.sendAction(player1, game, MiaGame.startRound());
+    final int startingLives = getPlayerLives(player3);
+
+    specificThrow(5, 5);
+    callThrowDice(player1);
+    announceDiceValues(player1, 7, 6);
+    callBelieve(player2);
+
+    assertThrowToBeat(new MiaGame.DiceThrow((byte) 7, (byte) 6));
+
+    // The highest pair beats (7,6), and is only a valid throw with eight faces
+    specificThrow(7, 7);
+    callThrowDice(player2);
+    announceDiceValues(player2, 7, 7);
+    calloutPlayer(player3);
+
+    assertRevealedThrow(7, 7);
+    assertPlayersNumberOfLivesLeft(player3, startingLives - 1);
+  }
+
+  /**
+   * An announced throw with dice values outside the faces of the dice is reduced before it is
+   * compared. A stated throw reducing to Mia counts as Mia when called out, so calling out a
+   * truthful Mia costs two lives.
+   */
+  @ContractTest(previous = "startTheGame")
+  void callOutUnreducedMia() {
+    throwMia();
+    callThrowDice(player1);
+    announceDiceValues(player1, 7, 6);
+    calloutPlayer(player2);
+
+    assertRevealedThrow(1, 0);
+    assertPlayersNumberOfLivesLeft(player1, 6);
+    assertPlayersNumberOfLivesLeft(player2, 4);
+  }
+
+  /** A player lying about a stated throw reducing to Mia loses two lives when called out. */
+  @ContractTest(previous = "startTheGame")
+  void callOutLieAboutUnreducedMia() {
+    specificThrow(3, 3);
+    callThrowDice(player1);
+    announceDiceValues(player1, 7, 6);
+    calloutPlayer(player2);
+
+    assertRevealedThrow(3, 3);
+    assertPlayersNumberOfLivesLeft(player1, 4);
+    assertPlayersNumberOfLivesLeft(player2, 6);
+  }
+
+  /** The contract cannot be deployed with dice of an unsupported number of faces. */
+  @ContractTest
+  void deployWithUnsupportedDieFaces() {
+    player1 = blockchain.newAccount(1);
+    player2 = blockchain.newAccount(2);
+    player3 = blockchain.newAccount(3);
+
+    byte[] initRpc = initialize(List.of(player1, player2, player3), 9);
+
+    Assertions.assertThatThrownBy(() -> blockchain.deployZkContract(player1, MIA_CONTRACT, initRpc))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("The number of die faces must be between 4 and 8, but was 9.");
+  }
+
   /** A player who is not in turn cannot start a new round. */
   @ContractTest(previous = "deploy")
   void wrongPlayerStartsRound() {
@@ -448,6 +958,83 @@ public final class MiaGameTest extends JunitContractTest {
         .hasMessageContaining("Stated throw must be better than the last stated throw.");
   }
 
+  /** Restating the exact value of the throw to beat is not allowed. */
+  @ContractTest(previous = "startTheGame")
+  void statedThrowTiesThrowToBeat() {
+    specificThrow(4, 2);
+    callThrowDice(player1);
+    announceDiceValues(player1, 4, 2);
+    callBelieve(player2);
+
+    specificThrow(4, 2);
+    callThrowDice(player2);
+
+    Assertions.assertThatThrownBy(() -> announceDiceValues(player2, 2, 4))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Stated throw must be better than the last stated throw.");
+
+    announceDiceValues(player2, 4, 3);
+    assertCurrentGamePhase(MiaGame.GamePhaseD.DECIDE);
+  }
+
+  /** A revealed throw that exactly ties the stated throw was truthful, so the caller loses. */
+  @ContractTest(previous = "startTheGame")
+  void revealedThrowTiesStatedThrow() {
+    final int player1Lives = getPlayerLives(player1);
+    final int player2Lives = getPlayerLives(player2);
+
+    specificThrow(4, 2);
+    callThrowDice(player1);
+    announceDiceValues(player1, 2, 4);
+    calloutPlayer(player2);
+
+    assertRevealedThrow(4, 2);
+    assertPlayersNumberOfLivesLeft(player1, player1Lives);
+    assertPlayersNumberOfLivesLeft(player2, player2Lives - 1);
+    assertThrowToBeat(null);
+  }
+
+  /** A call out ends the round, such that the next round can start with any throw. */
+  @ContractTest(previous = "startTheGame")
+  void throwToBeatResetsAfterReveal() {
+    specificThrow(5, 5);
+    callThrowDice(player1);
+    announceDiceValues(player1, 5, 5);
+    callBelieve(player2);
+
+    specificThrow(5, 5);
+    callThrowDice(player2);
+    announceDiceValues(player2, 0, 2);
+    calloutPlayer(player3);
+
+    assertPlayerInTurn(player3);
+    specificThrow(1, 2);
+    callThrowDice(player3);
+    announceDiceValues(player3, 1, 2);
+    assertCurrentGamePhase(MiaGame.GamePhaseD.DECIDE);
+  }
+
+  /** Believing a Mia means giving up, so the believing player loses one life. */
+  @ContractTest(previous = "startTheGame")
+  void believingMiaEndsRound() {
+    final int player2Lives = getPlayerLives(player2);
+
+    throwMia();
+    callThrowDice(player1);
+    announceDiceValues(player1, 0, 1);
+    callBelieve(player2);
+
+    assertPlayersNumberOfLivesLeft(player2, player2Lives - 1);
+    assertPlayerInTurn(player2);
+    assertCurrentGamePhase(MiaGame.GamePhaseD.ADD_RANDOMNESS);
+    assertThrowToBeat(null);
+
+    specificThrow(1, 2);
+    callThrowDice(player2);
+    announceDiceValues(player2, 1, 2);
+    assertCurrentGamePhase(MiaGame.GamePhaseD.DECIDE);
+  }
+
   /** A player who is not next in turn cannot believe the current player's throw. */
   @ContractTest(previous = "startTheGame")
   void wrongPlayerBelievesThrow() {
@@ -495,7 +1082,10 @@ public final class MiaGameTest extends JunitContractTest {
         .hasMessageContaining("Must be in the deciding phase say if the throwing player is lying.");
   }
 
-  /** A higher dice throw wins against a lower or equal dice throw. */
+  /**
+   * A higher dice throw wins against a lower or equal dice throw. Believing Mia ends the round, so
+   * any throw can be announced afterwards.
+   */
   @Previous(value = "startTheGame")
   @ParameterizedTest
   @MethodSource("providePairThrow")
@@ -511,8 +1101,8 @@ public final class MiaGameTest extends JunitContractTest {
     callThrowDice(player2);
     byte[] announceThrow = MiaGame.announceThrow(nextThrow.diceThrow());
 
-    // An exception is thrown if the next throw isn't better or equal to the throw to beat
-    if (throwToBeat.throwValue() > nextThrow.throwValue()) {
+    // An exception is thrown if the next throw isn't strictly better than the throw to beat
+    if (!throwToBeat.equals(MIA) && throwToBeat.throwValue() >= nextThrow.throwValue()) {
       Assertions.assertThatThrownBy(() -> blockchain.sendAction(player2, game, announceThrow))
           .isInstanceOf(ActionFailureException.class)
           .hasMessageContaining("Stated throw must be better than the last stated throw.");
@@ -533,6 +1123,19 @@ public final class MiaGameTest extends JunitContractTest {
     return result;
   }
 
+  private void assertScoreOf(MiaGame.DiceThrow diceThrow, int score, int category) {
+    SafeDataInputStream throwScore =
+        ReturnData.of(blockchain.sendAction(player2, game, MiaGame.scoreOf(diceThrow)));
+    Assertions.assertThat(throwScore.readShort()).isEqualTo((short) score);
+    Assertions.assertThat(throwScore.readByte()).isEqualTo((byte) category);
+  }
+
+  private BigInteger tokenBalance(BlockchainAddress holder) {
+    Token.TokenState state = new Token(getStateClient(), entryToken).getState();
+    BigInteger balance = state.balances().get(holder);
+    return balance == null ? BigInteger.ZERO : balance;
+  }
+
   private void assertRevealedThrow(int d1, int d2) {
     MiaGame.MiaState state =
         MiaGame.ZkStateImmutable.deserialize(blockchain.getContractState(game)).openState();
@@ -540,6 +1143,15 @@ public final class MiaGameTest extends JunitContractTest {
         .isEqualTo(new MiaGame.DiceThrow((byte) d1, (byte) d2));
   }
 
+  private static byte[] initialize(List<BlockchainAddress> players) {
+    return initialize(players, DIE_FACES);
+  }
+
+  private static byte[] initialize(List<BlockchainAddress> players, int dieFaces) {
+    return MiaGame.initialize(
+        players, MAX_NR_OF_PLAYERS, ENTRY_TOKEN, NO_ENTRY_FEE, (byte) dieFaces);
+  }
+
   private void calloutPlayer(BlockchainAddress sender) {
     blockchain.sendAction(sender, game, MiaGame.callOut());
   }
@@ -555,6 +1167,13 @@ public final class MiaGameTest extends JunitContractTest {
     announceDiceValues(player, d1, d2);
   }
 
+  private void callOutNextPlayer() {
+    MiaGame.MiaState state =
+        MiaGame.ZkStateImmutable.deserialize(blockchain.getContractState(game)).openState();
+    int nextPlayer = (state.playerThrowing() + 1) % state.players().size();
+    calloutPlayer(state.players().get(nextPlayer));
+  }
+
   private void callThrowDice(BlockchainAddress sender) {
     byte[] throwRpc = MiaGame.throwDice();
     blockchain.sendAction(sender, game, throwRpc);
@@ -620,15 +1239,39 @@ public final class MiaGameTest extends JunitContractTest {
   }
 
   void throwMia() {
-    addRandomness(player1, 0, 0);
-    addRandomness(player2, 1, 0);
-    addRandomness(player3, 0, 0);
+    specificThrow(1, 0);
   }
 
   void specificThrow(int d1, int d2) {
-    addRandomness(player1, d1, d2);
-    addRandomness(player2, 0, 0);
-    addRandomness(player3, 0, 0);
+    List<BlockchainAddress> contributors = remainingPlayers();
+    addRandomness(contributors.get(0), d1, d2);
+    for (BlockchainAddress contributor : contributors.subList(1, contributors.size())) {
+      addRandomness(contributor, 0, 0);
+    }
+  }
+
+  /** Plays the started game until a winner is found, and returns the state of the finished game. */
+  private MiaGame.MiaState playDownToWinner() {
+    MiaGame.MiaState state = getState();
+    while (state.gamePhase().discriminant() != MiaGame.GamePhaseD.DONE) {
+      BlockchainAddress thrower = state.players().get(state.playerThrowing());
+      throwMia();
+      callThrowDice(thrower);
+      announceDiceValues(thrower, 0, 1);
+      callOutNextPlayer();
+      state = getState();
+    }
+    return state;
+  }
+
+  private MiaGame.MiaState getState() {
+    return MiaGame.ZkStateImmutable.deserialize(blockchain.getContractState(game)).openState();
+  }
+
+  private List<BlockchainAddress> remainingPlayers() {
+    MiaGame.MiaState state =
+        MiaGame.ZkStateImmutable.deserialize(blockchain.getContractState(game)).openState();
+    return state.players();
   }
 
   private void specificMiaDiceThrow(MiaGame.DiceThrow diceThrow) {
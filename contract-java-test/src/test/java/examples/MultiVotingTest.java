@@ -2,11 +2,14 @@ package examples;
 
 import com.partisiablockchain.BlockchainAddress;
 import com.partisiablockchain.language.abicodegen.MultiVotingContract;
+import com.partisiablockchain.language.abicodegen.Token;
 import com.partisiablockchain.language.abicodegen.Voting;
 import com.partisiablockchain.language.junit.ContractBytes;
 import com.partisiablockchain.language.junit.ContractTest;
 import com.partisiablockchain.language.junit.JunitContractTest;
 import com.partisiablockchain.language.junit.exceptions.ActionFailureException;
+import java.math.BigInteger;
+import java.util.List;
 import org.assertj.core.api.Assertions;
 
 /** This class contains a test suite for the multi-voting smart contract. */
@@ -17,27 +20,52 @@ public final class MultiVotingTest extends JunitContractTest {
   private static final ContractBytes VOTING_CONTRACT_BYTES =
       ContractBytesLoader.forContract("voting");
 
+  private static final ContractBytes TOKEN_CONTRACT_BYTES =
+      ContractBytesLoader.forContract("token");
+
   private BlockchainAddress multiVotingOwner;
   private BlockchainAddress multiVoting;
   private BlockchainAddress voter1;
   private BlockchainAddress voter2;
+  private BlockchainAddress depositToken;
+
+  /** Deposit required for voters to create proposals. */
+  private static final BigInteger PROPOSAL_DEPOSIT = BigInteger.valueOf(100);
+
+  /** Deposit tokens initially held by each voter. */
+  private static final BigInteger VOTER_TOKENS = BigInteger.valueOf(1000);
 
   /**
    * Root test that provides setup for all other tests. Instantiates the owner of the multi voting
-   * contract, deploys the multi voting contract and instantiates two voters.
+   * contract, deploys the deposit token and the multi voting contract, and instantiates two voters
+   * holding deposit tokens.
    */
   @ContractTest
   public void setup() {
     multiVotingOwner = blockchain.newAccount(1);
+    voter1 = blockchain.newAccount(2);
+    voter2 = blockchain.newAccount(3);
+
+    BlockchainAddress tokenIssuer = blockchain.newAccount(4);
+    depositToken =
+        blockchain.deployContract(
+            tokenIssuer,
+            TOKEN_CONTRACT_BYTES,
+            Token.initialize(
+                "Deposit", "DEP", (byte) 0, VOTER_TOKENS.multiply(BigInteger.TWO), List.of()));
+    blockchain.sendAction(tokenIssuer, depositToken, Token.transfer(voter1, VOTER_TOKENS));
+    blockchain.sendAction(tokenIssuer, depositToken, Token.transfer(voter2, VOTER_TOKENS));
+
     byte[] multiVotingInitRpc =
         MultiVotingContract.initialize(
-            VOTING_CONTRACT_BYTES.code(), VOTING_CONTRACT_BYTES.abi(), 1);
+            VOTING_CONTRACT_BYTES.code(),
+            VOTING_CONTRACT_BYTES.abi(),
+            1,
+            depositToken,
+            PROPOSAL_DEPOSIT);
     multiVoting =
         blockchain.deployContract(
             multiVotingOwner, MULTI_VOTING_CONTRACT_BYTES, multiVotingInitRpc);
-
-    voter1 = blockchain.newAccount(2);
-    voter2 = blockchain.newAccount(3);
   }
 
   // Feature: Deploy Voting Contract
@@ -94,6 +122,46 @@ public final class MultiVotingTest extends JunitContractTest {
         .hasMessageContaining("Voters are required");
   }
 
+  /**
+   * If the deployment of a voting contract fails, the proposal is recorded as failed, and the owner
+   * can retry the deployment once the cause of the failure has been fixed.
+   */
+  @ContractTest(previous = "deployVotingContractNoVoters")
+  public void retryFailedVotingContract() {
+    MultiVotingContract.MultiVotingState state =
+        MultiVotingContract.MultiVotingState.deserialize(blockchain.getContractState(multiVoting));
+    Assertions.assertThat(state.votingContracts().containsKey(10L)).isFalse();
+    Assertions.assertThat(state.failedProposals()).containsExactly(10L);
+
+    blockchain.sendAction(multiVotingOwner, multiVoting, MultiVotingContract.addVoter(voter1));
+    byte[] retryRpc = MultiVotingContract.retryVotingContract(10, 60 * 60 * 1000);
+    blockchain.sendAction(multiVotingOwner, multiVoting, retryRpc);
+
+    state =
+        MultiVotingContract.MultiVotingState.deserialize(blockchain.getContractState(multiVoting));
+    Assertions.assertThat(state.votingContracts().get(10L)).isNotNull();
+    Assertions.assertThat(state.failedProposals()).isEmpty();
+  }
+
+  /** Only the owner can retry the deployment of a failed voting contract. */
+  @ContractTest(previous = "deployVotingContractNoVoters")
+  public void nonOwnerRetryFailedVotingContract() {
+    byte[] retryRpc = MultiVotingContract.retryVotingContract(10, 60 * 60 * 1000);
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(voter1, multiVoting, retryRpc))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Only owner can retry contracts");
+  }
+
+  /** Only proposals whose deployment failed can be retried. */
+  @ContractTest(previous = "deployVotingContract")
+  public void retryVotingContractWithoutFailure() {
+    byte[] retryRpc = MultiVotingContract.retryVotingContract(10, 60 * 60 * 1000);
+    Assertions.assertThatThrownBy(
+            () -> blockchain.sendAction(multiVotingOwner, multiVoting, retryRpc))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("No failed deployment for proposal id");
+  }
+
   /** The multi-voting contract cannot deploy a voting contract with insufficient gas. */
   @ContractTest(previous = "setup")
   public void deployVotingContractNotEnoughGas() {
@@ -108,7 +176,8 @@ public final class MultiVotingTest extends JunitContractTest {
   @ContractTest(previous = "setup")
   public void deployVotingContractIncorrectWasm() {
     byte[] multiVotingInitRpc =
-        MultiVotingContract.initialize(new byte[] {}, VOTING_CONTRACT_BYTES.abi(), 1);
+        MultiVotingContract.initialize(
+            new byte[] {}, VOTING_CONTRACT_BYTES.abi(), 1, depositToken, PROPOSAL_DEPOSIT);
     BlockchainAddress multiVotingContract =
         blockchain.deployContract(
             multiVotingOwner, MULTI_VOTING_CONTRACT_BYTES, multiVotingInitRpc);
@@ -125,7 +194,11 @@ public final class MultiVotingTest extends JunitContractTest {
   public void deployVotingContractIncorrectBinderId() {
     byte[] multiVotingInitRpc =
         MultiVotingContract.initialize(
-            VOTING_CONTRACT_BYTES.code(), VOTING_CONTRACT_BYTES.abi(), 42);
+            VOTING_CONTRACT_BYTES.code(),
+            VOTING_CONTRACT_BYTES.abi(),
+            42,
+            depositToken,
+            PROPOSAL_DEPOSIT);
     BlockchainAddress multiVotingContract =
         blockchain.deployContract(
             multiVotingOwner, MULTI_VOTING_CONTRACT_BYTES, multiVotingInitRpc);
@@ -137,6 +210,153 @@ public final class MultiVotingTest extends JunitContractTest {
         .hasMessageContaining("PublicDeployContractState.getBinderInfo(int)\" is null");
   }
 
+  // Feature: Propose Voting Contract
+
+  /** The deposit configuration is stored in the state when the contract is deployed. */
+  @ContractTest(previous = "setup")
+  public void depositConfigurationIsStored() {
+    MultiVotingContract.MultiVotingState state =
+        MultiVotingContract.MultiVotingState.deserialize(blockchain.getContractState(multiVoting));
+    Assertions.assertThat(state.depositToken()).isEqualTo(depositToken);
+    Assertions.assertThat(state.proposalDeposit()).isEqualTo(PROPOSAL_DEPOSIT);
+    Assertions.assertThat(state.proposalDeposits()).isEmpty();
+  }
+
+  /**
+   * An eligible voter can create a proposal by paying a deposit. The deposit is transferred to the
+   * multi-voting contract and recorded, and the voting contract is deployed.
+   */
+  @ContractTest(previous = "addVoterToVotingContract")
+  public void voterProposalWithDeposit() {
+    blockchain.sendAction(voter1, depositToken, Token.approve(multiVoting, PROPOSAL_DEPOSIT));
+    byte[] proposeRpc = MultiVotingContract.proposeVotingContract(20, 60 * 60 * 1000);
+    blockchain.sendAction(voter1, multiVoting, proposeRpc);
+
+    MultiVotingContract.MultiVotingState state =
+        MultiVotingContract.MultiVotingState.deserialize(blockchain.getContractState(multiVoting));
+    Assertions.assertThat(state.votingContracts().get(20L)).isNotNull();
+    MultiVotingContract.ProposalDeposit deposit = state.proposalDeposits().get(20L);
+    Assertions.assertThat(deposit.proposer()).isEqualTo(voter1);
+    Assertions.assertThat(deposit.amount()).isEqualTo(PROPOSAL_DEPOSIT);
+
+    Assertions.assertThat(tokenBalance(voter1)).isEqualTo(VOTER_TOKENS.subtract(PROPOSAL_DEPOSIT));
+    Assertions.assertThat(tokenBalance(multiVoting)).isEqualTo(PROPOSAL_DEPOSIT);
+  }
+
+  /**
+   * An eligible voter can create a proposal by paying a deposit. If the deposit cannot be
+   * transferred, the proposal id is released again and no deposit is recorded.
+   */
+  @ContractTest(previous = "addVoterToVotingContract")
+  public void voterProposalWithoutDepositIsReleased() {
+    byte[] proposeRpc = MultiVotingContract.proposeVotingContract(20, 60 * 60 * 1000);
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(voter1, multiVoting, proposeRpc))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Insufficient allowance");
+
+    MultiVotingContract.MultiVotingState state =
+        MultiVotingContract.MultiVotingState.deserialize(blockchain.getContractState(multiVoting));
+    Assertions.assertThat(state.votingContracts().containsKey(20L)).isFalse();
+    Assertions.assertThat(state.proposalDeposits()).isEmpty();
+    Assertions.assertThat(tokenBalance(voter1)).isEqualTo(VOTER_TOKENS);
+  }
+
+  /** Users who are not eligible voters cannot create proposals. */
+  @ContractTest(previous = "setup")
+  public void nonVoterCannotPropose() {
+    byte[] proposeRpc = MultiVotingContract.proposeVotingContract(20, 60 * 60 * 1000);
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(voter1, multiVoting, proposeRpc))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Only eligible voters can propose contracts");
+  }
+
+  /** Voters cannot create a proposal with an id already in use. */
+  @ContractTest(previous = "deployVotingContract")
+  public void voterProposalIdAlreadyExists() {
+    byte[] proposeRpc = MultiVotingContract.proposeVotingContract(10, 60 * 60 * 1000);
+    Assertions.assertThatThrownBy(
+            () -> blockchain.sendAction(multiVotingOwner, multiVoting, proposeRpc))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Proposal id already exists");
+  }
+
+  // Feature: Settle Proposal Deposit
+
+  /**
+   * Once the votes have been counted, anyone can settle the deposit of a proposal. The deposit is
+   * refunded to the proposer if enough of the eligible voters voted for the proposal.
+   */
+  @ContractTest(previous = "voterProposalWithDeposit")
+  public void settleDepositOfSupportedProposal() {
+    BlockchainAddress voting = votingContractOf(20L);
+    blockchain.sendAction(voter1, voting, Voting.vote(true));
+    blockchain.sendAction(multiVotingOwner, voting, Voting.vote(false));
+    blockchain.waitForBlockProductionTime(2 * 60 * 60 * 1000);
+    blockchain.sendAction(voter1, voting, Voting.count());
+
+    blockchain.sendAction(voter2, multiVoting, MultiVotingContract.settleProposalDeposit(20));
+
+    MultiVotingContract.MultiVotingState state =
+        MultiVotingContract.MultiVotingState.deserialize(blockchain.getContractState(multiVoting));
+    Assertions.assertThat(state.proposalDeposits()).isEmpty();
+    Assertions.assertThat(tokenBalance(voter1)).isEqualTo(VOTER_TOKENS);
+    Assertions.assertThat(tokenBalance(multiVoting)).isEqualTo(BigInteger.ZERO);
+  }
+
+  /**
+   * If too few of the eligible voters voted for a proposal, it is considered spam, and the deposit
+   * is slashed by transferring it to the owner.
+   */
+  @ContractTest(previous = "voterProposalWithDeposit")
+  public void slashDepositOfSpamProposal() {
+    BlockchainAddress voting = votingContractOf(20L);
+    blockchain.sendAction(multiVotingOwner, voting, Voting.vote(false));
+    blockchain.waitForBlockProductionTime(2 * 60 * 60 * 1000);
+    blockchain.sendAction(voter1, voting, Voting.count());
+
+    blockchain.sendAction(voter1, multiVoting, MultiVotingContract.settleProposalDeposit(20));
+
+    MultiVotingContract.MultiVotingState state =
+        MultiVotingContract.MultiVotingState.deserialize(blockchain.getContractState(multiVoting));
+    Assertions.assertThat(state.proposalDeposits()).isEmpty();
+    Assertions.assertThat(tokenBalance(voter1)).isEqualTo(VOTER_TOKENS.subtract(PROPOSAL_DEPOSIT));
+    Assertions.assertThat(tokenBalance(multiVotingOwner)).isEqualTo(PROPOSAL_DEPOSIT);
+    Assertions.assertThat(tokenBalance(multiVoting)).isEqualTo(BigInteger.ZERO);
+  }
+
+  /** Deposits cannot be settled before the votes on the proposal have been counted. */
+  @ContractTest(previous = "voterProposalWithDeposit")
+  public void settleDepositBeforeVotesAreCounted() {
+    byte[] settleRpc = MultiVotingContract.settleProposalDeposit(20);
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(voter1, multiVoting, settleRpc))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("The votes have not been counted yet");
+
+    MultiVotingContract.MultiVotingState state =
+        MultiVotingContract.MultiVotingState.deserialize(blockchain.getContractState(multiVoting));
+    Assertions.assertThat(state.proposalDeposits().get(20L)).isNotNull();
+    Assertions.assertThat(tokenBalance(multiVoting)).isEqualTo(PROPOSAL_DEPOSIT);
+  }
+
+  /** A deposit can only be settled once. */
+  @ContractTest(previous = "settleDepositOfSupportedProposal")
+  public void settleDepositTwice() {
+    byte[] settleRpc = MultiVotingContract.settleProposalDeposit(20);
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(voter1, multiVoting, settleRpc))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("No deposit for proposal");
+  }
+
+  /** Proposals created by the owner do not hold a deposit which can be settled. */
+  @ContractTest(previous = "deployVotingContract")
+  public void settleProposalWithoutDeposit() {
+    byte[] settleRpc = MultiVotingContract.settleProposalDeposit(10);
+    Assertions.assertThatThrownBy(
+            () -> blockchain.sendAction(multiVotingOwner, multiVoting, settleRpc))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("No deposit for proposal");
+  }
+
   // Feature: Add Voter
 
   /** The multi-voting contract can add users as registered voters. */
@@ -241,4 +461,16 @@ public final class MultiVotingTest extends JunitContractTest {
         .isInstanceOf(ActionFailureException.class)
         .hasMessageContaining("Only owner can remove voters");
   }
+
+  private BlockchainAddress votingContractOf(long proposalId) {
+    MultiVotingContract.MultiVotingState state =
+        MultiVotingContract.MultiVotingState.deserialize(blockchain.getContractState(multiVoting));
+    return state.votingContracts().get(proposalId);
+  }
+
+  private BigInteger tokenBalance(BlockchainAddress holder) {
+    Token.TokenState state = new Token(getStateClient(), depositToken).getState();
+    BigInteger balance = state.balances().get(holder);
+    return balance == null ? BigInteger.ZERO : balance;
+  }
 }
@@ -1,6 +1,7 @@
 package examples;
 
 import com.partisiablockchain.BlockchainAddress;
+import com.partisiablockchain.crypto.Hash;
 import com.partisiablockchain.crypto.KeyPair;
 import com.partisiablockchain.language.abicodegen.OffChainPublishRandomness;
 import com.partisiablockchain.language.junit.ContractBytes;
@@ -9,7 +10,10 @@ import com.partisiablockchain.language.junit.JunitContractTest;
 import com.partisiablockchain.language.junit.TestBlockchain;
 import com.partisiablockchain.language.testenvironment.TxExecution;
 import com.partisiablockchain.language.testenvironment.executionengine.TestExecutionEngine;
+import com.secata.stream.SafeDataInputStream;
 import java.math.BigInteger;
+import java.nio.charset.StandardCharsets;
+import java.util.ArrayList;
 import java.util.List;
 import java.util.stream.IntStream;
 import org.assertj.core.api.Assertions;
@@ -37,6 +41,9 @@ public final class OffChainPublishRandomnessTest extends JunitContractTest {
   private BlockchainAddress contractAddress;
   private OffChainPublishRandomness contract;
 
+  /** Time in milliseconds before the owner can reset a stuck round. */
+  private static final long RESET_TIMEOUT = 60 * 60 * 1000;
+
   private static final List<String> INITIAL_RANDOM_DATA_SHARES =
       List.of(
           "fba7bc34d30643feaba539cbae8734959262bf29c69a5b9d233faf27b01b8ea1",
@@ -44,6 +51,13 @@ public final class OffChainPublishRandomnessTest extends JunitContractTest {
           "1122eedd2bf519649f9f3ba741f6e2203760389912d9ec87f3ff226abb59cdc5",
           "06655509ff7d2e31a5a23a39f7be0bdb64e17b61ec39a4128b1fe9c8b6baadb3");
 
+  private static final List<String> SECOND_RANDOM_DATA_SHARES =
+      List.of(
+          "cec5d7d0df156f99614ce9ad83f4f5a97d6bf40fb19d994bed6da12e40188bc2",
+          "99c1fc8ffec77c121b14cba0b4392080995241cc158fbc83a41c09fbf144d1f0",
+          "aedc2821c0714ae132dd1d293a8211e7a53462290cc1d5a3e576f1d5c6bdccc4",
+          "d359c9111c92bad3331efd2321c1c3c5f2779d90cd99d062e9bf9cb7f3d2ca0e");
+
   /**
    * Publish-randomness contract can be deployed. Commit task is automatically created on
    * deployment.
@@ -74,13 +88,53 @@ public final class OffChainPublishRandomnessTest extends JunitContractTest {
   void randomnessConsumedWhichWillTriggerNewRandomness() {
     blockchain.sendAction(sender, contractAddress, OffChainPublishRandomness.consumeRandomness());
 
-    assertCommitAndUploadPerformed(
-        2,
-        List.of(
-            "cec5d7d0df156f99614ce9ad83f4f5a97d6bf40fb19d994bed6da12e40188bc2",
-            "99c1fc8ffec77c121b14cba0b4392080995241cc158fbc83a41c09fbf144d1f0",
-            "aedc2821c0714ae132dd1d293a8211e7a53462290cc1d5a3e576f1d5c6bdccc4",
-            "d359c9111c92bad3331efd2321c1c3c5f2779d90cd99d062e9bf9cb7f3d2ca0e"));
+    assertCommitAndUploadPerformed(2, SECOND_RANDOM_DATA_SHARES);
+  }
+
+  /**
+   * Randomness can be consumed for a specific domain, which will also trigger creation of new
+   * randomness.
+   */
+  @ContractTest(previous = "enginesSendRandomShares")
+  void randomnessConsumedForDomainWhichWillTriggerNewRandomness() {
+    final byte[] domain = "consumer".getBytes(StandardCharsets.UTF_8);
+    final Hash derived = consumeRandomnessForDomain(domain);
+
+    final byte[] randomness = combineShares(INITIAL_RANDOM_DATA_SHARES);
+    Assertions.assertThat(derived).isEqualTo(deriveForDomain(randomness, domain));
+
+    assertCommitAndUploadPerformed(2, SECOND_RANDOM_DATA_SHARES);
+  }
+
+  /** The randomness derived for different domains differs, even from the same randomness. */
+  @ContractTest(previous = "enginesSendRandomShares")
+  void randomnessForDifferentDomainsDiffers() {
+    final byte[] firstDomain = "consumer".getBytes(StandardCharsets.UTF_8);
+    final byte[] secondDomain = "other".getBytes(StandardCharsets.UTF_8);
+    final byte[] randomness = combineShares(INITIAL_RANDOM_DATA_SHARES);
+
+    final Hash derived = consumeRandomnessForDomain(firstDomain);
+    Assertions.assertThat(derived)
+        .isEqualTo(deriveForDomain(randomness, firstDomain))
+        .isNotEqualTo(deriveForDomain(randomness, secondDomain));
+
+    assertCommitAndUploadPerformed(2, SECOND_RANDOM_DATA_SHARES);
+    final Hash nextDerived = consumeRandomnessForDomain(secondDomain);
+    Assertions.assertThat(nextDerived)
+        .isEqualTo(deriveForDomain(combineShares(SECOND_RANDOM_DATA_SHARES), secondDomain))
+        .isNotEqualTo(derived);
+  }
+
+  /** Cannot consume randomness for a domain if nothing is available. */
+  @ContractTest(previous = "setup")
+  void cannotConsumeRandomnessForDomainIfNothingAvailable() {
+    Assertions.assertThatCode(
+            () ->
+                blockchain.sendAction(
+                    sender,
+                    contractAddress,
+                    OffChainPublishRandomness.consumeRandomnessForDomain(new byte[] {1, 2})))
+        .hasMessageContaining("No randomness available!");
   }
 
   /** Contract will maintain exactly one piece of randomness. */
@@ -143,6 +197,270 @@ public final class OffChainPublishRandomnessTest extends JunitContractTest {
     assertNoUploadTasks();
   }
 
+  /**
+   * An engine whose commitment refers to randomness it does not have replaces the commitment with
+   * a commitment to new randomness, such that randomness is still produced once the remaining
+   * engines commit.
+   */
+  @ContractTest(previous = "contractWaitsForAllEnginesToUploadCommit")
+  void engineCanRecommitBeforeUpload() {
+    Hash unknownCommitment = Hash.create(s -> s.writeString("new randomness"));
+    blockchain.sendAction(
+        engineConfigs.get(0).address(),
+        contractAddress,
+        OffChainPublishRandomness.recommitToRandomness(1, unknownCommitment));
+
+    OffChainPublishRandomness.ContractState state = contract.getState();
+    final Hash recommitment = state.commitQueue().tasks().get(1).completionData().get(0);
+    Assertions.assertThat(recommitment).isNotEqualTo(unknownCommitment);
+    Assertions.assertThat(recommitment.toString())
+        .isNotEqualTo("f432ec4869998609e50c1effd8421b2440dff4ac2b8fe34acd3c78f001fca40b");
+    Assertions.assertThat(state.commitQueue().tasks().get(1).completionData().get(3)).isNull();
+    assertNoUploadTasks();
+
+    blockchain.addExecutionEngine(contractAddress::equals, ENGINE_KEYS.get(3));
+    pingContract();
+
+    state = contract.getState();
+    final List<byte[]> shares = state.uploadQueue().tasks().get(1).completionData();
+    Assertions.assertThat(state.uploadQueue().tasks().get(1).definition().commitments().get(0))
+        .isEqualTo(recommitment);
+    Assertions.assertThat(shares).hasSize(4).doesNotContainNull();
+    Assertions.assertThat(Hash.create(stream -> stream.write(shares.get(0))))
+        .isEqualTo(recommitment);
+
+    blockchain.sendAction(sender, contractAddress, OffChainPublishRandomness.consumeRandomness());
+    Assertions.assertThat(contract.getState().uploadQueue().tasks().get(1)).isNull();
+  }
+
+  /** An engine cannot use re-commit for a task it has not yet committed to. */
+  @ContractTest(previous = "contractWaitsForAllEnginesToUploadCommit")
+  void engineCannotRecommitWithoutCommitment() {
+    Hash newCommitment = Hash.create(s -> s.writeString("new randomness"));
+    Assertions.assertThatCode(
+            () ->
+                blockchain.sendAction(
+                    engineConfigs.get(3).address(),
+                    contractAddress,
+                    OffChainPublishRandomness.recommitToRandomness(1, newCommitment)))
+        .hasMessageContaining("Caller has not yet committed to the task");
+  }
+
+  /** Commitments cannot be changed once the upload task has been created. */
+  @ContractTest(previous = "enginesSendRandomShares")
+  void engineCannotRecommitAfterUploadTaskCreated() {
+    Hash newCommitment = Hash.create(s -> s.writeString("new randomness"));
+    Assertions.assertThatCode(
+            () ->
+                blockchain.sendAction(
+                    engineConfigs.get(0).address(),
+                    contractAddress,
+                    OffChainPublishRandomness.recommitToRandomness(1, newCommitment)))
+        .hasMessageContaining(
+            "No such commit task, or the commitments are already being uploaded");
+
+    assertCommitAndUploadPerformed(1, INITIAL_RANDOM_DATA_SHARES);
+  }
+
+  /** The owner can reset a round that is stuck, once the timeout has passed. */
+  @ContractTest(previous = "contractWaitsForAllEnginesToUploadCommit")
+  void ownerCanResetStuckRound() {
+    blockchain.waitForBlockProductionTime(blockchain.getBlockProductionTime() + RESET_TIMEOUT);
+    blockchain.sendAction(sender, contractAddress, OffChainPublishRandomness.resetRound());
+
+    final OffChainPublishRandomness.ContractState state = contract.getState();
+    Assertions.assertThat(state.commitQueue().taskIdOfLastCreated()).isEqualTo(2);
+    Assertions.assertThat(state.commitQueue().taskIdOfCurrent()).isEqualTo(2);
+    Assertions.assertThat(state.commitQueue().tasks().size()).isEqualTo(1);
+    Assertions.assertThat(state.commitQueue().tasks().get(1)).isNull();
+    assertNoUploadTasks();
+  }
+
+  /** Randomness generation continues after a reset, once all engines respond again. */
+  @ContractTest(previous = "ownerCanResetStuckRound")
+  void randomnessIsGeneratedAfterReset() {
+    blockchain.addExecutionEngine(contractAddress::equals, ENGINE_KEYS.get(3));
+    pingContract();
+
+    final OffChainPublishRandomness.ContractState state = contract.getState();
+    Assertions.assertThat(state.commitQueue().tasks().size()).isEqualTo(0);
+    Assertions.assertThat(state.uploadQueue().tasks().get(1).completionData())
+        .hasSize(4)
+        .doesNotContainNull();
+    Assertions.assertThat(state.uploadQueue().tasks().get(1).definition().commitments())
+        .hasSize(4);
+  }
+
+  /** A stuck round cannot be reset before the timeout has passed. */
+  @ContractTest(previous = "contractWaitsForAllEnginesToUploadCommit")
+  void cannotResetRoundBeforeTimeout() {
+    Assertions.assertThatCode(
+            () ->
+                blockchain.sendAction(
+                    sender, contractAddress, OffChainPublishRandomness.resetRound()))
+        .hasMessageContaining("Cannot reset the round before the timeout has passed");
+    assertPartialCommitments();
+  }
+
+  /** Only the owner can reset a stuck round. */
+  @ContractTest(previous = "contractWaitsForAllEnginesToUploadCommit")
+  void onlyOwnerCanResetRound() {
+    blockchain.waitForBlockProductionTime(blockchain.getBlockProductionTime() + RESET_TIMEOUT);
+    Assertions.assertThatCode(
+            () ->
+                blockchain.sendAction(
+                    engineConfigs.get(0).address(),
+                    contractAddress,
+                    OffChainPublishRandomness.resetRound()))
+        .hasMessageContaining("Only the owner can reset the round");
+    assertPartialCommitments();
+  }
+
+  /** A round that has produced randomness is not stuck, and cannot be reset. */
+  @ContractTest(previous = "enginesSendRandomShares")
+  void cannotResetRoundWithAvailableRandomness() {
+    blockchain.waitForBlockProductionTime(blockchain.getBlockProductionTime() + RESET_TIMEOUT);
+    Assertions.assertThatCode(
+            () ->
+                blockchain.sendAction(
+                    sender, contractAddress, OffChainPublishRandomness.resetRound()))
+        .hasMessageContaining("Randomness is available, so the round is not stuck");
+    assertCommitAndUploadPerformed(1, INITIAL_RANDOM_DATA_SHARES);
+  }
+
+  /** The liveness of each engine tracks the most recent commit and upload task it completed. */
+  @ContractTest(previous = "enginesSendRandomShares")
+  void engineLivenessIsTracked() {
+    blockchain.sendAction(sender, contractAddress, OffChainPublishRandomness.consumeRandomness());
+    assertCommitAndUploadPerformed(2, SECOND_RANDOM_DATA_SHARES);
+
+    final List<OffChainPublishRandomness.EngineLiveness> liveness = engineLiveness();
+    Assertions.assertThat(liveness).hasSize(4);
+    for (OffChainPublishRandomness.EngineLiveness engine : liveness) {
+      Assertions.assertThat(engine.lastCommitTaskId()).isEqualTo(2);
+      Assertions.assertThat(engine.lastUploadTaskId()).isEqualTo(2);
+    }
+    Assertions.assertThat(contract.getState().engineLiveness()).isEqualTo(liveness);
+  }
+
+  /**
+   * Consuming randomness keeps a proof of the round, where every share matches the commitment of
+   * its engine and the XOR of all shares is the delivered randomness.
+   */
+  @ContractTest(previous = "enginesSendRandomShares")
+  void consumedRoundCanBeProven() {
+    blockchain.sendAction(sender, contractAddress, OffChainPublishRandomness.consumeRandomness());
+
+    final OffChainPublishRandomness.RoundProof proof = proveRound(1);
+    Assertions.assertThat(proof.roundId()).isEqualTo(1);
+    Assertions.assertThat(proof.shares().stream().map(Hex::toHexString))
+        .containsExactlyElementsOf(INITIAL_RANDOM_DATA_SHARES);
+
+    final byte[] combined = new byte[32];
+    for (int i = 0; i < proof.shares().size(); i++) {
+      final byte[] share = proof.shares().get(i);
+      Assertions.assertThat(proof.commitments().get(i))
+          .isEqualTo(Hash.create(stream -> stream.write(share)));
+      for (int j = 0; j < combined.length; j++) {
+        combined[j] ^= share[j];
+      }
+    }
+    Assertions.assertThat(proof.randomness()).isEqualTo(combined);
+  }
+
+  /** A round that has not been consumed has no proof. */
+  @ContractTest(previous = "enginesSendRandomShares")
+  void cannotProveUnconsumedRound() {
+    Assertions.assertThat(contract.getState().roundProofs()).containsOnlyNulls();
+    Assertions.assertThatCode(
+            () ->
+                blockchain.sendAction(
+                    sender, contractAddress, OffChainPublishRandomness.proveRound(1)))
+        .hasMessageContaining("No proof stored for round 1");
+  }
+
+  /** An engine that has not responded is visible as lagging behind in the liveness. */
+  @ContractTest(previous = "contractWaitsForAllEnginesToUploadCommit")
+  void laggingEngineIsVisibleInLiveness() {
+    final List<OffChainPublishRandomness.EngineLiveness> liveness = engineLiveness();
+    for (int i = 0; i < 3; i++) {
+      Assertions.assertThat(liveness.get(i).lastCommitTaskId()).isEqualTo(1);
+      Assertions.assertThat(liveness.get(i).lastUploadTaskId()).isNull();
+    }
+    Assertions.assertThat(liveness.get(3).lastCommitTaskId()).isNull();
+    Assertions.assertThat(liveness.get(3).lastUploadTaskId()).isNull();
+  }
+
+  /** Consume randomness for the given domain, returning the derived randomness. */
+  private Hash consumeRandomnessForDomain(byte[] domain) {
+    return Hash.read(
+        ReturnData.of(
+            blockchain.sendAction(
+                sender,
+                contractAddress,
+                OffChainPublishRandomness.consumeRandomnessForDomain(domain))));
+  }
+
+  /** The randomness delivered by the contract, which is the XOR of the shares of all engines. */
+  private static byte[] combineShares(List<String> shares) {
+    final byte[] combined = new byte[32];
+    for (String share : shares) {
+      final byte[] shareBytes = Hex.decode(share);
+      for (int i = 0; i < combined.length; i++) {
+        combined[i] ^= shareBytes[i];
+      }
+    }
+    return combined;
+  }
+
+  /** The randomness derived for a domain, which is the hash of the randomness and the domain. */
+  private static Hash deriveForDomain(byte[] randomness, byte[] domain) {
+    return Hash.create(
+        stream -> {
+          stream.write(randomness);
+          stream.write(domain);
+        });
+  }
+
+  /** Read the proof of a consumed round, as returned by {@code prove_round}. */
+  private OffChainPublishRandomness.RoundProof proveRound(int roundId) {
+    final SafeDataInputStream stream =
+        ReturnData.of(
+            blockchain.sendAction(
+                sender, contractAddress, OffChainPublishRandomness.proveRound(roundId)));
+    final int returnedRoundId = stream.readInt();
+    final List<Hash> commitments = new ArrayList<>();
+    final int numberOfCommitments = stream.readInt();
+    for (int i = 0; i < numberOfCommitments; i++) {
+      commitments.add(Hash.read(stream));
+    }
+    final List<byte[]> shares = new ArrayList<>();
+    final int numberOfShares = stream.readInt();
+    for (int i = 0; i < numberOfShares; i++) {
+      shares.add(stream.readBytes(stream.readInt()));
+    }
+    final byte[] randomness = stream.readBytes(stream.readInt());
+    return new OffChainPublishRandomness.RoundProof(
+        returnedRoundId, commitments, shares, randomness);
+  }
+
+  /** Read the liveness of every engine, as returned by {@code engine_liveness}. */
+  private List<OffChainPublishRandomness.EngineLiveness> engineLiveness() {
+    final SafeDataInputStream stream =
+        ReturnData.of(
+            blockchain.sendAction(
+                sender, contractAddress, OffChainPublishRandomness.engineLiveness()));
+    final int numberOfEngines = stream.readInt();
+    final List<OffChainPublishRandomness.EngineLiveness> liveness = new ArrayList<>();
+    for (int i = 0; i < numberOfEngines; i++) {
+      liveness.add(
+          new OffChainPublishRandomness.EngineLiveness(
+              ReturnData.readOption(stream, SafeDataInputStream::readInt),
+              ReturnData.readOption(stream, SafeDataInputStream::readInt)));
+    }
+    return liveness;
+  }
+
   /** Check that state have been initialized, without any engine work having been done. */
   private void assertInitialState() {
     final OffChainPublishRandomness.ContractState state = contract.getState();
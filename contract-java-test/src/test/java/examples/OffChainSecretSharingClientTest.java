@@ -48,7 +48,10 @@ public final class OffChainSecretSharingClientTest extends JunitContractTest {
 
     contractAddress =
         blockchain.deployContract(
-            sender, CONTRACT_BYTES, OffChainSecretSharing.initialize(engineConfigs));
+            sender,
+            CONTRACT_BYTES,
+            OffChainSecretSharing.initialize(
+                engineConfigs, true, OffChainSecretSharingTest.SIGNATURE_VALID_DURATION_MS));
   }
 
   /** Client can upload sharings. */
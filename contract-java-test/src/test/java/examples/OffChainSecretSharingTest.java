@@ -14,11 +14,14 @@ import com.partisiablockchain.language.junit.ContractTest;
 import com.partisiablockchain.language.junit.JunitContractTest;
 import com.partisiablockchain.language.junit.TestBlockchain;
 import com.partisiablockchain.language.testenvironment.executionengine.TestExecutionEngine;
+import com.secata.stream.SafeDataInputStream;
 import com.secata.stream.SafeDataOutputStream;
 import com.secata.tools.immutable.Bytes;
 import examples.client.SecretShares;
 import examples.client.SecretSharingClient;
 import java.math.BigInteger;
+import java.util.ArrayList;
+import java.util.Collections;
 import java.util.List;
 import java.util.Map;
 import org.assertj.core.api.Assertions;
@@ -34,6 +37,15 @@ public final class OffChainSecretSharingTest extends JunitContractTest {
   public static final List<KeyPair> ENGINE_KEYS =
       List.of(20L, 21L, 22L, 23L).stream().map(BigInteger::valueOf).map(KeyPair::new).toList();
 
+  /** Number of milliseconds a signed request is valid for. */
+  public static final long SIGNATURE_VALID_DURATION_MS = 60 * 1000;
+
+  /** Widened number of milliseconds a signed request is valid for. */
+  private static final long WIDENED_SIGNATURE_VALID_DURATION_MS = 5 * 60 * 1000;
+
+  /** Private key of an engine added after deployment. */
+  private static final KeyPair NEW_ENGINE_KEY = new KeyPair(BigInteger.valueOf(24));
+
   /** Configurations of the engines. */
   private List<OffChainSecretSharing.NodeConfig> engineConfigs;
 
@@ -50,6 +62,9 @@ public final class OffChainSecretSharingTest extends JunitContractTest {
   private static final BigInteger SHARING_ID_1 = BigInteger.ONE;
   private static final BigInteger SHARING_ID_2 = BigInteger.TWO;
 
+  /** Maximum number of entries in the download request log. */
+  private static final int MAX_DOWNLOAD_REQUEST_LOG_SIZE = 32;
+
   /** The sharings to upload to engines. */
   private static final List<byte[]> SHARES_WITH_NONCE =
       List.of(
@@ -62,6 +77,15 @@ public final class OffChainSecretSharingTest extends JunitContractTest {
   private static final List<Hash> SHARE_COMMITMENTS =
       SHARES_WITH_NONCE.stream().map(SecretShares::createShareCommitment).toList();
 
+  /** The share to upload to the engine added after deployment. */
+  private static final byte[] NEW_ENGINE_SHARE = nonceAndData((byte) 4, new byte[] {13, 14, 15});
+
+  /** Configuration of the engine added after deployment. */
+  private OffChainSecretSharing.NodeConfig newEngineConfig;
+
+  /** Engine test object of the engine added after deployment. */
+  private TestExecutionEngine newEngine;
+
   /** Deploys contracts and sets up execution engines. */
   @ContractTest
   void setup() {
@@ -70,12 +94,15 @@ public final class OffChainSecretSharingTest extends JunitContractTest {
     engines = createEngines(blockchain);
     engineConfigs = createEngineConfigs(blockchain);
 
-    byte[] initPayload = OffChainSecretSharing.initialize(engineConfigs);
+    byte[] initPayload =
+        OffChainSecretSharing.initialize(engineConfigs, true, SIGNATURE_VALID_DURATION_MS);
     contractAddress = blockchain.deployContract(sender, CONTRACT_BYTES, initPayload);
     contract = new OffChainSecretSharing(getStateClient(), contractAddress);
 
     OffChainSecretSharing.ContractState state = contract.getState();
     assertThat(state.secretSharings().size()).isEqualTo(0);
+    assertThat(state.requireDistinctCommitments()).isTrue();
+    assertThat(state.signatureValidDurationMs()).isEqualTo(SIGNATURE_VALID_DURATION_MS);
     for (int i = 0; i < state.nodes().size(); i++) {
       assertThat(state.nodes().get(i)).isEqualTo(engineConfigs.get(i));
     }
@@ -214,6 +241,34 @@ public final class OffChainSecretSharingTest extends JunitContractTest {
         .isEqualTo(List.of(true, true, true, true));
   }
 
+  /** The upload progress of a sharing can be read as nodes confirm their share. */
+  @ContractTest(previous = "registerSharing")
+  void sharingProgressFollowsUploads() {
+    assertThat(sharingProgress(SHARING_ID_1)).containsExactly(0, engines.size());
+
+    for (int nodeIdx = 0; nodeIdx < engines.size(); nodeIdx++) {
+      final HttpRequestData requestData =
+          uploadRequest(
+              senderKey, engineConfigs.get(nodeIdx), SHARING_ID_1, SHARES_WITH_NONCE.get(nodeIdx));
+      engines.get(nodeIdx).makeHttpRequest(contractAddress, requestData);
+
+      assertThat(sharingProgress(SHARING_ID_1)).containsExactly(nodeIdx + 1, engines.size());
+      List<Boolean> completed =
+          contract.getState().secretSharings().get(SHARING_ID_1).nodesWithCompletedUpload();
+      assertThat(completed.stream().filter(x -> x).count()).isEqualTo(nodeIdx + 1);
+    }
+  }
+
+  /** The upload progress cannot be read for an unknown sharing. */
+  @ContractTest(previous = "setup")
+  void sharingProgressOfUnknownSharing() {
+    Assertions.assertThatThrownBy(
+            () ->
+                blockchain.sendAction(
+                    sender, contractAddress, OffChainSecretSharing.sharingProgress(SHARING_ID_1)))
+        .hasMessageContaining("Unknown sharing");
+  }
+
   /** Users can request the download of their owned secret shares. */
   @ContractTest(previous = "eachNodeStoresItsOwnSharing")
   void requestShareDownload() {
@@ -225,6 +280,37 @@ public final class OffChainSecretSharingTest extends JunitContractTest {
     assertThat(blockchain.getBlockProductionTime()).isEqualTo(15);
   }
 
+  /** Download requests are logged on-chain with the sharing, requester and time of request. */
+  @ContractTest(previous = "requestShareDownload")
+  void downloadRequestIsLogged() {
+    assertThat(recentDownloadRequests(10))
+        .containsExactly(new OffChainSecretSharing.DownloadRequest(SHARING_ID_1, sender, 14L));
+    assertThat(recentDownloadRequests(0)).isEmpty();
+  }
+
+  /** The download request log is capped, discarding the oldest requests when full. */
+  @ContractTest(previous = "eachNodeStoresItsOwnSharing")
+  void downloadRequestLogRollsOver() {
+    for (int i = 0; i < MAX_DOWNLOAD_REQUEST_LOG_SIZE + 3; i++) {
+      blockchain.sendAction(
+          sender, contractAddress, OffChainSecretSharing.requestDownload(SHARING_ID_1));
+    }
+
+    assertThat(contract.getState().downloadRequestLog()).hasSize(MAX_DOWNLOAD_REQUEST_LOG_SIZE);
+
+    List<OffChainSecretSharing.DownloadRequest> log =
+        recentDownloadRequests(MAX_DOWNLOAD_REQUEST_LOG_SIZE + 3);
+    assertThat(log).hasSize(MAX_DOWNLOAD_REQUEST_LOG_SIZE);
+    for (int i = 1; i < log.size(); i++) {
+      assertThat(log.get(i).requestedAt()).isGreaterThan(log.get(i - 1).requestedAt());
+    }
+    assertThat(log.get(log.size() - 1).requestedAt())
+        .isEqualTo(blockchain.getBlockProductionTime() - 2);
+
+    List<OffChainSecretSharing.DownloadRequest> lastTwo = recentDownloadRequests(2);
+    assertThat(lastTwo).containsExactlyElementsOf(log.subList(log.size() - 2, log.size()));
+  }
+
   /** Users can download secret shares after requesting the download. */
   @ContractTest(previous = "requestShareDownload")
   void usersDownloadAllSharesForSharing() {
@@ -406,6 +492,41 @@ public final class OffChainSecretSharingTest extends JunitContractTest {
         .hasMessageContaining("Cannot register sharing with the same identifier");
   }
 
+  /** Sharings with identical commitments for several engines are rejected. */
+  @ContractTest(previous = "setup")
+  void registerSharingWithDuplicateCommitments() {
+    List<Hash> duplicateCommitments =
+        List.of(
+            SHARE_COMMITMENTS.get(0),
+            SHARE_COMMITMENTS.get(1),
+            SHARE_COMMITMENTS.get(0),
+            SHARE_COMMITMENTS.get(3));
+    byte[] payload = OffChainSecretSharing.registerSharing(SHARING_ID_1, duplicateCommitments);
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(sender, contractAddress, payload))
+        .hasMessageContaining("Share commitments must be distinct");
+
+    assertThat(contract.getState().secretSharings().size()).isEqualTo(0);
+  }
+
+  /** Identical commitments are accepted when the contract does not require distinct commitments. */
+  @ContractTest
+  void registerSharingWithDuplicateCommitmentsAllowed() {
+    sender = blockchain.newAccount(senderKey);
+    engineConfigs = createEngineConfigs(blockchain);
+
+    byte[] initPayload =
+        OffChainSecretSharing.initialize(engineConfigs, false, SIGNATURE_VALID_DURATION_MS);
+    contractAddress = blockchain.deployContract(sender, CONTRACT_BYTES, initPayload);
+    contract = new OffChainSecretSharing(getStateClient(), contractAddress);
+
+    List<Hash> duplicateCommitments = Collections.nCopies(4, SHARE_COMMITMENTS.get(0));
+    byte[] payload = OffChainSecretSharing.registerSharing(SHARING_ID_1, duplicateCommitments);
+    blockchain.sendAction(sender, contractAddress, payload);
+
+    OffChainSecretSharing.Sharing sharing = contract.getState().secretSharings().get(SHARING_ID_1);
+    assertThat(sharing.shareCommitments()).isEqualTo(duplicateCommitments);
+  }
+
   /** Only engines are allowed to call register_shared. */
   @ContractTest(previous = "registerSharing")
   void nonEngineRegisterShared() {
@@ -482,19 +603,149 @@ public final class OffChainSecretSharingTest extends JunitContractTest {
         .hasMessageContaining("Invalid number of share commitments");
   }
 
-  /** Fail when uploading the wrong share, even if it is "just" the nonce. */
+  /**
+   * The owner of the contract can add a node after sharings have been registered. Existing
+   * sharings are extended with the new node, which has not yet received its share.
+   */
+  @ContractTest(previous = "sendShareToEngine")
+  void addNode() {
+    newEngine = blockchain.addExecutionEngine(p -> true, NEW_ENGINE_KEY);
+    BlockchainAddress newEngineAddress = blockchain.newAccount(NEW_ENGINE_KEY);
+    newEngineConfig =
+        new OffChainSecretSharing.NodeConfig(
+            newEngineAddress, "http://%s.example.org".formatted(newEngineAddress.writeAsString()));
+
+    blockchain.sendAction(sender, contractAddress, OffChainSecretSharing.addNode(newEngineConfig));
+
+    OffChainSecretSharing.ContractState state = contract.getState();
+    assertThat(state.nodes()).hasSize(5);
+    assertThat(state.nodes().get(4)).isEqualTo(newEngineConfig);
+    OffChainSecretSharing.Sharing sharing = state.secretSharings().get(SHARING_ID_1);
+    assertThat(sharing.nodesWithCompletedUpload())
+        .isEqualTo(List.of(true, false, false, false, false));
+    assertThat(sharing.shareCommitments()).isEqualTo(SHARE_COMMITMENTS);
+  }
+
+  /** Only the owner of the contract can add nodes. */
+  @ContractTest(previous = "setup")
+  void nonOwnerCannotAddNode() {
+    BlockchainAddress newEngineAddress = blockchain.newAccount(NEW_ENGINE_KEY);
+    byte[] payload =
+        OffChainSecretSharing.addNode(
+            new OffChainSecretSharing.NodeConfig(newEngineAddress, "http://example.org"));
+    Assertions.assertThatThrownBy(
+            () -> blockchain.sendAction(otherSender, contractAddress, payload))
+        .hasMessageContaining("Only the owner of the contract can add nodes");
+  }
+
+  /** A node cannot be added twice. */
+  @ContractTest(previous = "setup")
+  void cannotAddExistingNode() {
+    byte[] payload = OffChainSecretSharing.addNode(engineConfigs.get(2));
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(sender, contractAddress, payload))
+        .hasMessageContaining("Node is already part of the node set");
+  }
+
+  /** The added node rejects uploads until the owner has registered a commitment for its share. */
+  @ContractTest(previous = "addNode")
+  void addedNodeRejectsUploadWithoutCommitment() {
+    final HttpRequestData requestData =
+        uploadRequest(senderKey, newEngineConfig, SHARING_ID_1, NEW_ENGINE_SHARE);
+    final HttpResponseData response =
+        newEngine.makeHttpRequest(contractAddress, requestData).response();
+    assertThat(response.statusCode()).isEqualTo(409);
+    assertThat(response.bodyAsText())
+        .isEqualTo("{ \"error\": \"No commitment registered for this node\" }");
+  }
+
+  /**
+   * The owner can upload the share of the added node after registering its commitment. The uploads
+   * of the added node and the existing nodes are registered at their own index.
+   */
+  @ContractTest(previous = "addNode")
+  void uploadShareToAddedNode() {
+    blockchain.sendAction(
+        sender,
+        contractAddress,
+        OffChainSecretSharing.registerShareCommitment(
+            SHARING_ID_1, SecretShares.createShareCommitment(NEW_ENGINE_SHARE)));
+
+    final HttpRequestData newEngineRequest =
+        uploadRequest(senderKey, newEngineConfig, SHARING_ID_1, NEW_ENGINE_SHARE);
+    assertThat(newEngine.makeHttpRequest(contractAddress, newEngineRequest).response().statusCode())
+        .isEqualTo(201);
+
+    final HttpRequestData existingEngineRequest =
+        uploadRequest(senderKey, engineConfigs.get(1), SHARING_ID_1, SHARES_WITH_NONCE.get(1));
+    assertThat(
+            engines
+                .get(1)
+                .makeHttpRequest(contractAddress, existingEngineRequest)
+                .response()
+                .statusCode())
+        .isEqualTo(201);
+
+    OffChainSecretSharing.Sharing sharing = contract.getState().secretSharings().get(SHARING_ID_1);
+    assertThat(sharing.shareCommitments()).hasSize(5);
+    assertThat(sharing.nodesWithCompletedUpload())
+        .isEqualTo(List.of(true, true, false, false, true));
+  }
+
+  /** Only one commitment can be registered for each added node. */
+  @ContractTest(previous = "addNode")
+  void cannotRegisterCommitmentTwice() {
+    blockchain.sendAction(
+        sender,
+        contractAddress,
+        OffChainSecretSharing.registerShareCommitment(
+            SHARING_ID_1, SecretShares.createShareCommitment(NEW_ENGINE_SHARE)));
+
+    byte[] payload =
+        OffChainSecretSharing.registerShareCommitment(
+            SHARING_ID_1, SecretShares.createShareCommitment(NEW_ENGINE_SHARE));
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(sender, contractAddress, payload))
+        .hasMessageContaining("Sharing already has a commitment for every node");
+  }
+
+  /** A sharing is not complete until the share of the added node has been uploaded. */
+  @ContractTest(previous = "eachNodeStoresItsOwnSharing")
+  void cannotRequestDownloadBeforeAddedNodeReceivedShare() {
+    BlockchainAddress newEngineAddress = blockchain.newAccount(NEW_ENGINE_KEY);
+    blockchain.sendAction(
+        sender,
+        contractAddress,
+        OffChainSecretSharing.addNode(
+            new OffChainSecretSharing.NodeConfig(newEngineAddress, "http://example.org")));
+
+    Assertions.assertThatThrownBy(
+            () ->
+                blockchain.sendAction(
+                    sender, contractAddress, OffChainSecretSharing.requestDownload(SHARING_ID_1)))
+        .hasMessageContaining("Shares haven't been uploaded to all nodes yet");
+  }
+
+  /**
+   * Fail when uploading the wrong share, even if it is "just" the nonce. The error identifies the
+   * node and includes prefixes of the expected and computed hashes.
+   */
   @ContractTest(previous = "registerSharing")
   void failWhenUploadingWrongShare() {
+    final byte[] wrongShare = nonceAndData((byte) 9, new byte[] {1, 2, 3});
     final HttpRequestData requestData =
-        uploadRequest(
-            senderKey,
-            engineConfigs.get(0),
-            SHARING_ID_1,
-            nonceAndData((byte) 9, new byte[] {1, 2, 3}));
+        uploadRequest(senderKey, engineConfigs.get(0), SHARING_ID_1, wrongShare);
     final HttpResponseData response = makeEngine0Request(requestData);
     assertThat(response.statusCode()).isEqualTo(401);
+
+    String expectedPrefix = SHARE_COMMITMENTS.get(0).toString().substring(0, 8);
+    String computedPrefix = Hash.create(s -> s.write(wrongShare)).toString().substring(0, 8);
     assertThat(response.bodyAsText())
-        .isEqualTo("{ \"error\": \"User uploaded data doesn't match commitment\" }");
+        .isEqualTo(
+            "{ \"error\": \"User uploaded data doesn't match commitment\", \"node_index\": 0, "
+                + "\"expected_hash_prefix\": \""
+                + expectedPrefix
+                + "\", \"computed_hash_prefix\": \""
+                + computedPrefix
+                + "\" }");
   }
 
   /**
@@ -529,6 +780,69 @@ public final class OffChainSecretSharingTest extends JunitContractTest {
     assertThat(response.bodyAsText()).isEqualTo("{ \"error\": \"Unauthorized\" }");
   }
 
+  /** A request signed just inside a widened validity window is accepted. */
+  @ContractTest
+  void uploadWithinWidenedValidityWindow() {
+    deployWithSignatureValidDuration(WIDENED_SIGNATURE_VALID_DURATION_MS);
+    long timestamp =
+        blockchain.getBlockProductionTime() - (WIDENED_SIGNATURE_VALID_DURATION_MS - 30 * 1000);
+
+    HttpRequestData request =
+        uploadRequest(
+            senderKey, engineConfigs.get(0), SHARING_ID_1, SHARES_WITH_NONCE.get(0), timestamp);
+    HttpResponseData response = makeEngine0Request(request);
+
+    assertThat(response.statusCode()).isEqualTo(201);
+  }
+
+  /** A request signed just outside a widened validity window is rejected with 401. */
+  @ContractTest
+  void uploadOutsideWidenedValidityWindow() {
+    deployWithSignatureValidDuration(WIDENED_SIGNATURE_VALID_DURATION_MS);
+    long timestamp =
+        blockchain.getBlockProductionTime() - (WIDENED_SIGNATURE_VALID_DURATION_MS + 30 * 1000);
+
+    HttpRequestData request =
+        uploadRequest(
+            senderKey, engineConfigs.get(0), SHARING_ID_1, SHARES_WITH_NONCE.get(0), timestamp);
+    HttpResponseData response = makeEngine0Request(request);
+
+    assertThat(response.statusCode()).isEqualTo(401);
+    assertThat(response.bodyAsText()).isEqualTo("{ \"error\": \"Unauthorized\" }");
+  }
+
+  /** The contract cannot be deployed with a validity window above the maximum of ten minutes. */
+  @ContractTest
+  void deployWithTooLongSignatureValidDuration() {
+    sender = blockchain.newAccount(senderKey);
+    engineConfigs = createEngineConfigs(blockchain);
+
+    byte[] initPayload = OffChainSecretSharing.initialize(engineConfigs, true, 10 * 60 * 1000 + 1);
+    Assertions.assertThatThrownBy(
+            () -> blockchain.deployContract(sender, CONTRACT_BYTES, initPayload))
+        .hasMessageContaining(
+            "Signature validity duration must be between 1 and 600000 ms, but was 600001 ms");
+  }
+
+  /**
+   * Deploy the contract with the given signature validity window, and register a sharing.
+   *
+   * @param signatureValidDurationMs Number of milliseconds a signed request is valid for.
+   */
+  private void deployWithSignatureValidDuration(long signatureValidDurationMs) {
+    sender = blockchain.newAccount(senderKey);
+    engines = createEngines(blockchain);
+    engineConfigs = createEngineConfigs(blockchain);
+
+    byte[] initPayload =
+        OffChainSecretSharing.initialize(engineConfigs, true, signatureValidDurationMs);
+    contractAddress = blockchain.deployContract(sender, CONTRACT_BYTES, initPayload);
+    contract = new OffChainSecretSharing(getStateClient(), contractAddress);
+
+    byte[] payload = OffChainSecretSharing.registerSharing(SHARING_ID_1, SHARE_COMMITMENTS);
+    blockchain.sendAction(sender, contractAddress, payload);
+  }
+
   /** The node fails with 401 if the request does not contain a timestamp. */
   @ContractTest(previous = "registerSharing")
   void missingTimestamp() {
@@ -580,8 +894,27 @@ public final class OffChainSecretSharingTest extends JunitContractTest {
       OffChainSecretSharing.NodeConfig engineConfig,
       BigInteger secretSharingId,
       byte[] share) {
+    return uploadRequest(
+        senderKey, engineConfig, secretSharingId, share, blockchain.getBlockProductionTime());
+  }
+
+  /**
+   * Create a signed share upload request with the given timestamp.
+   *
+   * @param senderKey Key used to sign request. Not nullable.
+   * @param engineConfig Configuration of the engine that request is sent to. Not nullable.
+   * @param secretSharingId Identifier of the secret sharing. Not nullable.
+   * @param share Share to upload. Not nullable.
+   * @param timestamp Time of the request in milliseconds since the unix epoch.
+   * @return Signed request. Not nullable.
+   */
+  private HttpRequestData uploadRequest(
+      KeyPair senderKey,
+      OffChainSecretSharing.NodeConfig engineConfig,
+      BigInteger secretSharingId,
+      byte[] share,
+      long timestamp) {
     assertThat(share).as("Share must have nonce").hasSizeGreaterThan(32);
-    long timestamp = blockchain.getBlockProductionTime();
 
     final String method = "PUT";
     final Signature signature =
@@ -641,4 +974,40 @@ public final class OffChainSecretSharingTest extends JunitContractTest {
         "Authorization",
         List.of(SecretSharingClient.authorizationHeaderValue(signature, timestamp)));
   }
+
+  /**
+   * Read the most recent download requests, as returned by {@code recent_download_requests}.
+   *
+   * @param count Maximum number of requests to read.
+   * @return The most recent download requests, oldest first.
+   */
+  private List<OffChainSecretSharing.DownloadRequest> recentDownloadRequests(int count) {
+    SafeDataInputStream requests =
+        ReturnData.of(
+            blockchain.sendAction(
+                otherSender, contractAddress, OffChainSecretSharing.recentDownloadRequests(count)));
+    int numberOfRequests = requests.readInt();
+    List<OffChainSecretSharing.DownloadRequest> log = new ArrayList<>();
+    for (int i = 0; i < numberOfRequests; i++) {
+      BigInteger sharingId = new BigInteger(1, requests.readBytes(16));
+      BlockchainAddress requester = BlockchainAddress.read(requests);
+      log.add(new OffChainSecretSharing.DownloadRequest(sharingId, requester, requests.readLong()));
+    }
+    return log;
+  }
+
+  /**
+   * Read the upload progress of a sharing, as returned by {@code sharing_progress}.
+   *
+   * @param sharingId Identifier of the sharing.
+   * @return The number of nodes that have completed their upload, followed by the total number of
+   *     nodes.
+   */
+  private List<Integer> sharingProgress(BigInteger sharingId) {
+    SafeDataInputStream progress =
+        ReturnData.of(
+            blockchain.sendAction(
+                otherSender, contractAddress, OffChainSecretSharing.sharingProgress(sharingId)));
+    return List.of(progress.readInt(), progress.readInt());
+  }
 }
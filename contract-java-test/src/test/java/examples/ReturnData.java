@@ -0,0 +1,34 @@
+package examples;
+
+import com.partisiablockchain.language.testenvironment.TxExecution;
+import com.secata.stream.SafeDataInputStream;
+import java.util.function.Function;
+
+/** Reader of the data returned by actions, which are serialized using RPC serialization. */
+public final class ReturnData {
+
+  private ReturnData() {}
+
+  /**
+   * Create a stream reading the data returned by the action of the given execution.
+   *
+   * @param execution Execution of the action returning data. Not nullable.
+   * @return Stream reading the return data.
+   */
+  public static SafeDataInputStream of(TxExecution execution) {
+    return SafeDataInputStream.createFromBytes(execution.getReturnValue());
+  }
+
+  /**
+   * Read an RPC serialized {@code Option} from the stream.
+   *
+   * @param stream Stream to read from. Not nullable.
+   * @param reader Reader of the value, if present. Not nullable.
+   * @param <T> Type of the value.
+   * @return The value, or null if the option is None.
+   */
+  public static <T> T readOption(
+      SafeDataInputStream stream, Function<SafeDataInputStream, T> reader) {
+    return stream.readBoolean() ? reader.apply(stream) : null;
+  }
+}
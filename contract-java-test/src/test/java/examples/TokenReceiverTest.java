@@ -0,0 +1,137 @@
+package examples;
+
+import com.partisiablockchain.BlockchainAddress;
+import com.partisiablockchain.language.abicodegen.Token;
+import com.partisiablockchain.language.abicodegen.TokenReceiver;
+import com.partisiablockchain.language.junit.ContractBytes;
+import com.partisiablockchain.language.junit.ContractTest;
+import com.partisiablockchain.language.junit.JunitContractTest;
+import com.partisiablockchain.language.junit.exceptions.ActionFailureException;
+import java.math.BigInteger;
+import java.util.List;
+import org.assertj.core.api.Assertions;
+
+/** Test suite for the TokenReceiver contract. */
+public final class TokenReceiverTest extends JunitContractTest {
+
+  private static final ContractBytes TOKEN_CONTRACT_BYTES =
+      ContractBytesLoader.forContract("token");
+
+  private static final ContractBytes TOKEN_RECEIVER_CONTRACT_BYTES =
+      ContractBytesLoader.forContract("token_receiver");
+
+  private static final BigInteger DEPOSITOR_TOKENS = BigInteger.valueOf(1000);
+
+  private BlockchainAddress owner;
+  private BlockchainAddress depositor;
+  private BlockchainAddress token;
+  private BlockchainAddress tokenReceiver;
+
+  /** The token receiver accepts deposits when deployed. */
+  @ContractTest
+  void deploy() {
+    owner = blockchain.newAccount(2);
+    depositor = blockchain.newAccount(3);
+
+    token =
+        blockchain.deployContract(
+            depositor,
+            TOKEN_CONTRACT_BYTES,
+            Token.initialize("Deposit", "DEP", (byte) 0, DEPOSITOR_TOKENS, List.of()));
+    tokenReceiver =
+        blockchain.deployContract(
+            owner, TOKEN_RECEIVER_CONTRACT_BYTES, TokenReceiver.initialize(token));
+
+    TokenReceiver.TokenReceiverState state = getState();
+    Assertions.assertThat(state.owner()).isEqualTo(owner);
+    Assertions.assertThat(state.token()).isEqualTo(token);
+    Assertions.assertThat(state.accepting()).isTrue();
+    Assertions.assertThat(state.deposits().size()).isEqualTo(0);
+  }
+
+  /** Depositors can deposit tokens they have approved the receiver to transfer. */
+  @ContractTest(previous = "deploy")
+  void deposit() {
+    blockchain.sendAction(depositor, token, Token.approve(tokenReceiver, BigInteger.valueOf(300)));
+    blockchain.sendAction(
+        depositor, tokenReceiver, TokenReceiver.deposit(depositor, BigInteger.valueOf(100)));
+    blockchain.sendAction(
+        depositor, tokenReceiver, TokenReceiver.deposit(depositor, BigInteger.valueOf(200)));
+
+    Assertions.assertThat(getState().deposits().get(depositor)).isEqualTo(BigInteger.valueOf(300));
+    Assertions.assertThat(tokenBalance(tokenReceiver)).isEqualTo(BigInteger.valueOf(300));
+    Assertions.assertThat(tokenBalance(depositor)).isEqualTo(BigInteger.valueOf(700));
+  }
+
+  /** A deposit without an allowance is not recorded. */
+  @ContractTest(previous = "deploy")
+  void depositWithoutAllowance() {
+    byte[] deposit = TokenReceiver.deposit(depositor, BigInteger.valueOf(100));
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(depositor, tokenReceiver, deposit))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Insufficient allowance for transfer_from: 0/100");
+
+    Assertions.assertThat(getState().deposits().get(depositor)).isNull();
+  }
+
+  /** Only the depositor or the token can deposit the tokens of the depositor. */
+  @ContractTest(previous = "deploy")
+  void depositOnBehalfOfOthers() {
+    blockchain.sendAction(depositor, token, Token.approve(tokenReceiver, BigInteger.valueOf(300)));
+
+    byte[] deposit = TokenReceiver.deposit(depositor, BigInteger.valueOf(100));
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(owner, tokenReceiver, deposit))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining(
+            "Only the depositor or the token can deposit the tokens of the depositor");
+
+    Assertions.assertThat(tokenBalance(depositor)).isEqualTo(DEPOSITOR_TOKENS);
+  }
+
+  /** Only the token can notify the receiver of received tokens. */
+  @ContractTest(previous = "deploy")
+  void onTokenReceivedFromOthers() {
+    byte[] onTokenReceived =
+        TokenReceiver.onTokenReceived(depositor, BigInteger.valueOf(100), new byte[0]);
+    Assertions.assertThatThrownBy(
+            () -> blockchain.sendAction(depositor, tokenReceiver, onTokenReceived))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Only the token can notify the receiver of received tokens");
+
+    Assertions.assertThat(getState().deposits().get(depositor)).isNull();
+  }
+
+  /** The owner can stop accepting deposits, after which deposits fail. */
+  @ContractTest(previous = "deploy")
+  void stopAcceptingDeposits() {
+    blockchain.sendAction(owner, tokenReceiver, TokenReceiver.setAccepting(false));
+    blockchain.sendAction(depositor, token, Token.approve(tokenReceiver, BigInteger.valueOf(300)));
+
+    Assertions.assertThat(getState().accepting()).isFalse();
+    byte[] deposit = TokenReceiver.deposit(depositor, BigInteger.valueOf(100));
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(depositor, tokenReceiver, deposit))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Deposits are not accepted");
+  }
+
+  /** Only the owner can change whether deposits are accepted. */
+  @ContractTest(previous = "deploy")
+  void setAcceptingAsNonOwner() {
+    byte[] setAccepting = TokenReceiver.setAccepting(false);
+    Assertions.assertThatThrownBy(
+            () -> blockchain.sendAction(depositor, tokenReceiver, setAccepting))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Only owner can change whether deposits are accepted");
+
+    Assertions.assertThat(getState().accepting()).isTrue();
+  }
+
+  private TokenReceiver.TokenReceiverState getState() {
+    return new TokenReceiver(getStateClient(), tokenReceiver).getState();
+  }
+
+  private BigInteger tokenBalance(BlockchainAddress holder) {
+    BigInteger balance = new Token(getStateClient(), token).getState().balances().get(holder);
+    return balance == null ? BigInteger.ZERO : balance;
+  }
+}
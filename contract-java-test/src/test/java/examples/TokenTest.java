@@ -0,0 +1,903 @@
+package examples;
+
+import com.partisiablockchain.BlockchainAddress;
+import com.partisiablockchain.language.abicodegen.Token;
+import com.partisiablockchain.language.abicodegen.TokenReceiver;
+import com.partisiablockchain.language.junit.ContractBytes;
+import com.partisiablockchain.language.junit.ContractTest;
+import com.partisiablockchain.language.junit.JunitContractTest;
+import com.partisiablockchain.language.junit.exceptions.ActionFailureException;
+import com.secata.stream.SafeDataInputStream;
+import java.math.BigInteger;
+import java.util.ArrayList;
+import java.util.Arrays;
+import java.util.List;
+import org.assertj.core.api.Assertions;
+
+/** Test suite for the Token contract. */
+public final class TokenTest extends JunitContractTest {
+
+  private static final ContractBytes TOKEN_CONTRACT_BYTES =
+      ContractBytesLoader.forContract("token");
+
+  private static final ContractBytes TOKEN_RECEIVER_CONTRACT_BYTES =
+      ContractBytesLoader.forContract("token_receiver");
+
+  /** Shortname of the deposit action of the token receiver. */
+  private static final int DEPOSIT_SHORTNAME = 0x02;
+
+  private static final BigInteger TOTAL_SUPPLY = BigInteger.valueOf(1_000_000);
+
+  /** Block production time at which allowances with an expiry expire in the tests. */
+  private static final long ALLOWANCE_EXPIRY = 60 * 60 * 1000;
+
+  private BlockchainAddress issuer;
+  private BlockchainAddress alice;
+  private BlockchainAddress bob;
+  private BlockchainAddress feeCollector;
+  private BlockchainAddress guardian;
+  private BlockchainAddress token;
+  private BlockchainAddress tokenReceiver;
+
+  /** The entire supply is minted to the deployer of the token. */
+  @ContractTest
+  void deploy() {
+    issuer = blockchain.newAccount(2);
+    alice = blockchain.newAccount(3);
+    bob = blockchain.newAccount(4);
+
+    byte[] initRpc = Token.initialize("Example Token", "EXT", (byte) 4, TOTAL_SUPPLY, List.of());
+    token = blockchain.deployContract(issuer, TOKEN_CONTRACT_BYTES, initRpc);
+
+    Token.TokenState state = getState();
+    Assertions.assertThat(state.name()).isEqualTo("Example Token");
+    Assertions.assertThat(state.symbol()).isEqualTo("EXT");
+    Assertions.assertThat(state.decimals()).isEqualTo((byte) 4);
+    Assertions.assertThat(state.totalSupply()).isEqualTo(TOTAL_SUPPLY);
+    Assertions.assertThat(balanceOf(issuer)).isEqualTo(TOTAL_SUPPLY);
+    Assertions.assertThat(balanceOf(alice)).isEqualTo(BigInteger.ZERO);
+    Assertions.assertThat(holderCount()).isEqualTo(1);
+  }
+
+  /** A token can have up to 36 decimals. */
+  @ContractTest(previous = "deploy")
+  void deployWithMaximalDecimals() {
+    byte[] initRpc = Token.initialize("Precise Token", "PRT", (byte) 36, TOTAL_SUPPLY, List.of());
+    BlockchainAddress preciseToken =
+        blockchain.deployContract(issuer, TOKEN_CONTRACT_BYTES, initRpc);
+
+    Token.TokenState state = new Token(getStateClient(), preciseToken).getState();
+    Assertions.assertThat(state.decimals()).isEqualTo((byte) 36);
+  }
+
+  /** Amounts are formatted with the number of decimals of the token. */
+  @ContractTest(previous = "deploy")
+  void formatAmounts() {
+    assertFormattedAmount(token, 1_234_567, 123, 4567, "123.4567");
+    assertFormattedAmount(token, 5, 0, 5, "0.0005");
+    assertFormattedAmount(token, 20_000, 2, 0, "2.0000");
+  }
+
+  /** Amounts of a token without decimals are formatted without a decimal point. */
+  @ContractTest(previous = "deploy")
+  void formatAmountsWithoutDecimals() {
+    byte[] initRpc = Token.initialize("Whole Token", "WHT", (byte) 0, TOTAL_SUPPLY, List.of());
+    BlockchainAddress wholeToken = blockchain.deployContract(issuer, TOKEN_CONTRACT_BYTES, initRpc);
+
+    assertFormattedAmount(wholeToken, 42, 42, 0, "42");
+    assertFormattedAmount(wholeToken, 0, 0, 0, "0");
+  }
+
+  /** Amounts of a token with the maximal number of decimals are formatted with all decimals. */
+  @ContractTest(previous = "deploy")
+  void formatAmountsWithMaximalDecimals() {
+    byte[] initRpc = Token.initialize("Precise Token", "PRT", (byte) 36, TOTAL_SUPPLY, List.of());
+    BlockchainAddress preciseToken =
+        blockchain.deployContract(issuer, TOKEN_CONTRACT_BYTES, initRpc);
+
+    assertFormattedAmount(preciseToken, 1, 0, 1, "0." + "0".repeat(35) + "1");
+  }
+
+  /** A token cannot have more than 36 decimals. */
+  @ContractTest(previous = "deploy")
+  void deployWithTooManyDecimals() {
+    byte[] initRpc = Token.initialize("Absurd Token", "ABT", (byte) 37, TOTAL_SUPPLY, List.of());
+    Assertions.assertThatThrownBy(
+            () -> blockchain.deployContract(issuer, TOKEN_CONTRACT_BYTES, initRpc))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Decimals must be at most 36, but was 37");
+
+    byte[] maximalByteRpc =
+        Token.initialize("Absurd Token", "ABT", (byte) 255, TOTAL_SUPPLY, List.of());
+    Assertions.assertThatThrownBy(
+            () -> blockchain.deployContract(issuer, TOKEN_CONTRACT_BYTES, maximalByteRpc))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Decimals must be at most 36, but was 255");
+  }
+
+  /** The supply can be distributed among several accounts when the token is deployed. */
+  @ContractTest(previous = "deploy")
+  void deployWithInitialAllocation() {
+    List<Token.Allocation> allocation =
+        List.of(
+            new Token.Allocation(alice, BigInteger.valueOf(600_000)),
+            new Token.Allocation(bob, BigInteger.valueOf(400_000)));
+    byte[] initRpc = Token.initialize("Fair Token", "FAT", (byte) 4, TOTAL_SUPPLY, allocation);
+    BlockchainAddress fairToken = blockchain.deployContract(issuer, TOKEN_CONTRACT_BYTES, initRpc);
+
+    Token.TokenState state = new Token(getStateClient(), fairToken).getState();
+    Assertions.assertThat(state.totalSupply()).isEqualTo(TOTAL_SUPPLY);
+    Assertions.assertThat(state.balances().get(alice)).isEqualTo(BigInteger.valueOf(600_000));
+    Assertions.assertThat(state.balances().get(bob)).isEqualTo(BigInteger.valueOf(400_000));
+    Assertions.assertThat(state.balances().get(issuer)).isNull();
+    Assertions.assertThat(state.holderCount()).isEqualTo(2L);
+    Assertions.assertThat(state.owner()).isEqualTo(issuer);
+  }
+
+  /** The initial allocation must sum to exactly the total supply. */
+  @ContractTest(previous = "deploy")
+  void deployWithMismatchedInitialAllocation() {
+    List<Token.Allocation> tooLittle =
+        List.of(
+            new Token.Allocation(alice, BigInteger.valueOf(600_000)),
+            new Token.Allocation(bob, BigInteger.valueOf(399_999)));
+    byte[] tooLittleRpc = Token.initialize("Fair Token", "FAT", (byte) 4, TOTAL_SUPPLY, tooLittle);
+    Assertions.assertThatThrownBy(
+            () -> blockchain.deployContract(issuer, TOKEN_CONTRACT_BYTES, tooLittleRpc))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Initial allocation must sum to the total supply");
+
+    List<Token.Allocation> tooMuch =
+        List.of(
+            new Token.Allocation(alice, TOTAL_SUPPLY),
+            new Token.Allocation(bob, BigInteger.ONE));
+    byte[] tooMuchRpc = Token.initialize("Fair Token", "FAT", (byte) 4, TOTAL_SUPPLY, tooMuch);
+    Assertions.assertThatThrownBy(
+            () -> blockchain.deployContract(issuer, TOKEN_CONTRACT_BYTES, tooMuchRpc))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Initial allocation must sum to the total supply");
+  }
+
+  /** The metadata of the token is returned in a single call, matching the initialization. */
+  @ContractTest(previous = "deploy")
+  void readMetadata() {
+    SafeDataInputStream metadata =
+        ReturnData.of(blockchain.sendAction(alice, token, Token.metadata()));
+
+    Assertions.assertThat(metadata.readString()).isEqualTo("Example Token");
+    Assertions.assertThat(metadata.readString()).isEqualTo("EXT");
+    Assertions.assertThat(metadata.readUnsignedByte()).isEqualTo(4);
+    Assertions.assertThat(readU128(metadata)).isEqualTo(TOTAL_SUPPLY);
+  }
+
+  /** Holders can transfer their tokens to other accounts. */
+  @ContractTest(previous = "deploy")
+  void transfer() {
+    blockchain.sendAction(issuer, token, Token.transfer(alice, BigInteger.valueOf(300)));
+    blockchain.sendAction(alice, token, Token.transfer(bob, BigInteger.valueOf(100)));
+
+    Assertions.assertThat(balanceOf(issuer)).isEqualTo(BigInteger.valueOf(999_700));
+    Assertions.assertThat(balanceOf(alice)).isEqualTo(BigInteger.valueOf(200));
+    Assertions.assertThat(balanceOf(bob)).isEqualTo(BigInteger.valueOf(100));
+    Assertions.assertThat(getState().totalSupply()).isEqualTo(TOTAL_SUPPLY);
+  }
+
+  /** A transfer of more tokens than the sender holds fails, without moving any tokens. */
+  @ContractTest(previous = "transfer")
+  void transferWithInsufficientFunds() {
+    byte[] transfer = Token.transfer(bob, BigInteger.valueOf(201));
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(alice, token, transfer))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Insufficient funds for transfer: 200/201");
+
+    Assertions.assertThat(balanceOf(alice)).isEqualTo(BigInteger.valueOf(200));
+    Assertions.assertThat(balanceOf(bob)).isEqualTo(BigInteger.valueOf(100));
+  }
+
+  /** Balances of several accounts are returned in one call, where unknown accounts hold zero. */
+  @ContractTest(previous = "transfer")
+  void readBalancesOfSeveralAccounts() {
+    BlockchainAddress emptyAccount = blockchain.newAccount(5);
+    SafeDataInputStream balances =
+        ReturnData.of(
+            blockchain.sendAction(
+                alice, token, Token.balancesOf(List.of(bob, emptyAccount, issuer, alice))));
+
+    Assertions.assertThat(balances.readInt()).isEqualTo(4);
+    Assertions.assertThat(readU128(balances)).isEqualTo(BigInteger.valueOf(100));
+    Assertions.assertThat(readU128(balances)).isEqualTo(BigInteger.ZERO);
+    Assertions.assertThat(readU128(balances)).isEqualTo(BigInteger.valueOf(999_700));
+    Assertions.assertThat(readU128(balances)).isEqualTo(BigInteger.valueOf(200));
+  }
+
+  /** Approving a spender sets its allowance, replacing any previous allowance. */
+  @ContractTest(previous = "transfer")
+  void approve() {
+    blockchain.sendAction(alice, token, Token.approve(bob, BigInteger.valueOf(50)));
+    Assertions.assertThat(allowance(alice, bob)).isEqualTo(BigInteger.valueOf(50));
+
+    blockchain.sendAction(alice, token, Token.approve(bob, BigInteger.valueOf(80)));
+    Assertions.assertThat(allowance(alice, bob)).isEqualTo(BigInteger.valueOf(80));
+    Assertions.assertThat(allowance(bob, alice)).isEqualTo(BigInteger.ZERO);
+  }
+
+  /** An allowance with an expiry can be spent until it expires, after which it is zero. */
+  @ContractTest(previous = "transfer")
+  void approveWithExpiry() {
+    blockchain.sendAction(
+        alice, token, Token.approveWithExpiry(bob, BigInteger.valueOf(80), ALLOWANCE_EXPIRY));
+    blockchain.sendAction(bob, token, Token.transferFrom(alice, bob, BigInteger.valueOf(30)));
+
+    Assertions.assertThat(allowance(alice, bob)).isEqualTo(BigInteger.valueOf(50));
+    Assertions.assertThat(balanceOf(bob)).isEqualTo(BigInteger.valueOf(130));
+
+    blockchain.waitForBlockProductionTime(2 * ALLOWANCE_EXPIRY);
+
+    byte[] transferFrom = Token.transferFrom(alice, bob, BigInteger.TEN);
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(bob, token, transferFrom))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Insufficient allowance for transfer_from: 0/10");
+
+    byte[] burnFrom = Token.burnFrom(alice, BigInteger.TEN);
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(bob, token, burnFrom))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Insufficient allowance for burn_from: 0/10");
+
+    Assertions.assertThat(balanceOf(alice)).isEqualTo(BigInteger.valueOf(170));
+  }
+
+  /** Approving without an expiry replaces an allowance with an expiry. */
+  @ContractTest(previous = "transfer")
+  void approveReplacesExpiry() {
+    blockchain.sendAction(
+        alice, token, Token.approveWithExpiry(bob, BigInteger.valueOf(80), ALLOWANCE_EXPIRY));
+    blockchain.sendAction(alice, token, Token.approve(bob, BigInteger.valueOf(40)));
+    Assertions.assertThat(getState().allowanceExpiries().size()).isEqualTo(0);
+
+    blockchain.waitForBlockProductionTime(2 * ALLOWANCE_EXPIRY);
+    blockchain.sendAction(bob, token, Token.transferFrom(alice, bob, BigInteger.valueOf(40)));
+
+    Assertions.assertThat(allowance(alice, bob)).isEqualTo(BigInteger.ZERO);
+    Assertions.assertThat(balanceOf(bob)).isEqualTo(BigInteger.valueOf(140));
+  }
+
+  /** An approved spender can transfer tokens of the owner, which reduces its allowance. */
+  @ContractTest(previous = "approve")
+  void transferFrom() {
+    blockchain.sendAction(bob, token, Token.transferFrom(alice, issuer, BigInteger.valueOf(30)));
+
+    Assertions.assertThat(allowance(alice, bob)).isEqualTo(BigInteger.valueOf(50));
+    Assertions.assertThat(balanceOf(alice)).isEqualTo(BigInteger.valueOf(170));
+    Assertions.assertThat(balanceOf(issuer)).isEqualTo(BigInteger.valueOf(999_730));
+    Assertions.assertThat(balanceOf(bob)).isEqualTo(BigInteger.valueOf(100));
+  }
+
+  /** A spender cannot transfer more than its allowance. */
+  @ContractTest(previous = "approve")
+  void transferFromWithInsufficientAllowance() {
+    byte[] transferFrom = Token.transferFrom(alice, bob, BigInteger.valueOf(81));
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(bob, token, transferFrom))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Insufficient allowance for transfer_from: 80/81");
+
+    Assertions.assertThat(allowance(alice, bob)).isEqualTo(BigInteger.valueOf(80));
+    Assertions.assertThat(balanceOf(alice)).isEqualTo(BigInteger.valueOf(200));
+  }
+
+  /** The holder count follows transfers that create and empty balances. */
+  @ContractTest(previous = "transfer")
+  void holderCountFollowsBalances() {
+    Assertions.assertThat(holderCount()).isEqualTo(3);
+
+    blockchain.sendAction(bob, token, Token.transfer(alice, BigInteger.valueOf(100)));
+    Assertions.assertThat(holderCount()).isEqualTo(2);
+
+    blockchain.sendAction(alice, token, Token.transfer(alice, BigInteger.valueOf(300)));
+    Assertions.assertThat(holderCount()).isEqualTo(2);
+
+    blockchain.sendAction(alice, token, Token.transfer(bob, BigInteger.valueOf(1)));
+    Assertions.assertThat(holderCount()).isEqualTo(3);
+
+    blockchain.sendAction(alice, token, Token.transfer(bob, BigInteger.ZERO));
+    Assertions.assertThat(holderCount()).isEqualTo(3);
+  }
+
+  /** Burning the entire balance of a holder removes it from the holder count. */
+  @ContractTest(previous = "transfer")
+  void holderCountFollowsBurns() {
+    blockchain.sendAction(bob, token, Token.burn(BigInteger.valueOf(100)));
+
+    Assertions.assertThat(holderCount()).isEqualTo(2);
+    Assertions.assertThat(getState().holderCount()).isEqualTo(2L);
+  }
+
+  /** Holders can burn their own tokens, which reduces the total supply. */
+  @ContractTest(previous = "transfer")
+  void burn() {
+    blockchain.sendAction(alice, token, Token.burn(BigInteger.valueOf(50)));
+
+    Assertions.assertThat(balanceOf(alice)).isEqualTo(BigInteger.valueOf(150));
+    Assertions.assertThat(getState().totalSupply()).isEqualTo(BigInteger.valueOf(999_950));
+
+    byte[] burn = Token.burn(BigInteger.valueOf(151));
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(alice, token, burn))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Insufficient funds for burn: 150/151");
+  }
+
+  /** An approved spender can burn tokens of the owner, which reduces its allowance. */
+  @ContractTest(previous = "approve")
+  void burnFrom() {
+    blockchain.sendAction(bob, token, Token.burnFrom(alice, BigInteger.valueOf(30)));
+
+    Assertions.assertThat(allowance(alice, bob)).isEqualTo(BigInteger.valueOf(50));
+    Assertions.assertThat(balanceOf(alice)).isEqualTo(BigInteger.valueOf(170));
+    Assertions.assertThat(balanceOf(bob)).isEqualTo(BigInteger.valueOf(100));
+    Assertions.assertThat(getState().totalSupply()).isEqualTo(BigInteger.valueOf(999_970));
+  }
+
+  /** A spender cannot burn more than its allowance. */
+  @ContractTest(previous = "approve")
+  void burnFromWithInsufficientAllowance() {
+    byte[] burnFrom = Token.burnFrom(alice, BigInteger.valueOf(81));
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(bob, token, burnFrom))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Insufficient allowance for burn_from: 80/81");
+
+    Assertions.assertThat(allowance(alice, bob)).isEqualTo(BigInteger.valueOf(80));
+    Assertions.assertThat(balanceOf(alice)).isEqualTo(BigInteger.valueOf(200));
+    Assertions.assertThat(getState().totalSupply()).isEqualTo(TOTAL_SUPPLY);
+  }
+
+  /** An account without an allowance cannot transfer tokens on behalf of the owner. */
+  @ContractTest(previous = "transfer")
+  void transferFromWithoutAllowance() {
+    byte[] transferFrom = Token.transferFrom(alice, bob, BigInteger.ONE);
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(bob, token, transferFrom))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Insufficient allowance for transfer_from: 0/1");
+  }
+
+  /** The allowance does not allow transferring more tokens than the owner holds. */
+  @ContractTest(previous = "transfer")
+  void transferFromWithInsufficientFunds() {
+    blockchain.sendAction(alice, token, Token.approve(bob, BigInteger.valueOf(1000)));
+
+    byte[] transferFrom = Token.transferFrom(alice, bob, BigInteger.valueOf(201));
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(bob, token, transferFrom))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Insufficient funds for transfer: 200/201");
+
+    Assertions.assertThat(allowance(alice, bob)).isEqualTo(BigInteger.valueOf(1000));
+    Assertions.assertThat(balanceOf(alice)).isEqualTo(BigInteger.valueOf(200));
+  }
+
+  /** Tokens can be transferred to several recipients at once, returning a record of each. */
+  @ContractTest(previous = "transfer")
+  void bulkTransferWithEvents() {
+    List<Token.Recipient> recipients =
+        List.of(
+            new Token.Recipient(bob, BigInteger.valueOf(50)),
+            new Token.Recipient(issuer, BigInteger.valueOf(70)),
+            new Token.Recipient(bob, BigInteger.valueOf(5)));
+    SafeDataInputStream events =
+        ReturnData.of(
+            blockchain.sendAction(alice, token, Token.bulkTransferWithEvents(recipients)));
+
+    Assertions.assertThat(readTransferEvents(events))
+        .containsExactly(
+            new TransferEvent(alice, bob, BigInteger.valueOf(50), BigInteger.ZERO),
+            new TransferEvent(alice, issuer, BigInteger.valueOf(70), BigInteger.ZERO),
+            new TransferEvent(alice, bob, BigInteger.valueOf(5), BigInteger.ZERO));
+    Assertions.assertThat(balanceOf(alice)).isEqualTo(BigInteger.valueOf(75));
+    Assertions.assertThat(balanceOf(bob)).isEqualTo(BigInteger.valueOf(155));
+    Assertions.assertThat(balanceOf(issuer)).isEqualTo(BigInteger.valueOf(999_770));
+  }
+
+  /** A bulk transfer exceeding the balance of the sender fails entirely. */
+  @ContractTest(previous = "transfer")
+  void bulkTransferWithInsufficientFunds() {
+    List<Token.Recipient> recipients =
+        List.of(
+            new Token.Recipient(bob, BigInteger.valueOf(150)),
+            new Token.Recipient(issuer, BigInteger.valueOf(51)));
+    byte[] bulkTransfer = Token.bulkTransferWithEvents(recipients);
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(alice, token, bulkTransfer))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Insufficient funds for bulk transfer: 200/201");
+
+    Assertions.assertThat(balanceOf(alice)).isEqualTo(BigInteger.valueOf(200));
+    Assertions.assertThat(balanceOf(bob)).isEqualTo(BigInteger.valueOf(100));
+    Assertions.assertThat(balanceOf(issuer)).isEqualTo(BigInteger.valueOf(999_700));
+  }
+
+  /** The owner can set a transfer fee, which is credited to the fee collector. */
+  @ContractTest(previous = "transfer")
+  void setTransferFee() {
+    feeCollector = blockchain.newAccount(6);
+    blockchain.sendAction(
+        issuer, token, Token.setTransferFee(new Token.TransferFee((short) 25, feeCollector)));
+
+    Token.TransferFee transferFee = getState().transferFee();
+    Assertions.assertThat(transferFee.perMille()).isEqualTo((short) 25);
+    Assertions.assertThat(transferFee.collector()).isEqualTo(feeCollector);
+  }
+
+  /** The fee is deducted from transfers and credited to the collector, rounding the fee down. */
+  @ContractTest(previous = "setTransferFee")
+  void transferWithFee() {
+    blockchain.sendAction(alice, token, Token.transfer(bob, BigInteger.valueOf(100)));
+
+    Assertions.assertThat(balanceOf(alice)).isEqualTo(BigInteger.valueOf(100));
+    Assertions.assertThat(balanceOf(bob)).isEqualTo(BigInteger.valueOf(198));
+    Assertions.assertThat(balanceOf(feeCollector)).isEqualTo(BigInteger.valueOf(2));
+
+    blockchain.sendAction(alice, token, Token.approve(bob, BigInteger.valueOf(80)));
+    blockchain.sendAction(bob, token, Token.transferFrom(alice, bob, BigInteger.valueOf(80)));
+
+    Assertions.assertThat(balanceOf(alice)).isEqualTo(BigInteger.valueOf(20));
+    Assertions.assertThat(balanceOf(bob)).isEqualTo(BigInteger.valueOf(276));
+    Assertions.assertThat(balanceOf(feeCollector)).isEqualTo(BigInteger.valueOf(4));
+    Assertions.assertThat(getState().totalSupply()).isEqualTo(TOTAL_SUPPLY);
+  }
+
+  /** The records of a bulk transfer include the deducted transfer fees. */
+  @ContractTest(previous = "setTransferFee")
+  void bulkTransferWithFeeEvents() {
+    List<Token.Recipient> recipients =
+        List.of(
+            new Token.Recipient(bob, BigInteger.valueOf(100)),
+            new Token.Recipient(issuer, BigInteger.valueOf(100)));
+    SafeDataInputStream events =
+        ReturnData.of(
+            blockchain.sendAction(alice, token, Token.bulkTransferWithEvents(recipients)));
+
+    Assertions.assertThat(readTransferEvents(events))
+        .containsExactly(
+            new TransferEvent(alice, bob, BigInteger.valueOf(100), BigInteger.TWO),
+            new TransferEvent(alice, issuer, BigInteger.valueOf(100), BigInteger.ZERO));
+    Assertions.assertThat(balanceOf(bob)).isEqualTo(BigInteger.valueOf(198));
+    Assertions.assertThat(balanceOf(feeCollector)).isEqualTo(BigInteger.TWO);
+  }
+
+  /** Transfers from or to the owner or the fee collector are exempt from the fee. */
+  @ContractTest(previous = "setTransferFee")
+  void transferWithFeeExemptions() {
+    blockchain.sendAction(issuer, token, Token.transfer(alice, BigInteger.valueOf(1000)));
+    Assertions.assertThat(balanceOf(alice)).isEqualTo(BigInteger.valueOf(1200));
+
+    blockchain.sendAction(alice, token, Token.transfer(issuer, BigInteger.valueOf(200)));
+    Assertions.assertThat(balanceOf(issuer)).isEqualTo(BigInteger.valueOf(998_900));
+
+    blockchain.sendAction(alice, token, Token.transfer(feeCollector, BigInteger.valueOf(400)));
+    Assertions.assertThat(balanceOf(feeCollector)).isEqualTo(BigInteger.valueOf(400));
+
+    blockchain.sendAction(feeCollector, token, Token.transfer(bob, BigInteger.valueOf(100)));
+    Assertions.assertThat(balanceOf(bob)).isEqualTo(BigInteger.valueOf(200));
+    Assertions.assertThat(balanceOf(feeCollector)).isEqualTo(BigInteger.valueOf(300));
+    Assertions.assertThat(balanceOf(alice)).isEqualTo(BigInteger.valueOf(600));
+  }
+
+  /** The owner can remove the transfer fee again. */
+  @ContractTest(previous = "setTransferFee")
+  void removeTransferFee() {
+    blockchain.sendAction(issuer, token, Token.setTransferFee(null));
+    blockchain.sendAction(alice, token, Token.transfer(bob, BigInteger.valueOf(100)));
+
+    Assertions.assertThat(getState().transferFee()).isNull();
+    Assertions.assertThat(balanceOf(bob)).isEqualTo(BigInteger.valueOf(200));
+    Assertions.assertThat(balanceOf(feeCollector)).isEqualTo(BigInteger.ZERO);
+  }
+
+  /** Only the owner can set the transfer fee. */
+  @ContractTest(previous = "transfer")
+  void setTransferFeeAsNonOwner() {
+    byte[] setTransferFee = Token.setTransferFee(new Token.TransferFee((short) 25, alice));
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(alice, token, setTransferFee))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Only owner can set the transfer fee");
+  }
+
+  /** The transfer fee cannot exceed the transferred amount. */
+  @ContractTest(previous = "transfer")
+  void setTransferFeeAboveOneThousandPerMille() {
+    byte[] setTransferFee = Token.setTransferFee(new Token.TransferFee((short) 1001, bob));
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(issuer, token, setTransferFee))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Transfer fee must be at most 1000 per mille, but was 1001");
+
+    Assertions.assertThat(getState().transferFee()).isNull();
+  }
+
+  /** Airdropped tokens are distributed proportionally, giving the rounding dust to the largest. */
+  @ContractTest(previous = "transfer")
+  void airdropProportional() {
+    blockchain.sendAction(issuer, token, Token.airdropProportional(BigInteger.valueOf(12_345)));
+
+    Assertions.assertThat(balanceOf(issuer)).isEqualTo(BigInteger.valueOf(1_012_042));
+    Assertions.assertThat(balanceOf(alice)).isEqualTo(BigInteger.valueOf(202));
+    Assertions.assertThat(balanceOf(bob)).isEqualTo(BigInteger.valueOf(101));
+    Assertions.assertThat(getState().totalSupply()).isEqualTo(BigInteger.valueOf(1_012_345));
+  }
+
+  /** Airdrops are exact for balances whose products with the amount exceed 128 bits. */
+  @ContractTest(previous = "deploy")
+  void airdropProportionalWithLargeBalances() {
+    BigInteger unit = BigInteger.TEN.pow(30);
+    List<Token.Allocation> allocation =
+        List.of(
+            new Token.Allocation(alice, unit),
+            new Token.Allocation(bob, unit.multiply(BigInteger.TWO)));
+    BigInteger totalSupply = unit.multiply(BigInteger.valueOf(3));
+    byte[] initRpc = Token.initialize("Large Token", "LAT", (byte) 18, totalSupply, allocation);
+    token = blockchain.deployContract(issuer, TOKEN_CONTRACT_BYTES, initRpc);
+
+    blockchain.sendAction(issuer, token, Token.airdropProportional(unit));
+
+    BigInteger aliceShare = new BigInteger("333333333333333333333333333333");
+    Assertions.assertThat(balanceOf(alice)).isEqualTo(unit.add(aliceShare));
+    Assertions.assertThat(balanceOf(bob))
+        .isEqualTo(unit.multiply(BigInteger.TWO).add(unit).subtract(aliceShare));
+    Assertions.assertThat(getState().totalSupply()).isEqualTo(totalSupply.add(unit));
+  }
+
+  /** Only the owner can airdrop tokens. */
+  @ContractTest(previous = "transfer")
+  void airdropProportionalAsNonOwner() {
+    byte[] airdrop = Token.airdropProportional(BigInteger.valueOf(12_345));
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(alice, token, airdrop))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Only owner can airdrop tokens");
+
+    Assertions.assertThat(getState().totalSupply()).isEqualTo(TOTAL_SUPPLY);
+  }
+
+  /** The owner can snapshot the balances, and deposit a dividend for the holders at it. */
+  @ContractTest(previous = "transfer")
+  void depositDividend() {
+    SafeDataInputStream snapshotId =
+        ReturnData.of(blockchain.sendAction(issuer, token, Token.snapshot()));
+    Assertions.assertThat(snapshotId.readInt()).isEqualTo(0);
+
+    blockchain.sendAction(alice, token, Token.transfer(bob, BigInteger.valueOf(100)));
+    blockchain.sendAction(issuer, token, Token.depositDividend(0, BigInteger.valueOf(500_000)));
+
+    Assertions.assertThat(balanceOf(issuer)).isEqualTo(BigInteger.valueOf(499_700));
+    Assertions.assertThat(balanceOf(token)).isEqualTo(BigInteger.valueOf(500_000));
+    Assertions.assertThat(getState().snapshots().get(0).dividendPool())
+        .isEqualTo(BigInteger.valueOf(500_000));
+  }
+
+  /** Holders claim dividends proportional to their balances at the snapshot. */
+  @ContractTest(previous = "depositDividend")
+  void claimDividends() {
+    blockchain.sendAction(alice, token, Token.claimDividend(0));
+    blockchain.sendAction(bob, token, Token.claimDividend(0));
+    blockchain.sendAction(issuer, token, Token.claimDividend(0));
+
+    Assertions.assertThat(balanceOf(alice)).isEqualTo(BigInteger.valueOf(200));
+    Assertions.assertThat(balanceOf(bob)).isEqualTo(BigInteger.valueOf(250));
+    Assertions.assertThat(balanceOf(issuer)).isEqualTo(BigInteger.valueOf(999_550));
+    Assertions.assertThat(balanceOf(token)).isEqualTo(BigInteger.ZERO);
+    Assertions.assertThat(getState().totalSupply()).isEqualTo(TOTAL_SUPPLY);
+  }
+
+  /** Each holder can claim the dividend of a snapshot only once. */
+  @ContractTest(previous = "depositDividend")
+  void claimDividendTwice() {
+    blockchain.sendAction(alice, token, Token.claimDividend(0));
+
+    byte[] claimDividend = Token.claimDividend(0);
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(alice, token, claimDividend))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Dividend already claimed for snapshot 0");
+
+    Assertions.assertThat(balanceOf(alice)).isEqualTo(BigInteger.valueOf(200));
+  }
+
+  /** Accounts without a balance at the snapshot have no dividend to claim. */
+  @ContractTest(previous = "depositDividend")
+  void claimDividendWithoutBalanceAtSnapshot() {
+    BlockchainAddress newHolder = blockchain.newAccount(9);
+    blockchain.sendAction(bob, token, Token.transfer(newHolder, BigInteger.valueOf(100)));
+
+    byte[] claimDividend = Token.claimDividend(0);
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(newHolder, token, claimDividend))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("No balance at snapshot 0");
+  }
+
+  /** Dividends cannot be claimed before they are deposited, or for unknown snapshots. */
+  @ContractTest(previous = "transfer")
+  void claimDividendWithoutDeposit() {
+    blockchain.sendAction(issuer, token, Token.snapshot());
+
+    byte[] claimDividend = Token.claimDividend(0);
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(alice, token, claimDividend))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("No dividend deposited for snapshot 0");
+
+    byte[] claimUnknownDividend = Token.claimDividend(1);
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(alice, token, claimUnknownDividend))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Unknown snapshot 1");
+  }
+
+  /** The dividend of a snapshot can only be deposited once, as holders may have claimed it. */
+  @ContractTest(previous = "depositDividend")
+  void depositDividendTwice() {
+    byte[] depositDividend = Token.depositDividend(0, BigInteger.valueOf(1000));
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(issuer, token, depositDividend))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Dividend already deposited for snapshot 0");
+  }
+
+  /** Only the owner can take snapshots and deposit dividends. */
+  @ContractTest(previous = "transfer")
+  void snapshotAsNonOwner() {
+    byte[] snapshot = Token.snapshot();
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(alice, token, snapshot))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Only owner can take snapshots");
+
+    blockchain.sendAction(issuer, token, snapshot);
+    byte[] depositDividend = Token.depositDividend(0, BigInteger.valueOf(100));
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(alice, token, depositDividend))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Only owner can deposit dividends");
+  }
+
+  /** The owner can set a guardian allowed to pause the token. */
+  @ContractTest(previous = "transfer")
+  void setGuardian() {
+    guardian = blockchain.newAccount(7);
+    blockchain.sendAction(issuer, token, Token.setGuardian(guardian));
+
+    Assertions.assertThat(getState().guardian()).isEqualTo(guardian);
+    Assertions.assertThat(getState().paused()).isFalse();
+  }
+
+  /** The guardian can pause the token, but only the owner can unpause it. */
+  @ContractTest(previous = "setGuardian")
+  void pauseAsGuardian() {
+    blockchain.sendAction(guardian, token, Token.pause());
+    Assertions.assertThat(getState().paused()).isTrue();
+
+    byte[] unpause = Token.unpause();
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(guardian, token, unpause))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Only owner can unpause the token");
+    Assertions.assertThat(getState().paused()).isTrue();
+
+    blockchain.sendAction(issuer, token, unpause);
+    Assertions.assertThat(getState().paused()).isFalse();
+  }
+
+  /** The owner can both pause and unpause the token. */
+  @ContractTest(previous = "setGuardian")
+  void pauseAndUnpauseAsOwner() {
+    blockchain.sendAction(issuer, token, Token.pause());
+    Assertions.assertThat(getState().paused()).isTrue();
+
+    blockchain.sendAction(issuer, token, Token.unpause());
+    Assertions.assertThat(getState().paused()).isFalse();
+
+    blockchain.sendAction(alice, token, Token.transfer(bob, BigInteger.valueOf(100)));
+    Assertions.assertThat(balanceOf(bob)).isEqualTo(BigInteger.valueOf(200));
+  }
+
+  /** Transfers and burns fail while the token is paused, but approvals are still possible. */
+  @ContractTest(previous = "setGuardian")
+  void transferWhilePaused() {
+    blockchain.sendAction(guardian, token, Token.pause());
+    blockchain.sendAction(alice, token, Token.approve(bob, BigInteger.valueOf(80)));
+
+    byte[] transfer = Token.transfer(bob, BigInteger.valueOf(100));
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(alice, token, transfer))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("The token is paused");
+
+    byte[] transferFrom = Token.transferFrom(alice, bob, BigInteger.valueOf(80));
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(bob, token, transferFrom))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("The token is paused");
+
+    byte[] burn = Token.burn(BigInteger.valueOf(100));
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(alice, token, burn))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("The token is paused");
+
+    Assertions.assertThat(balanceOf(alice)).isEqualTo(BigInteger.valueOf(200));
+    Assertions.assertThat(allowance(alice, bob)).isEqualTo(BigInteger.valueOf(80));
+  }
+
+  /** Accounts other than the owner and the guardian cannot pause the token. */
+  @ContractTest(previous = "setGuardian")
+  void pauseAsOther() {
+    byte[] pause = Token.pause();
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(alice, token, pause))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Only owner or guardian can pause the token");
+
+    Assertions.assertThat(getState().paused()).isFalse();
+  }
+
+  /** Only the owner can set the guardian. */
+  @ContractTest(previous = "setGuardian")
+  void setGuardianAsNonOwner() {
+    byte[] setGuardian = Token.setGuardian(alice);
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(guardian, token, setGuardian))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Only owner can set the guardian");
+
+    Assertions.assertThat(getState().guardian()).isEqualTo(guardian);
+  }
+
+  /** A contract pulling tokens from its users can be deployed. */
+  @ContractTest(previous = "transfer")
+  void deployTokenReceiver() {
+    tokenReceiver =
+        blockchain.deployContract(
+            issuer, TOKEN_RECEIVER_CONTRACT_BYTES, TokenReceiver.initialize(token));
+
+    Assertions.assertThat(getTokenReceiverState().token()).isEqualTo(token);
+  }
+
+  /** The approved spender is called in the same transaction, and can pull the approved tokens. */
+  @ContractTest(previous = "deployTokenReceiver")
+  void approveAndCall() {
+    BigInteger amount = BigInteger.valueOf(150);
+    byte[] depositArguments = depositArguments(alice, amount);
+    blockchain.sendAction(
+        alice,
+        token,
+        Token.approveAndCall(tokenReceiver, amount, DEPOSIT_SHORTNAME, depositArguments));
+
+    Assertions.assertThat(balanceOf(alice)).isEqualTo(BigInteger.valueOf(50));
+    Assertions.assertThat(balanceOf(tokenReceiver)).isEqualTo(BigInteger.valueOf(150));
+    Assertions.assertThat(allowance(alice, tokenReceiver)).isEqualTo(BigInteger.ZERO);
+    Assertions.assertThat(getTokenReceiverState().deposits().get(alice))
+        .isEqualTo(BigInteger.valueOf(150));
+  }
+
+  /** If the call to the spender fails, the allowance is reverted to its previous value. */
+  @ContractTest(previous = "deployTokenReceiver")
+  void approveAndCallWithFailingCall() {
+    blockchain.sendAction(issuer, tokenReceiver, TokenReceiver.setAccepting(false));
+    blockchain.sendAction(alice, token, Token.approve(tokenReceiver, BigInteger.TEN));
+
+    BigInteger amount = BigInteger.valueOf(150);
+    byte[] depositArguments = depositArguments(alice, amount);
+    byte[] approveAndCall =
+        Token.approveAndCall(tokenReceiver, amount, DEPOSIT_SHORTNAME, depositArguments);
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(alice, token, approveAndCall))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Deposits are not accepted");
+
+    Assertions.assertThat(allowance(alice, tokenReceiver)).isEqualTo(BigInteger.TEN);
+    Assertions.assertThat(balanceOf(alice)).isEqualTo(BigInteger.valueOf(200));
+    Assertions.assertThat(getTokenReceiverState().deposits().get(alice)).isNull();
+  }
+
+  /** Transferring to a contract notifies the contract, which can accept the tokens. */
+  @ContractTest(previous = "deployTokenReceiver")
+  void transferAndCall() {
+    byte[] data = new byte[] {1, 2, 3};
+    blockchain.sendAction(
+        alice, token, Token.transferAndCall(tokenReceiver, BigInteger.valueOf(150), data));
+
+    Assertions.assertThat(balanceOf(alice)).isEqualTo(BigInteger.valueOf(50));
+    Assertions.assertThat(balanceOf(tokenReceiver)).isEqualTo(BigInteger.valueOf(150));
+    Assertions.assertThat(getTokenReceiverState().deposits().get(alice))
+        .isEqualTo(BigInteger.valueOf(150));
+  }
+
+  /** If the receiving contract rejects the tokens, the transfer is reverted. */
+  @ContractTest(previous = "deployTokenReceiver")
+  void transferAndCallRejected() {
+    blockchain.sendAction(issuer, tokenReceiver, TokenReceiver.setAccepting(false));
+
+    byte[] transferAndCall =
+        Token.transferAndCall(tokenReceiver, BigInteger.valueOf(150), new byte[0]);
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(alice, token, transferAndCall))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Deposits are not accepted");
+
+    Assertions.assertThat(balanceOf(alice)).isEqualTo(BigInteger.valueOf(200));
+    Assertions.assertThat(balanceOf(tokenReceiver)).isEqualTo(BigInteger.ZERO);
+    Assertions.assertThat(getState().holderCount()).isEqualTo(3L);
+  }
+
+  /** Reverting a rejected transfer also reverts the transfer fee. */
+  @ContractTest(previous = "setTransferFee")
+  void transferAndCallRejectedWithFee() {
+    tokenReceiver =
+        blockchain.deployContract(
+            issuer, TOKEN_RECEIVER_CONTRACT_BYTES, TokenReceiver.initialize(token));
+    blockchain.sendAction(issuer, tokenReceiver, TokenReceiver.setAccepting(false));
+
+    byte[] transferAndCall =
+        Token.transferAndCall(tokenReceiver, BigInteger.valueOf(100), new byte[0]);
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(alice, token, transferAndCall))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Deposits are not accepted");
+
+    Assertions.assertThat(balanceOf(alice)).isEqualTo(BigInteger.valueOf(200));
+    Assertions.assertThat(balanceOf(tokenReceiver)).isEqualTo(BigInteger.ZERO);
+    Assertions.assertThat(balanceOf(feeCollector)).isEqualTo(BigInteger.ZERO);
+  }
+
+  /** Transferring to an account transfers the tokens without calling anything. */
+  @ContractTest(previous = "transfer")
+  void transferAndCallToAccount() {
+    blockchain.sendAction(
+        alice, token, Token.transferAndCall(bob, BigInteger.valueOf(150), new byte[] {1}));
+
+    Assertions.assertThat(balanceOf(alice)).isEqualTo(BigInteger.valueOf(50));
+    Assertions.assertThat(balanceOf(bob)).isEqualTo(BigInteger.valueOf(250));
+  }
+
+  private void assertFormattedAmount(
+      BlockchainAddress tokenAddress,
+      long amount,
+      long integerPart,
+      long fractionalPart,
+      String text) {
+    SafeDataInputStream formatted =
+        ReturnData.of(
+            blockchain.sendAction(
+                alice, tokenAddress, Token.formatAmount(BigInteger.valueOf(amount))));
+    Assertions.assertThat(readU128(formatted)).isEqualTo(BigInteger.valueOf(integerPart));
+    Assertions.assertThat(readU128(formatted)).isEqualTo(BigInteger.valueOf(fractionalPart));
+    Assertions.assertThat(formatted.readString()).isEqualTo(text);
+  }
+
+  private long holderCount() {
+    return ReturnData.of(blockchain.sendAction(alice, token, Token.holderCount())).readLong();
+  }
+
+  private static List<TransferEvent> readTransferEvents(SafeDataInputStream stream) {
+    int count = stream.readInt();
+    List<TransferEvent> events = new ArrayList<>();
+    for (int i = 0; i < count; i++) {
+      events.add(
+          new TransferEvent(
+              BlockchainAddress.read(stream),
+              BlockchainAddress.read(stream),
+              readU128(stream),
+              readU128(stream)));
+    }
+    return events;
+  }
+
+  private static BigInteger readU128(SafeDataInputStream stream) {
+    return new BigInteger(1, stream.readBytes(16));
+  }
+
+  /** Arguments of a deposit on the token receiver, without the shortname of the action. */
+  private static byte[] depositArguments(BlockchainAddress depositor, BigInteger amount) {
+    byte[] rpc = TokenReceiver.deposit(depositor, amount);
+    return Arrays.copyOfRange(rpc, 1, rpc.length);
+  }
+
+  private TokenReceiver.TokenReceiverState getTokenReceiverState() {
+    return new TokenReceiver(getStateClient(), tokenReceiver).getState();
+  }
+
+  private Token.TokenState getState() {
+    return new Token(getStateClient(), token).getState();
+  }
+
+  private BigInteger balanceOf(BlockchainAddress holder) {
+    BigInteger balance = getState().balances().get(holder);
+    return balance == null ? BigInteger.ZERO : balance;
+  }
+
+  private BigInteger allowance(BlockchainAddress owner, BlockchainAddress spender) {
+    BigInteger allowance = getState().allowed().get(new Token.AllowedAddress(owner, spender));
+    return allowance == null ? BigInteger.ZERO : allowance;
+  }
+
+  private record TransferEvent(
+      BlockchainAddress from, BlockchainAddress to, BigInteger amount, BigInteger fee) {}
+}
@@ -6,6 +6,7 @@ import com.partisiablockchain.language.junit.ContractBytes;
 import com.partisiablockchain.language.junit.ContractTest;
 import com.partisiablockchain.language.junit.JunitContractTest;
 import com.partisiablockchain.language.junit.exceptions.ActionFailureException;
+import com.secata.stream.SafeDataInputStream;
 import java.util.List;
 import org.assertj.core.api.Assertions;
 
@@ -64,6 +65,28 @@ public final class VotingTest extends JunitContractTest {
     Assertions.assertThat(state.result()).isTrue();
   }
 
+  /** The tally summarizes the votes cast, and includes the result once the votes are counted. */
+  @ContractTest(previous = "setUp")
+  public void tallyVotes() {
+    blockchain.sendAction(voter1, voting, Voting.vote(true));
+    blockchain.sendAction(voter2, voting, Voting.vote(false));
+
+    SafeDataInputStream tally =
+        ReturnData.of(blockchain.sendAction(voter3, voting, Voting.tally()));
+    Assertions.assertThat(tally.readInt()).isEqualTo(3);
+    Assertions.assertThat(tally.readInt()).isEqualTo(1);
+    Assertions.assertThat(ReturnData.readOption(tally, SafeDataInputStream::readBoolean)).isNull();
+
+    blockchain.waitForBlockProductionTime(2 * 60 * 60 * 1000);
+    blockchain.sendAction(voter1, voting, Voting.count());
+
+    tally = ReturnData.of(blockchain.sendAction(voter3, voting, Voting.tally()));
+    Assertions.assertThat(tally.readInt()).isEqualTo(3);
+    Assertions.assertThat(tally.readInt()).isEqualTo(1);
+    Assertions.assertThat(ReturnData.readOption(tally, SafeDataInputStream::readBoolean))
+        .isFalse();
+  }
+
   /** If the majority votes no, the result of the count is that the proposal fails. */
   @ContractTest(previous = "setUp")
   public void countVotesMajorityAgainst() {
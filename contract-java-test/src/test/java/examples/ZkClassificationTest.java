@@ -79,6 +79,46 @@ public final class ZkClassificationTest extends JunitContractTest {
         .isInstanceOf(RuntimeException.class);
   }
 
+  /** A model of the expected size is accepted, while a model of any other size is rejected. */
+  @ContractTest(previous = "deploy")
+  public void addSecretModelWithWrongSize() {
+    CompactBitArray model = secretModel();
+    CompactBitArray sampleAsModel = secretSample(input);
+    byte[] modelRpc = addModelPublicRpc(conversion);
+
+    Assertions.assertThatThrownBy(
+            () -> blockchain.sendSecretInput(classifier, modelOwner, sampleAsModel, modelRpc))
+        .isInstanceOf(RuntimeException.class)
+        .hasMessageContaining("Expected an input of sizes [176], but got sizes [160]");
+    Assertions.assertThat(blockchain.getContractStateJson(classifier).getNode("/variables").size())
+        .isEqualTo(0);
+
+    blockchain.sendSecretInput(classifier, modelOwner, model, modelRpc);
+    Assertions.assertThat(blockchain.getContractStateJson(classifier).getNode("/variables").size())
+        .isEqualTo(1);
+  }
+
+  /** A sample of the expected size is accepted, while a sample of any other size is rejected. */
+  @ContractTest(previous = "deploy")
+  public void addSecretSampleWithWrongSize() {
+    blockchain.sendSecretInput(
+        classifier, modelOwner, secretModel(), addModelPublicRpc(conversion));
+
+    CompactBitArray tooSmallSample = secretSample(Arrays.copyOf(input, input.length - 1));
+    byte[] sampleRpc = addSamplePublicRpc(1, resultReceiver);
+
+    Assertions.assertThatThrownBy(
+            () -> blockchain.sendSecretInput(classifier, sampleOwner, tooSmallSample, sampleRpc))
+        .isInstanceOf(RuntimeException.class)
+        .hasMessageContaining("Expected an input of sizes [160], but got sizes [144]");
+    Assertions.assertThat(blockchain.getContractStateJson(classifier).getNode("/variables").size())
+        .isEqualTo(1);
+
+    blockchain.sendSecretInput(classifier, sampleOwner, secretSample(input), sampleRpc);
+    Assertions.assertThat(blockchain.getContractStateJson(classifier).getNode("/variables").size())
+        .isEqualTo(3);
+  }
+
   /**
    * ZK computation complexity analysis showing the number of multiplications and the number of
    * rounds needed to evaluate the model on a single input sample.
@@ -129,6 +169,53 @@ public final class ZkClassificationTest extends JunitContractTest {
     Assertions.assertThat(resultOwner).isEqualTo(resultReceiver.writeAsString());
   }
 
+  /** The owner of an inference result can open it, making the predicted class public. */
+  @ContractTest(previous = "deploy")
+  public void ownerOpensResult() {
+    int resultId = evaluateSampleForReceiver();
+
+    Assertions.assertThat(openState().openedResults().size()).isEqualTo(0);
+
+    blockchain.sendAction(resultReceiver, classifier, ZkClassification.openResult(resultId));
+
+    Assertions.assertThat(openState().openedResults().get(resultId)).isTrue();
+    Assertions.assertThat(blockchain.getContractStateJson(classifier).getNode("/variables").size())
+        .isEqualTo(resultId - 1);
+  }
+
+  /** Only the owner of an inference result can open it, so the result stays private. */
+  @ContractTest(previous = "deploy")
+  public void nonOwnerCannotOpenResult() {
+    int resultId = evaluateSampleForReceiver();
+
+    Assertions.assertThatThrownBy(
+            () ->
+                blockchain.sendAction(
+                    sampleOwner, classifier, ZkClassification.openResult(resultId)))
+        .isInstanceOf(RuntimeException.class)
+        .hasMessageContaining("Only the owner of the result can open it");
+
+    Assertions.assertThatThrownBy(
+            () ->
+                blockchain.sendAction(
+                    modelOwner, classifier, ZkClassification.openResult(resultId)))
+        .isInstanceOf(RuntimeException.class)
+        .hasMessageContaining("Only the owner of the result can open it");
+
+    Assertions.assertThat(openState().openedResults().size()).isEqualTo(0);
+  }
+
+  /** The model and input samples are not inference results, and cannot be opened. */
+  @ContractTest(previous = "deploy")
+  public void cannotOpenModel() {
+    evaluateSampleForReceiver();
+
+    Assertions.assertThatThrownBy(
+            () -> blockchain.sendAction(modelOwner, classifier, ZkClassification.openResult(1)))
+        .isInstanceOf(RuntimeException.class)
+        .hasMessageContaining("Only inference results can be opened");
+  }
+
   /**
    * The secret-shared model computes the same output as the same model would in a normal evaluation
    * computed with the same input.
@@ -274,6 +361,25 @@ public final class ZkClassificationTest extends JunitContractTest {
     return predictions;
   }
 
+  /**
+   * Adds the model, and evaluates a sample for the result receiver.
+   *
+   * @return the id of the inference result
+   */
+  private int evaluateSampleForReceiver() {
+    blockchain.sendSecretInput(
+        classifier, modelOwner, secretModel(), addModelPublicRpc(conversion));
+    blockchain.sendSecretInput(
+        classifier, sampleOwner, secretSample(input), addSamplePublicRpc(1, resultReceiver));
+
+    return blockchain.getContractStateJson(classifier).getNode("/variables").size();
+  }
+
+  private ZkClassification.ContractState openState() {
+    return ZkClassification.ZkStateImmutable.deserialize(blockchain.getContractState(classifier))
+        .openState();
+  }
+
   private static void writeInternalVertices(BitOutput output, ZkClassification.Model model) {
     for (ZkClassification.InternalVertex vertex : model.internals()) {
       output.writeUnsignedInt(vertex.feature(), 8);
@@ -7,7 +7,15 @@ import com.partisiablockchain.language.junit.ContractBytes;
 import com.partisiablockchain.language.junit.ContractTest;
 import com.partisiablockchain.language.junit.JunitContractTest;
 import com.partisiablockchain.language.junit.Previous;
+import com.partisiablockchain.language.testenvironment.zk.node.RealNodeClusterInteractions;
 import com.secata.stream.CompactBitArray;
+import com.secata.stream.SafeDataInputStream;
+import com.secata.stream.SafeDataOutputStream;
+import java.util.ArrayList;
+import java.util.Arrays;
+import java.util.LinkedHashMap;
+import java.util.List;
+import java.util.Map;
 import java.util.Random;
 import java.util.stream.Stream;
 import org.assertj.core.api.Assertions;
@@ -25,19 +33,29 @@ public final class ZkFileShareTest extends JunitContractTest {
   private BlockchainAddress contractOwner;
   private BlockchainAddress initialUser;
   private BlockchainAddress secondUser;
+  private BlockchainAddress thirdUser;
 
   private BlockchainAddress fileShareAddress;
 
   private static final Random rand = new Random();
 
+  /**
+   * Maximum length in bytes of files that can be shared with multiple owners. Shareable files are
+   * padded to exactly this length when uploaded.
+   */
+  private static final int MAX_DUPLICATABLE_FILE_LENGTH = 256;
+
+  private RealNodeClusterInteractions zkNodes;
+
   /** Test that the contract can be correctly deployed. */
   @ContractTest
   void deploy() {
     contractOwner = blockchain.newAccount(17);
     initialUser = blockchain.newAccount(19);
     secondUser = blockchain.newAccount(23);
+    thirdUser = blockchain.newAccount(29);
 
-    blockchain.addRealv1MpcNodes();
+    zkNodes = blockchain.addRealv1MpcNodes();
 
     byte[] initRpc = ZkFileShare.initialize();
     fileShareAddress = blockchain.deployZkContract(contractOwner, FILE_SHARE_BYTES, initRpc);
@@ -146,6 +164,289 @@ public final class ZkFileShareTest extends JunitContractTest {
     assertSecretVariableOwner(1, secondUser);
   }
 
+  /**
+   * The owner of a file can share it with multiple new owners, who each receive their own copy of
+   * the file. The original file is deleted when it should not be kept.
+   */
+  @ContractTest(previous = "deploy")
+  void shareFileWithMultipleOwners() {
+    uploadDuplicatableFile(initialUser);
+
+    byte[] shareRpc = ZkFileShare.shareFileWithOwners(1, List.of(secondUser, thirdUser), false);
+    blockchain.sendAction(initialUser, fileShareAddress, shareRpc);
+
+    assertSecretVariablesAmount(2);
+    Assertions.assertThat(variableIdsOwnedBy(initialUser)).isEmpty();
+    Assertions.assertThat(variableIdsOwnedBy(secondUser)).hasSize(1);
+    Assertions.assertThat(variableIdsOwnedBy(thirdUser)).hasSize(1);
+  }
+
+  /** Each new owner of a shared file can delete their own copy independently of the others. */
+  @ContractTest(previous = "shareFileWithMultipleOwners")
+  void deleteSharedCopiesIndependently() {
+    int secondUserCopy = variableIdsOwnedBy(secondUser).get(0);
+    int thirdUserCopy = variableIdsOwnedBy(thirdUser).get(0);
+
+    blockchain.sendAction(secondUser, fileShareAddress, ZkFileShare.deleteFile(secondUserCopy));
+
+    assertSecretVariablesAmount(1);
+    assertSecretVariableOwner(thirdUserCopy, thirdUser);
+
+    blockchain.sendAction(thirdUser, fileShareAddress, ZkFileShare.deleteFile(thirdUserCopy));
+
+    assertSecretVariablesAmount(0);
+  }
+
+  /** The owner of a file can keep the original file when sharing it with new owners. */
+  @ContractTest(previous = "deploy")
+  void shareFileAndKeepOriginal() {
+    uploadDuplicatableFile(initialUser);
+
+    byte[] shareRpc = ZkFileShare.shareFileWithOwners(1, List.of(secondUser, thirdUser), true);
+    blockchain.sendAction(initialUser, fileShareAddress, shareRpc);
+
+    assertSecretVariablesAmount(3);
+    assertSecretVariableOwner(1, initialUser);
+    Assertions.assertThat(variableIdsOwnedBy(secondUser)).hasSize(1);
+    Assertions.assertThat(variableIdsOwnedBy(thirdUser)).hasSize(1);
+  }
+
+  /** A user cannot share a file which they do not own. */
+  @ContractTest(previous = "deploy")
+  void shareNonOwnedFile() {
+    uploadDuplicatableFile(initialUser);
+
+    byte[] shareRpc = ZkFileShare.shareFileWithOwners(1, List.of(secondUser, thirdUser), false);
+    Assertions.assertThatCode(() -> blockchain.sendAction(secondUser, fileShareAddress, shareRpc))
+        .isInstanceOf(RuntimeException.class)
+        .hasMessageContaining("Only the owner of the secret file is allowed to share it.");
+
+    assertSecretVariablesAmount(1);
+    assertSecretVariableOwner(1, initialUser);
+  }
+
+  /** Files longer than the maximum duplicatable length cannot be uploaded as shareable. */
+  @ContractTest(previous = "deploy")
+  void uploadShareableFileTooLong() {
+    Assertions.assertThatCode(
+            () ->
+                uploadShareableFile(
+                    initialUser, MAX_DUPLICATABLE_FILE_LENGTH + 1, "large.bin", "application/zip"))
+        .isInstanceOf(RuntimeException.class)
+        .hasMessageContaining(
+            "Only files of at most %d bytes can be shared with multiple owners.",
+            MAX_DUPLICATABLE_FILE_LENGTH);
+
+    assertSecretVariablesAmount(0);
+  }
+
+  /** Files which were not uploaded as shareable cannot be shared with multiple owners. */
+  @ContractTest(previous = "deploy")
+  void shareNonShareableFile() {
+    uploadFile(initialUser, 10, "short.txt", "text/plain");
+
+    byte[] shareRpc = ZkFileShare.shareFileWithOwners(1, List.of(secondUser, thirdUser), false);
+    Assertions.assertThatCode(() -> blockchain.sendAction(initialUser, fileShareAddress, shareRpc))
+        .isInstanceOf(RuntimeException.class)
+        .hasMessageContaining(
+            "Only files uploaded as shareable can be shared with multiple owners.");
+
+    assertSecretVariablesAmount(1);
+    assertSecretVariableOwner(1, initialUser);
+  }
+
+  /**
+   * Files shorter than the maximum duplicatable length can be shared with multiple owners, and the
+   * copies keep the real size of the file.
+   */
+  @ContractTest(previous = "deploy")
+  void shareShortFileWithMultipleOwners() {
+    uploadShareableFile(initialUser, 10, "short.txt", "text/plain");
+
+    byte[] shareRpc = ZkFileShare.shareFileWithOwners(1, List.of(secondUser, thirdUser), false);
+    blockchain.sendAction(initialUser, fileShareAddress, shareRpc);
+
+    assertSecretVariablesAmount(2);
+    ZkFileShare.FileMetadata expected = new ZkFileShare.FileMetadata("short.txt", "text/plain", 10);
+    Assertions.assertThat(getState().fileMetadata().get(variableIdsOwnedBy(secondUser).get(0)))
+        .isEqualTo(expected);
+    Assertions.assertThat(getState().fileMetadata().get(variableIdsOwnedBy(thirdUser).get(0)))
+        .isEqualTo(expected);
+  }
+
+  /**
+   * A file cannot be deleted or transferred while it is being shared with multiple owners, as the
+   * remaining copies are made from it. Once all copies have been made, the sharing is complete.
+   */
+  @ContractTest(previous = "deploy")
+  void deleteOrTransferFileBeingShared() {
+    uploadDuplicatableFile(initialUser);
+    zkNodes.stop();
+
+    byte[] shareRpc = ZkFileShare.shareFileWithOwners(1, List.of(secondUser, thirdUser), true);
+    blockchain.sendAction(initialUser, fileShareAddress, shareRpc);
+
+    Assertions.assertThatCode(
+            () -> blockchain.sendAction(initialUser, fileShareAddress, ZkFileShare.deleteFile(1)))
+        .isInstanceOf(RuntimeException.class)
+        .hasMessageContaining("The file is currently being shared with multiple owners.");
+    byte[] transferRpc = ZkFileShare.changeFileOwner(1, secondUser);
+    Assertions.assertThatCode(
+            () -> blockchain.sendAction(initialUser, fileShareAddress, transferRpc))
+        .isInstanceOf(RuntimeException.class)
+        .hasMessageContaining("The file is currently being shared with multiple owners.");
+
+    zkNodes.zkCompute(fileShareAddress);
+    zkNodes.zkCompute(fileShareAddress);
+
+    assertSecretVariablesAmount(3);
+    assertSecretVariableOwner(1, initialUser);
+    Assertions.assertThat(variableIdsOwnedBy(secondUser)).hasSize(1);
+    Assertions.assertThat(variableIdsOwnedBy(thirdUser)).hasSize(1);
+    Assertions.assertThat(getState().duplicationQueue()).isEmpty();
+
+    blockchain.sendAction(initialUser, fileShareAddress, ZkFileShare.deleteFile(1));
+    assertSecretVariablesAmount(2);
+  }
+
+  /**
+   * Different users can share their files while another file is being shared. The sharings are
+   * queued, and completed in the order the files were shared.
+   */
+  @ContractTest(previous = "deploy")
+  void shareFilesWhileAnotherFileIsBeingShared() {
+    uploadDuplicatableFile(initialUser);
+    uploadDuplicatableFile(secondUser);
+    zkNodes.stop();
+
+    byte[] firstShareRpc = ZkFileShare.shareFileWithOwners(1, List.of(thirdUser), false);
+    blockchain.sendAction(initialUser, fileShareAddress, firstShareRpc);
+    byte[] secondShareRpc = ZkFileShare.shareFileWithOwners(2, List.of(thirdUser), true);
+    blockchain.sendAction(secondUser, fileShareAddress, secondShareRpc);
+
+    Assertions.assertThat(getState().duplicationQueue()).hasSize(2);
+
+    zkNodes.zkCompute(fileShareAddress);
+
+    Assertions.assertThat(variableIdsOwnedBy(initialUser)).isEmpty();
+    Assertions.assertThat(variableIdsOwnedBy(thirdUser)).hasSize(1);
+    assertSecretVariableOwner(2, secondUser);
+    Assertions.assertThat(getState().duplicationQueue()).hasSize(1);
+
+    zkNodes.zkCompute(fileShareAddress);
+
+    assertSecretVariablesAmount(3);
+    assertSecretVariableOwner(2, secondUser);
+    Assertions.assertThat(variableIdsOwnedBy(thirdUser)).hasSize(2);
+    Assertions.assertThat(getState().duplicationQueue()).isEmpty();
+  }
+
+  /** A file cannot be shared again while it is waiting to be shared. */
+  @ContractTest(previous = "deploy")
+  void shareFileAlreadyBeingShared() {
+    uploadDuplicatableFile(initialUser);
+    zkNodes.stop();
+
+    byte[] shareRpc = ZkFileShare.shareFileWithOwners(1, List.of(secondUser), true);
+    blockchain.sendAction(initialUser, fileShareAddress, shareRpc);
+
+    Assertions.assertThatCode(() -> blockchain.sendAction(initialUser, fileShareAddress, shareRpc))
+        .isInstanceOf(RuntimeException.class)
+        .hasMessageContaining("The file is currently being shared with multiple owners.");
+    Assertions.assertThat(getState().duplicationQueue()).hasSize(1);
+  }
+
+  /** The public metadata of uploaded files is stored in the state, while the content is secret. */
+  @ContractTest(previous = "deploy")
+  void uploadFilesWithMetadata() {
+    uploadFile(initialUser, 100, "report.pdf", "application/pdf");
+    uploadFile(initialUser, 200, "notes.txt", "text/plain");
+    uploadFile(secondUser, 50, "image.png", "image/png");
+
+    assertSecretVariablesAmount(3);
+    Assertions.assertThat(getState().fileMetadata())
+        .containsExactlyInAnyOrderEntriesOf(
+            Map.of(
+                1, new ZkFileShare.FileMetadata("report.pdf", "application/pdf", 100),
+                2, new ZkFileShare.FileMetadata("notes.txt", "text/plain", 200),
+                3, new ZkFileShare.FileMetadata("image.png", "image/png", 50)));
+    Assertions.assertThat(getState().pendingUploads()).isEmpty();
+  }
+
+  /**
+   * The metadata of uploads which have not yet been confirmed is kept until the files are uploaded,
+   * and the files cannot be shared while they are still being uploaded.
+   */
+  @ContractTest(previous = "deploy")
+  void shareFileStillUploading() {
+    zkNodes.stop();
+    uploadDuplicatableFile(initialUser);
+    uploadFile(secondUser, 50, "image.png", "image/png");
+    Assertions.assertThat(getState().pendingUploads()).hasSize(2);
+
+    byte[] shareRpc = ZkFileShare.shareFileWithOwners(1, List.of(secondUser), false);
+    Assertions.assertThatCode(() -> blockchain.sendAction(initialUser, fileShareAddress, shareRpc))
+        .isInstanceOf(RuntimeException.class)
+        .hasMessageContaining("No uploaded file with id 1. The file may still be uploading.");
+
+    zkNodes.confirmInput(zkNodes.getPendingInputs(fileShareAddress).get(0));
+    zkNodes.confirmInput(zkNodes.getPendingInputs(fileShareAddress).get(0));
+
+    assertSecretVariablesAmount(2);
+    Assertions.assertThat(getState().pendingUploads()).isEmpty();
+    Assertions.assertThat(getState().fileMetadata()).containsOnlyKeys(1, 2);
+  }
+
+  /** The files of a user can be listed together with their metadata. */
+  @ContractTest(previous = "uploadFilesWithMetadata")
+  void listFilesOfUser() {
+    Assertions.assertThat(filesOf(initialUser))
+        .containsExactly(
+            Map.entry(1, new ZkFileShare.FileMetadata("report.pdf", "application/pdf", 100)),
+            Map.entry(2, new ZkFileShare.FileMetadata("notes.txt", "text/plain", 200)));
+    Assertions.assertThat(filesOf(secondUser))
+        .containsExactly(Map.entry(3, new ZkFileShare.FileMetadata("image.png", "image/png", 50)));
+    Assertions.assertThat(filesOf(thirdUser)).isEmpty();
+  }
+
+  /** The metadata of a file is removed when the file is deleted. */
+  @ContractTest(previous = "uploadFilesWithMetadata")
+  void deleteFileRemovesMetadata() {
+    blockchain.sendAction(initialUser, fileShareAddress, ZkFileShare.deleteFile(1));
+
+    assertSecretVariablesAmount(2);
+    Assertions.assertThat(getState().fileMetadata()).containsOnlyKeys(2, 3);
+  }
+
+  /** The metadata of a file is kept when the ownership of the file changes. */
+  @ContractTest(previous = "uploadFilesWithMetadata")
+  void changeFileOwnershipKeepsMetadata() {
+    blockchain.sendAction(initialUser, fileShareAddress, ZkFileShare.changeFileOwner(1, thirdUser));
+
+    assertSecretVariableOwner(1, thirdUser);
+    Assertions.assertThat(getState().fileMetadata().get(1))
+        .isEqualTo(new ZkFileShare.FileMetadata("report.pdf", "application/pdf", 100));
+  }
+
+  /** Copies of a file shared with multiple owners keep the metadata of the original file. */
+  @ContractTest(previous = "deploy")
+  void sharedCopiesKeepMetadata() {
+    uploadShareableFile(
+        initialUser, MAX_DUPLICATABLE_FILE_LENGTH, "secret.bin", "application/octet-stream");
+
+    byte[] shareRpc = ZkFileShare.shareFileWithOwners(1, List.of(secondUser, thirdUser), false);
+    blockchain.sendAction(initialUser, fileShareAddress, shareRpc);
+
+    ZkFileShare.FileMetadata expected =
+        new ZkFileShare.FileMetadata(
+            "secret.bin", "application/octet-stream", MAX_DUPLICATABLE_FILE_LENGTH);
+    int secondUserCopy = variableIdsOwnedBy(secondUser).get(0);
+    int thirdUserCopy = variableIdsOwnedBy(thirdUser).get(0);
+    Assertions.assertThat(getState().fileMetadata())
+        .containsExactlyInAnyOrderEntriesOf(
+            Map.of(secondUserCopy, expected, thirdUserCopy, expected));
+  }
+
   private Stream<Arguments> fileAndGasSizes() {
     return Stream.of(
         Arguments.arguments(0, 12_000),
@@ -159,14 +460,66 @@ public final class ZkFileShareTest extends JunitContractTest {
         Arguments.arguments(1240, 460_000L));
   }
 
+  private void uploadDuplicatableFile(BlockchainAddress owner) {
+    uploadShareableFile(
+        owner, MAX_DUPLICATABLE_FILE_LENGTH, "file.bin", "application/octet-stream");
+  }
+
   private byte[] publicRpc(int fileSize) {
-    return new byte[] {
-      0x42,
-      (byte) (fileSize >> 24),
-      (byte) (fileSize >> 16),
-      (byte) (fileSize >> 8),
-      (byte) fileSize,
-    };
+    return publicRpc(0x42, fileSize, "file.bin", "application/octet-stream");
+  }
+
+  private byte[] publicRpc(int shortname, int fileSize, String filename, String mimeType) {
+    return SafeDataOutputStream.serialize(
+        s -> {
+          s.writeByte(shortname);
+          s.writeInt(fileSize);
+          s.writeString(filename);
+          s.writeString(mimeType);
+        });
+  }
+
+  private void uploadFile(BlockchainAddress owner, int fileSize, String filename, String mimeType) {
+    byte[] file = randomBytesOfLength(fileSize);
+    blockchain.sendSecretInput(
+        fileShareAddress,
+        owner,
+        new CompactBitArray(file, fileSize * 8),
+        publicRpc(0x42, fileSize, filename, mimeType),
+        200_000);
+  }
+
+  /** Uploads a random file which can be shared, padded with zeros as required by the contract. */
+  private void uploadShareableFile(
+      BlockchainAddress owner, int fileSize, String filename, String mimeType) {
+    byte[] file = Arrays.copyOf(randomBytesOfLength(fileSize), MAX_DUPLICATABLE_FILE_LENGTH);
+    blockchain.sendSecretInput(
+        fileShareAddress,
+        owner,
+        new CompactBitArray(file, MAX_DUPLICATABLE_FILE_LENGTH * 8),
+        publicRpc(0x44, fileSize, filename, mimeType),
+        105_000);
+  }
+
+  /** Lists the files of the given owner by their ids, in the order returned by {@code files_of}. */
+  private Map<Integer, ZkFileShare.FileMetadata> filesOf(BlockchainAddress owner) {
+    SafeDataInputStream files =
+        ReturnData.of(
+            blockchain.sendAction(secondUser, fileShareAddress, ZkFileShare.filesOf(owner)));
+    int numberOfFiles = files.readInt();
+    Map<Integer, ZkFileShare.FileMetadata> entries = new LinkedHashMap<>();
+    for (int i = 0; i < numberOfFiles; i++) {
+      int fileId = files.readInt();
+      ZkFileShare.FileMetadata metadata =
+          new ZkFileShare.FileMetadata(files.readString(), files.readString(), files.readInt());
+      entries.put(fileId, metadata);
+    }
+    return entries;
+  }
+
+  private ZkFileShare.CollectionState getState() {
+    return ZkFileShare.ZkStateImmutable.deserialize(blockchain.getContractState(fileShareAddress))
+        .openState();
   }
 
   private byte[] randomBytesOfLength(int length) {
@@ -197,4 +550,17 @@ public final class ZkFileShareTest extends JunitContractTest {
     String assertOwnerString = assertOwner.writeAsString();
     Assertions.assertThat(realOwner).isEqualTo(assertOwnerString);
   }
+
+  private List<Integer> variableIdsOwnedBy(BlockchainAddress owner) {
+    List<Integer> ids = new ArrayList<>();
+    JsonNode variablesNode =
+        blockchain.getContractStateJson(fileShareAddress).getNode("/variables");
+    for (int i = 0; i < variablesNode.size(); i++) {
+      JsonNode variable = variablesNode.get(i).get("value");
+      if (variable.get("owner").asText().equals(owner.writeAsString())) {
+        ids.add(variable.get("id").asInt());
+      }
+    }
+    return ids;
+  }
 }
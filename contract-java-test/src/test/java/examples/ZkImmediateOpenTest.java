@@ -5,6 +5,8 @@ import com.partisiablockchain.language.abicodegen.ZkImmediateOpen;
 import com.partisiablockchain.language.junit.ContractBytes;
 import com.partisiablockchain.language.junit.ContractTest;
 import com.partisiablockchain.language.junit.JunitContractTest;
+import com.partisiablockchain.language.junit.exceptions.ActionFailureException;
+import com.partisiablockchain.language.junit.exceptions.SecretInputFailureException;
 import com.partisiablockchain.language.testenvironment.zk.node.RealNodeClusterInteractions;
 import com.partisiablockchain.language.testenvironment.zk.node.task.PendingOpenId;
 import com.partisiablockchain.language.testenvironment.zk.node.task.VariableId;
@@ -33,7 +35,7 @@ public final class ZkImmediateOpenTest extends JunitContractTest {
 
     zkNodes = blockchain.addRealv1MpcNodes();
 
-    byte[] initRpc = ZkImmediateOpen.initialize();
+    byte[] initRpc = ZkImmediateOpen.initialize(32, false);
 
     immediateOpen = blockchain.deployZkContract(account1, IMMEDIATE_OPEN_BYTES, initRpc);
 
@@ -55,7 +57,7 @@ public final class ZkImmediateOpenTest extends JunitContractTest {
         ZkImmediateOpen.ZkStateImmutable.deserialize(blockchain.getContractState(immediateOpen))
             .openState();
 
-    Assertions.assertThat(state.openedInputs().get(0)).isEqualTo(10);
+    Assertions.assertThat(state.openedInputs().get(0)).isEqualTo(10L);
 
     blockchain.sendSecretInput(immediateOpen, account2, createSecretInput(11), secretInputRpc());
     blockchain.sendSecretInput(immediateOpen, account2, createSecretInput(12), secretInputRpc());
@@ -67,11 +69,11 @@ public final class ZkImmediateOpenTest extends JunitContractTest {
         ZkImmediateOpen.ZkStateImmutable.deserialize(blockchain.getContractState(immediateOpen))
             .openState();
 
-    Assertions.assertThat(state.openedInputs().get(1)).isEqualTo(11);
-    Assertions.assertThat(state.openedInputs().get(2)).isEqualTo(12);
-    Assertions.assertThat(state.openedInputs().get(3)).isEqualTo(13);
-    Assertions.assertThat(state.openedInputs().get(4)).isEqualTo(14);
-    Assertions.assertThat(state.openedInputs().get(5)).isEqualTo(15);
+    Assertions.assertThat(state.openedInputs().get(1)).isEqualTo(11L);
+    Assertions.assertThat(state.openedInputs().get(2)).isEqualTo(12L);
+    Assertions.assertThat(state.openedInputs().get(3)).isEqualTo(13L);
+    Assertions.assertThat(state.openedInputs().get(4)).isEqualTo(14L);
+    Assertions.assertThat(state.openedInputs().get(5)).isEqualTo(15L);
   }
 
   /** A user can remove all publicized user inputs. */
@@ -85,7 +87,7 @@ public final class ZkImmediateOpenTest extends JunitContractTest {
             .openState();
 
     Assertions.assertThat(state.openedInputs().size()).isEqualTo(1);
-    Assertions.assertThat(state.openedInputs().get(0)).isEqualTo(10);
+    Assertions.assertThat(state.openedInputs().get(0)).isEqualTo(10L);
 
     // Reset state
     byte[] resetState = ZkImmediateOpen.resetContract();
@@ -125,14 +127,186 @@ public final class ZkImmediateOpenTest extends JunitContractTest {
             .openState();
 
     Assertions.assertThat(state.openedInputs().size()).isEqualTo(1);
-    Assertions.assertThat(state.openedInputs().get(0)).isEqualTo(1);
+    Assertions.assertThat(state.openedInputs().get(0)).isEqualTo(1L);
+  }
+
+  /** Inputs of 8 bits are opened and sign-extended, when the contract is configured for 8 bits. */
+  @ContractTest
+  void sendEightBitSecretInput() {
+    deployWithBitLength(8);
+
+    blockchain.sendSecretInput(
+        immediateOpen, account2, createSecretInput(-5, 8), secretInputRpc(0x43));
+    blockchain.sendSecretInput(
+        immediateOpen, account2, createSecretInput(127, 8), secretInputRpc(0x43));
+
+    ZkImmediateOpen.ContractState state =
+        ZkImmediateOpen.ZkStateImmutable.deserialize(blockchain.getContractState(immediateOpen))
+            .openState();
+
+    Assertions.assertThat(state.openedInputs()).containsExactly(-5L, 127L);
+  }
+
+  /** Inputs of 64 bits are opened, when the contract is configured for 64 bits. */
+  @ContractTest
+  void sendSixtyFourBitSecretInput() {
+    deployWithBitLength(64);
+
+    blockchain.sendSecretInput(
+        immediateOpen, account2, createSecretInput(Long.MAX_VALUE, 64), secretInputRpc(0x45));
+    blockchain.sendSecretInput(
+        immediateOpen, account2, createSecretInput(Long.MIN_VALUE, 64), secretInputRpc(0x45));
+
+    ZkImmediateOpen.ContractState state =
+        ZkImmediateOpen.ZkStateImmutable.deserialize(blockchain.getContractState(immediateOpen))
+            .openState();
+
+    Assertions.assertThat(state.openedInputs()).containsExactly(Long.MAX_VALUE, Long.MIN_VALUE);
+  }
+
+  /** Inputs with another bit length than the configured one are rejected. */
+  @ContractTest
+  void sendSecretInputWithWrongBitLength() {
+    deployWithBitLength(8);
+
+    Assertions.assertThatThrownBy(
+            () ->
+                blockchain.sendSecretInput(
+                    immediateOpen, account2, createSecretInput(10), secretInputRpc()))
+        .isInstanceOf(SecretInputFailureException.class)
+        .hasMessageContaining("Secret input must be 8 bits, but was 32 bits");
+  }
+
+  /** In running-sum mode, only the running total of the inputs is opened. */
+  @ContractTest
+  void sendSecretInputsInRunningSumMode() {
+    deploy(32, true);
+
+    blockchain.sendSecretInput(immediateOpen, account2, createSecretInput(10), secretInputRpc());
+    blockchain.sendSecretInput(immediateOpen, account1, createSecretInput(-3), secretInputRpc());
+    blockchain.sendSecretInput(immediateOpen, account2, createSecretInput(25), secretInputRpc());
+
+    ZkImmediateOpen.ContractState state =
+        ZkImmediateOpen.ZkStateImmutable.deserialize(blockchain.getContractState(immediateOpen))
+            .openState();
+
+    Assertions.assertThat(state.openedInputs()).containsExactly(10L, 7L, 32L);
+    Assertions.assertThat(
+            blockchain.getContractStateJson(immediateOpen).getNode("/variables").size())
+        .isEqualTo(1);
+  }
+
+  /**
+   * In running-sum mode, an input which arrives while the previous total is being opened is queued,
+   * and added to the new total once it has been opened.
+   */
+  @ContractTest
+  void sendSecretInputWhileOpeningRunningSum() {
+    deploy(32, true);
+    blockchain.sendSecretInput(immediateOpen, account2, createSecretInput(10), secretInputRpc());
+
+    zkNodes.stop();
+    blockchain.sendSecretInput(immediateOpen, account1, createSecretInput(5), secretInputRpc());
+    zkNodes.confirmInput(zkNodes.getPendingInputs(immediateOpen).get(0));
+    zkNodes.zkCompute(immediateOpen);
+
+    blockchain.sendSecretInput(immediateOpen, account2, createSecretInput(7), secretInputRpc());
+    zkNodes.confirmInput(zkNodes.getPendingInputs(immediateOpen).get(0));
+
+    for (PendingOpenId pendingOpen : zkNodes.getPendingOpens(immediateOpen)) {
+      zkNodes.openVariable(pendingOpen);
+    }
+    zkNodes.zkCompute(immediateOpen);
+    for (PendingOpenId pendingOpen : zkNodes.getPendingOpens(immediateOpen)) {
+      zkNodes.openVariable(pendingOpen);
+    }
+
+    ZkImmediateOpen.ContractState state =
+        ZkImmediateOpen.ZkStateImmutable.deserialize(blockchain.getContractState(immediateOpen))
+            .openState();
+
+    Assertions.assertThat(state.openedInputs()).containsExactly(10L, 15L, 22L);
+    Assertions.assertThat(state.queuedVariableIds()).isEmpty();
+    Assertions.assertThat(
+            blockchain.getContractStateJson(immediateOpen).getNode("/variables").size())
+        .isEqualTo(1);
+  }
+
+  /**
+   * In running-sum mode, the total has the same bit length as the inputs, and wraps around on
+   * overflow. Each input can still be recovered as the difference between consecutive totals.
+   */
+  @ContractTest
+  void runningSumWrapsAroundOnOverflow() {
+    deploy(8, true);
+
+    blockchain.sendSecretInput(
+        immediateOpen, account2, createSecretInput(100, 8), secretInputRpc(0x43));
+    blockchain.sendSecretInput(
+        immediateOpen, account1, createSecretInput(50, 8), secretInputRpc(0x43));
+
+    ZkImmediateOpen.ContractState state =
+        ZkImmediateOpen.ZkStateImmutable.deserialize(blockchain.getContractState(immediateOpen))
+            .openState();
+
+    Assertions.assertThat(state.openedInputs()).containsExactly(100L, -106L);
+    Assertions.assertThat((byte) (state.openedInputs().get(1) - state.openedInputs().get(0)))
+        .isEqualTo((byte) 50);
+  }
+
+  /** Resetting the contract in running-sum mode starts a new running total. */
+  @ContractTest(previous = "sendSecretInputsInRunningSumMode")
+  void resetRunningSum() {
+    blockchain.sendAction(account1, immediateOpen, ZkImmediateOpen.resetContract());
+    blockchain.sendSecretInput(immediateOpen, account2, createSecretInput(4), secretInputRpc());
+
+    ZkImmediateOpen.ContractState state =
+        ZkImmediateOpen.ZkStateImmutable.deserialize(blockchain.getContractState(immediateOpen))
+            .openState();
+
+    Assertions.assertThat(state.openedInputs()).containsExactly(4L);
+  }
+
+  /** The contract cannot be deployed with an unsupported bit length. */
+  @ContractTest
+  void deployWithUnsupportedBitLength() {
+    account1 = blockchain.newAccount(2);
+    blockchain.addRealv1MpcNodes();
+
+    byte[] initRpc = ZkImmediateOpen.initialize(12, false);
+
+    Assertions.assertThatThrownBy(
+            () -> blockchain.deployZkContract(account1, IMMEDIATE_OPEN_BYTES, initRpc))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Unsupported bit length 12. Must be one of [8, 16, 32, 64]");
+  }
+
+  private void deployWithBitLength(int bitLength) {
+    deploy(bitLength, false);
+  }
+
+  private void deploy(int bitLength, boolean runningSum) {
+    account1 = blockchain.newAccount(2);
+    account2 = blockchain.newAccount(3);
+    zkNodes = blockchain.addRealv1MpcNodes();
+
+    byte[] initRpc = ZkImmediateOpen.initialize(bitLength, runningSum);
+    immediateOpen = blockchain.deployZkContract(account1, IMMEDIATE_OPEN_BYTES, initRpc);
   }
 
   private CompactBitArray createSecretInput(Integer secret) {
-    return BitOutput.serializeBits(output -> output.writeSignedInt(secret, 32));
+    return createSecretInput(secret, 32);
+  }
+
+  private CompactBitArray createSecretInput(long secret, int bitLength) {
+    return BitOutput.serializeBits(output -> output.writeSignedLong(secret, bitLength));
   }
 
   byte[] secretInputRpc() {
-    return new byte[] {0x40};
+    return secretInputRpc(0x40);
+  }
+
+  byte[] secretInputRpc(int shortname) {
+    return new byte[] {(byte) shortname};
   }
 }
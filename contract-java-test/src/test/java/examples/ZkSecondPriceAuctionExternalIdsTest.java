@@ -26,6 +26,12 @@ public final class ZkSecondPriceAuctionExternalIdsTest extends JunitContractTest
 
   private RealNodeClusterInteractions zkNodes;
 
+  /** Maximum number of bids accepted by the deployed auction. */
+  private static final int MAX_NUM_BIDDERS = 6;
+
+  /** Time in UTC millis at which bidding closes for the deployed auction. */
+  private static final long BIDDING_DEADLINE = 60 * 60 * 1000;
+
   /** Deploy auction contract. */
   @ContractTest
   void deploy() {
@@ -36,12 +42,31 @@ public final class ZkSecondPriceAuctionExternalIdsTest extends JunitContractTest
 
     auctionContractAddress =
         blockchain.deployZkContract(
-            owner, CONTRACT_BYTES, ZkSecondPriceAuctionExternalIds.initialize());
+            owner,
+            CONTRACT_BYTES,
+            ZkSecondPriceAuctionExternalIds.initialize(MAX_NUM_BIDDERS, BIDDING_DEADLINE));
     auctionContract = new ZkSecondPriceAuctionExternalIds(getStateClient(), auctionContractAddress);
 
     ZkSecondPriceAuctionExternalIds.ContractState state = auctionContract.getState().openState();
 
     Assertions.assertThat(state).isNotNull();
+    Assertions.assertThat(state.maxNumBidders()).isEqualTo(MAX_NUM_BIDDERS);
+    Assertions.assertThat(state.biddingDeadlineUtcMillis()).isEqualTo(BIDDING_DEADLINE);
+  }
+
+  /** The auction cannot be deployed with a maximum below the minimum number of bidders. */
+  @ContractTest
+  void deployWithTooLowMaximumNumberOfBidders() {
+    owner = blockchain.newAccount(999);
+    blockchain.addRealv1MpcNodes();
+
+    Assertions.assertThatCode(
+            () ->
+                blockchain.deployZkContract(
+                    owner,
+                    CONTRACT_BYTES,
+                    ZkSecondPriceAuctionExternalIds.initialize(2, BIDDING_DEADLINE)))
+        .hasMessageContaining("Maximum number of bidders must be at least 3, but was 2");
   }
 
   /** Contract owner can register which users can bid on the contract. */
@@ -80,9 +105,10 @@ public final class ZkSecondPriceAuctionExternalIdsTest extends JunitContractTest
     bidOnAuction(accounts.get(6), 256);
   }
 
-  /** Contract owner can start the winner computation at any time. */
+  /** Contract owner can start the winner computation once the bidding deadline has passed. */
   @ContractTest(previous = "placeBidsOnContract")
   void startAuctionOnContract() {
+    blockchain.waitForBlockProductionTime(BIDDING_DEADLINE);
     startAuction(owner);
 
     ZkSecondPriceAuctionExternalIds.ContractState state = auctionContract.getState().openState();
@@ -124,6 +150,21 @@ public final class ZkSecondPriceAuctionExternalIdsTest extends JunitContractTest
         .hasMessageContaining("Each bidder is only allowed to place one bid");
   }
 
+  /** No more bids are accepted once the maximum number of bids has been placed. */
+  @ContractTest(previous = "placeBidsOnContract")
+  void bidAfterMaximumNumberOfBidsIsReached() {
+    Assertions.assertThat(numberOfSecretVariables()).isEqualTo(MAX_NUM_BIDDERS);
+    registerBidders(
+        owner,
+        List.of(
+            new ZkSecondPriceAuctionExternalIds.AddressAndExternalId(
+                accounts.get(7), externalId(7))));
+
+    Assertions.assertThatCode(() -> bidOnAuction(accounts.get(7), 2000))
+        .hasMessageContaining("Cannot place more than 6 bids");
+    Assertions.assertThat(numberOfSecretVariables()).isEqualTo(MAX_NUM_BIDDERS);
+  }
+
   /** Users must be registered to bid. */
   @ContractTest(previous = "deploy")
   void unregisteredBidder() {
@@ -138,14 +179,34 @@ public final class ZkSecondPriceAuctionExternalIdsTest extends JunitContractTest
         .hasMessageContaining("Only contract owner can start the auction");
   }
 
-  /** Contract owner can start the winner computation when nobody has bid. */
+  /** Contract owner cannot start the winner computation when nobody has bid. */
   @ContractTest(previous = "deploy")
   void startAuctionWithZeroBids() {
+    blockchain.waitForBlockProductionTime(BIDDING_DEADLINE);
     Assertions.assertThatCode(() -> startAuction(owner))
         .hasMessageContaining(
             "At least 3 bidders must have submitted bids for the auction to start");
   }
 
+  /** Bids are rejected once the bidding deadline has passed. */
+  @ContractTest(previous = "setupBidders")
+  void bidAfterDeadline() {
+    blockchain.waitForBlockProductionTime(BIDDING_DEADLINE);
+    Assertions.assertThatCode(() -> bidOnAuction(accounts.get(1), 10))
+        .hasMessageContaining("Cannot place bid after the bidding deadline");
+    Assertions.assertThat(numberOfSecretVariables()).isEqualTo(0);
+  }
+
+  /** Contract owner cannot start the winner computation before the bidding deadline. */
+  @ContractTest(previous = "placeBidsOnContract")
+  void startAuctionBeforeDeadline() {
+    Assertions.assertThatCode(() -> startAuction(owner))
+        .hasMessageContaining("Cannot start auction before the bidding deadline");
+
+    ZkSecondPriceAuctionExternalIds.ContractState state = auctionContract.getState().openState();
+    Assertions.assertThat(state.auctionBegun()).isFalse();
+  }
+
   /** Contract owner can register which users can bid on the contract. */
   @ContractTest(previous = "startAuctionOnContract")
   void failToSetupBiddersAfterAuctionIsDone() {
@@ -167,6 +228,57 @@ public final class ZkSecondPriceAuctionExternalIdsTest extends JunitContractTest
         .hasMessageContaining("Cannot start auction after it has already begun");
   }
 
+  /**
+   * Contract owner can cancel an auction before it has started, which deletes all placed bids and
+   * clears the registered bidders. The auction is marked as cancelled, and never begins.
+   */
+  @ContractTest(previous = "setupBidders")
+  void cancelPartiallyRegisteredAuction() {
+    bidOnAuction(accounts.get(1), 10);
+    bidOnAuction(accounts.get(2), 20);
+    Assertions.assertThat(numberOfSecretVariables()).isEqualTo(2);
+
+    cancelAuction(owner);
+
+    ZkSecondPriceAuctionExternalIds.ContractState state = auctionContract.getState().openState();
+    Assertions.assertThat(state.registeredBidders().size()).isEqualTo(0);
+    Assertions.assertThat(state.auctionResult()).isNull();
+    Assertions.assertThat(state.auctionCancelled()).isTrue();
+    Assertions.assertThat(state.auctionBegun()).isFalse();
+    Assertions.assertThat(numberOfSecretVariables()).isEqualTo(0);
+  }
+
+  /** No bidders can be registered and no bids can be placed once the auction is cancelled. */
+  @ContractTest(previous = "cancelPartiallyRegisteredAuction")
+  void cannotRegisterOrBidAfterCancel() {
+    List<ZkSecondPriceAuctionExternalIds.AddressAndExternalId> bidders =
+        List.of(
+            new ZkSecondPriceAuctionExternalIds.AddressAndExternalId(
+                accounts.get(1), externalId(1)));
+    Assertions.assertThatThrownBy(() -> registerBidders(owner, bidders))
+        .isInstanceOf(RuntimeException.class);
+    Assertions.assertThatThrownBy(() -> bidOnAuction(accounts.get(3), 30))
+        .isInstanceOf(RuntimeException.class);
+
+    ZkSecondPriceAuctionExternalIds.ContractState state = auctionContract.getState().openState();
+    Assertions.assertThat(state.registeredBidders().size()).isEqualTo(0);
+    Assertions.assertThat(numberOfSecretVariables()).isEqualTo(0);
+  }
+
+  /** Only the owner can cancel the auction. */
+  @ContractTest(previous = "setupBidders")
+  void nonOwnerCannotCancelAuction() {
+    Assertions.assertThatCode(() -> cancelAuction(accounts.get(1)))
+        .hasMessageContaining("Only contract owner can cancel the auction");
+  }
+
+  /** The auction cannot be cancelled after it has begun. */
+  @ContractTest(previous = "startAuctionOnContract")
+  void failToCancelAuctionAfterAuctionIsDone() {
+    Assertions.assertThatCode(() -> cancelAuction(owner))
+        .hasMessageContaining("Cannot cancel auction after it has begun");
+  }
+
   private void registerBidders(
       BlockchainAddress sender,
       List<ZkSecondPriceAuctionExternalIds.AddressAndExternalId> bidders) {
@@ -184,4 +296,13 @@ public final class ZkSecondPriceAuctionExternalIdsTest extends JunitContractTest
     blockchain.sendAction(
         sender, auctionContractAddress, ZkSecondPriceAuctionExternalIds.startAuction());
   }
+
+  private void cancelAuction(BlockchainAddress sender) {
+    blockchain.sendAction(
+        sender, auctionContractAddress, ZkSecondPriceAuctionExternalIds.cancelAuction());
+  }
+
+  private int numberOfSecretVariables() {
+    return blockchain.getContractStateJson(auctionContractAddress).getNode("/variables").size();
+  }
 }
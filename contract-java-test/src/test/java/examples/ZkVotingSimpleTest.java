@@ -11,6 +11,7 @@ import com.partisiablockchain.language.testenvironment.zk.node.RealNodeClusterIn
 import com.partisiablockchain.language.testenvironment.zk.node.task.VariableId;
 import com.secata.stream.BitOutput;
 import com.secata.stream.CompactBitArray;
+import com.secata.stream.SafeDataOutputStream;
 import org.assertj.core.api.Assertions;
 
 /** Test the Zero Knowledge Simple Voting Contract. */
@@ -28,6 +29,12 @@ public final class ZkVotingSimpleTest extends JunitContractTest {
 
   private BlockchainAddress votingSimple;
 
+  /** Proposal opened when the contract is deployed. */
+  private static final int PROPOSAL_ID = 1;
+
+  /** Second proposal, used for testing concurrent proposals. */
+  private static final int OTHER_PROPOSAL_ID = 2;
+
   private RealNodeClusterInteractions zkNodes;
 
   /** Deploy ZK voting contract. */
@@ -42,11 +49,14 @@ public final class ZkVotingSimpleTest extends JunitContractTest {
 
     zkNodes = blockchain.addRealv1MpcNodes();
 
-    byte[] initRpc = ZkVotingSimple.initialize(10000);
+    byte[] initRpc = ZkVotingSimple.initialize();
 
     votingSimple = blockchain.deployZkContract(account1, VOTING_SIMPLE_BYTES, initRpc);
+    blockchain.sendAction(
+        account1, votingSimple, ZkVotingSimple.openProposal(PROPOSAL_ID, 10000, 1));
 
     Assertions.assertThat(votingState()).isNotNull();
+    Assertions.assertThat(proposal(PROPOSAL_ID).voteResult()).isNull();
   }
 
   /** The votes are counted correctly after the voting has ended. */
@@ -54,23 +64,23 @@ public final class ZkVotingSimpleTest extends JunitContractTest {
   void countVotes() {
     // cast votes
     // "Yes"-votes
-    blockchain.sendSecretInput(votingSimple, account1, createSecretIntInput(1), secretInputRpc());
+    castVote(account1, PROPOSAL_ID, 1);
     // "No"-votes
-    blockchain.sendSecretInput(votingSimple, account2, createSecretIntInput(0), secretInputRpc());
-    blockchain.sendSecretInput(votingSimple, account3, createSecretIntInput(0), secretInputRpc());
-    blockchain.sendSecretInput(votingSimple, account4, createSecretIntInput(0), secretInputRpc());
-    blockchain.sendSecretInput(votingSimple, account5, createSecretIntInput(0), secretInputRpc());
-    blockchain.sendSecretInput(votingSimple, account6, createSecretIntInput(0), secretInputRpc());
+    castVote(account2, PROPOSAL_ID, 0);
+    castVote(account3, PROPOSAL_ID, 0);
+    castVote(account4, PROPOSAL_ID, 0);
+    castVote(account5, PROPOSAL_ID, 0);
+    castVote(account6, PROPOSAL_ID, 0);
 
     // pass time until past voting deadline
     blockchain.waitForBlockProductionTime(10500);
 
     // count votes
-    byte[] startVoteCount = ZkVotingSimple.startVoteCounting();
+    byte[] startVoteCount = ZkVotingSimple.startVoteCounting(PROPOSAL_ID);
 
     blockchain.sendAction(account1, votingSimple, startVoteCount);
 
-    Assertions.assertThat(votingState().voteResult())
+    Assertions.assertThat(proposal(PROPOSAL_ID).voteResult())
         .isEqualTo(new ZkVotingSimple.VoteResult(1, 5, false));
   }
 
@@ -78,22 +88,22 @@ public final class ZkVotingSimpleTest extends JunitContractTest {
   @ContractTest(previous = "deploy")
   void countVotesMajorityFor() {
     // "Yes"-votes
-    blockchain.sendSecretInput(votingSimple, account2, createSecretIntInput(1), secretInputRpc());
-    blockchain.sendSecretInput(votingSimple, account3, createSecretIntInput(1), secretInputRpc());
-    blockchain.sendSecretInput(votingSimple, account4, createSecretIntInput(1), secretInputRpc());
-    blockchain.sendSecretInput(votingSimple, account5, createSecretIntInput(1), secretInputRpc());
-    blockchain.sendSecretInput(votingSimple, account6, createSecretIntInput(1), secretInputRpc());
+    castVote(account2, PROPOSAL_ID, 1);
+    castVote(account3, PROPOSAL_ID, 1);
+    castVote(account4, PROPOSAL_ID, 1);
+    castVote(account5, PROPOSAL_ID, 1);
+    castVote(account6, PROPOSAL_ID, 1);
     // No-votes
-    blockchain.sendSecretInput(votingSimple, account1, createSecretIntInput(0), secretInputRpc());
+    castVote(account1, PROPOSAL_ID, 0);
 
     // pass time until past voting deadline
     blockchain.waitForBlockProductionTime(10500);
 
     // count votes
-    byte[] startVoteCount = ZkVotingSimple.startVoteCounting();
+    byte[] startVoteCount = ZkVotingSimple.startVoteCounting(PROPOSAL_ID);
     blockchain.sendAction(account1, votingSimple, startVoteCount);
 
-    Assertions.assertThat(votingState().voteResult())
+    Assertions.assertThat(proposal(PROPOSAL_ID).voteResult())
         .isEqualTo(new ZkVotingSimple.VoteResult(5, 1, true));
   }
 
@@ -101,22 +111,22 @@ public final class ZkVotingSimpleTest extends JunitContractTest {
   @ContractTest(previous = "deploy")
   void countVotesMajorityAgainst() {
     // "Yes"-votes
-    blockchain.sendSecretInput(votingSimple, account6, createSecretIntInput(1), secretInputRpc());
-    blockchain.sendSecretInput(votingSimple, account4, createSecretIntInput(1), secretInputRpc());
+    castVote(account6, PROPOSAL_ID, 1);
+    castVote(account4, PROPOSAL_ID, 1);
     // "No"-votes
-    blockchain.sendSecretInput(votingSimple, account1, createSecretIntInput(0), secretInputRpc());
-    blockchain.sendSecretInput(votingSimple, account2, createSecretIntInput(0), secretInputRpc());
-    blockchain.sendSecretInput(votingSimple, account3, createSecretIntInput(0), secretInputRpc());
-    blockchain.sendSecretInput(votingSimple, account5, createSecretIntInput(0), secretInputRpc());
+    castVote(account1, PROPOSAL_ID, 0);
+    castVote(account2, PROPOSAL_ID, 0);
+    castVote(account3, PROPOSAL_ID, 0);
+    castVote(account5, PROPOSAL_ID, 0);
 
     // pass time until past voting deadline
     blockchain.waitForBlockProductionTime(10500);
 
     // count votes
-    byte[] startVoteCount = ZkVotingSimple.startVoteCounting();
+    byte[] startVoteCount = ZkVotingSimple.startVoteCounting(PROPOSAL_ID);
     blockchain.sendAction(account1, votingSimple, startVoteCount);
 
-    Assertions.assertThat(votingState().voteResult())
+    Assertions.assertThat(proposal(PROPOSAL_ID).voteResult())
         .isEqualTo(new ZkVotingSimple.VoteResult(2, 4, false));
   }
 
@@ -124,20 +134,20 @@ public final class ZkVotingSimpleTest extends JunitContractTest {
   @ContractTest(previous = "deploy")
   void countVotesDraw() {
     // "Yes"-votes
-    blockchain.sendSecretInput(votingSimple, account1, createSecretIntInput(1), secretInputRpc());
-    blockchain.sendSecretInput(votingSimple, account2, createSecretIntInput(1), secretInputRpc());
+    castVote(account1, PROPOSAL_ID, 1);
+    castVote(account2, PROPOSAL_ID, 1);
     // "No"-votes
-    blockchain.sendSecretInput(votingSimple, account3, createSecretIntInput(0), secretInputRpc());
-    blockchain.sendSecretInput(votingSimple, account4, createSecretIntInput(0), secretInputRpc());
+    castVote(account3, PROPOSAL_ID, 0);
+    castVote(account4, PROPOSAL_ID, 0);
 
     // pass time until past voting deadline
     blockchain.waitForBlockProductionTime(10500);
 
     // count votes
-    byte[] startVoteCount = ZkVotingSimple.startVoteCounting();
+    byte[] startVoteCount = ZkVotingSimple.startVoteCounting(PROPOSAL_ID);
     blockchain.sendAction(account1, votingSimple, startVoteCount);
 
-    Assertions.assertThat(votingState().voteResult())
+    Assertions.assertThat(proposal(PROPOSAL_ID).voteResult())
         .isEqualTo(new ZkVotingSimple.VoteResult(2, 2, false));
   }
 
@@ -151,7 +161,7 @@ public final class ZkVotingSimpleTest extends JunitContractTest {
     Assertions.assertThatThrownBy(
             () ->
                 blockchain.sendSecretInput(
-                    votingSimple, account1, createSecretIntInput(1), secretInputRpc()))
+                    votingSimple, account1, createSecretIntInput(1), secretInputRpc(PROPOSAL_ID)))
         .isInstanceOf(SecretInputFailureException.class)
         .hasMessageContaining("Not allowed to vote after the deadline");
   }
@@ -160,13 +170,13 @@ public final class ZkVotingSimpleTest extends JunitContractTest {
   @ContractTest(previous = "deploy")
   void eachVoterCanOnlyVoteOnce() {
     // cast vote
-    blockchain.sendSecretInput(votingSimple, account1, createSecretIntInput(1), secretInputRpc());
+    castVote(account1, PROPOSAL_ID, 1);
 
     // cast another vote
     Assertions.assertThatThrownBy(
             () ->
                 blockchain.sendSecretInput(
-                    votingSimple, account1, createSecretIntInput(1), secretInputRpc()))
+                    votingSimple, account1, createSecretIntInput(1), secretInputRpc(PROPOSAL_ID)))
         .isInstanceOf(SecretInputFailureException.class)
         .hasMessageContaining("Each voter is only allowed to send one vote variable.");
   }
@@ -174,18 +184,18 @@ public final class ZkVotingSimpleTest extends JunitContractTest {
   /** A user cannot start the vote count before the voting deadline has passed. */
   @ContractTest(previous = "deploy")
   void startCountBeforeDeadline() {
-    blockchain.sendSecretInput(votingSimple, account1, createSecretIntInput(1), secretInputRpc());
+    castVote(account1, PROPOSAL_ID, 1);
 
     blockchain.waitForBlockProductionTime(10000);
 
-    byte[] startVoteCount = ZkVotingSimple.startVoteCounting();
+    byte[] startVoteCount = ZkVotingSimple.startVoteCounting(PROPOSAL_ID);
 
     Assertions.assertThatThrownBy(
             () -> blockchain.sendAction(account1, votingSimple, startVoteCount))
         .isInstanceOf(ActionFailureException.class)
         .hasMessageContaining("Vote counting cannot start before specified starting time");
 
-    Assertions.assertThat(votingState().voteResult()).isNull();
+    Assertions.assertThat(proposal(PROPOSAL_ID).voteResult()).isNull();
   }
 
   /** A user cannot start the vote count before the result of the vote has been calculated. */
@@ -194,7 +204,7 @@ public final class ZkVotingSimpleTest extends JunitContractTest {
     zkNodes.stop();
 
     // cast vote
-    blockchain.sendSecretInput(votingSimple, account1, createSecretIntInput(1), secretInputRpc());
+    castVote(account1, PROPOSAL_ID, 1);
 
     for (VariableId pendingInput : zkNodes.getPendingInputs(votingSimple)) {
       zkNodes.confirmInput(pendingInput);
@@ -202,7 +212,7 @@ public final class ZkVotingSimpleTest extends JunitContractTest {
 
     // pass time until past voting deadline
     blockchain.waitForBlockProductionTime(10050);
-    byte[] startVoteCount = ZkVotingSimple.startVoteCounting();
+    byte[] startVoteCount = ZkVotingSimple.startVoteCounting(PROPOSAL_ID);
 
     // start counting votes
     blockchain.sendAction(account1, votingSimple, startVoteCount);
@@ -213,33 +223,183 @@ public final class ZkVotingSimpleTest extends JunitContractTest {
         .isInstanceOf(RuntimeException.class)
         .hasMessageContaining("Vote counting must start from Waiting state, but was Calculating");
 
-    Assertions.assertThat(votingState().voteResult()).isNull();
+    Assertions.assertThat(proposal(PROPOSAL_ID).voteResult()).isNull();
   }
 
   /** A user cannot call for a recount of the votes for a proposal that has been decided. */
   @ContractTest(previous = "deploy")
   void startCountFromDoneState() {
     // cast vote
-    blockchain.sendSecretInput(votingSimple, account1, createSecretIntInput(1), secretInputRpc());
+    castVote(account1, PROPOSAL_ID, 1);
 
     // pass time until past voting deadline
     blockchain.waitForBlockProductionTime(10050);
-    byte[] startVoteCount = ZkVotingSimple.startVoteCounting();
+    byte[] startVoteCount = ZkVotingSimple.startVoteCounting(PROPOSAL_ID);
 
     blockchain.sendAction(account1, votingSimple, startVoteCount);
 
     Assertions.assertThatThrownBy(
             () -> blockchain.sendAction(account1, votingSimple, startVoteCount))
         .isInstanceOf(RuntimeException.class)
-        .hasMessageContaining("Vote counting must start from Waiting state, but was Done");
+        .hasMessageContaining("Votes for proposal 1 have already been counted");
+  }
+
+  /** Two proposals can be voted on at the same time, and are tallied independently. */
+  @ContractTest(previous = "deploy")
+  void twoProposalsWithSeparateTallies() {
+    blockchain.sendAction(
+        account1, votingSimple, ZkVotingSimple.openProposal(OTHER_PROPOSAL_ID, 20000, 1));
+
+    // Votes on the first proposal
+    castVote(account1, PROPOSAL_ID, 1);
+    castVote(account2, PROPOSAL_ID, 1);
+    castVote(account3, PROPOSAL_ID, 0);
+    // Votes on the second proposal, where the same voters may vote again
+    castVote(account1, OTHER_PROPOSAL_ID, 0);
+    castVote(account4, OTHER_PROPOSAL_ID, 0);
+    castVote(account5, OTHER_PROPOSAL_ID, 1);
+    castVote(account6, OTHER_PROPOSAL_ID, 0);
+
+    // The first proposal can be counted while the second is still open
+    blockchain.waitForBlockProductionTime(10500);
+    blockchain.sendAction(account2, votingSimple, ZkVotingSimple.startVoteCounting(PROPOSAL_ID));
+
+    Assertions.assertThat(proposal(PROPOSAL_ID).voteResult())
+        .isEqualTo(new ZkVotingSimple.VoteResult(2, 1, true));
+    Assertions.assertThat(proposal(OTHER_PROPOSAL_ID).voteResult()).isNull();
+
+    // The second proposal still accepts votes
+    castVote(account2, OTHER_PROPOSAL_ID, 0);
+
+    blockchain.waitForBlockProductionTime(20500);
+    blockchain.sendAction(
+        account2, votingSimple, ZkVotingSimple.startVoteCounting(OTHER_PROPOSAL_ID));
+
+    Assertions.assertThat(proposal(OTHER_PROPOSAL_ID).voteResult())
+        .isEqualTo(new ZkVotingSimple.VoteResult(1, 4, false));
+    Assertions.assertThat(proposal(PROPOSAL_ID).voteResult())
+        .isEqualTo(new ZkVotingSimple.VoteResult(2, 1, true));
+  }
+
+  /** The votes cannot be counted before the minimum number of votes have been cast. */
+  @ContractTest(previous = "deploy")
+  void cannotCountBelowMinimumNumberOfVotes() {
+    blockchain.sendAction(
+        account1, votingSimple, ZkVotingSimple.openProposal(OTHER_PROPOSAL_ID, 10000, 3));
+    castVote(account1, OTHER_PROPOSAL_ID, 1);
+    castVote(account2, OTHER_PROPOSAL_ID, 0);
+
+    blockchain.waitForBlockProductionTime(10500);
+    byte[] startVoteCount = ZkVotingSimple.startVoteCounting(OTHER_PROPOSAL_ID);
+
+    Assertions.assertThatThrownBy(
+            () -> blockchain.sendAction(account1, votingSimple, startVoteCount))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Vote counting requires at least 3 votes, but proposal 2 has 2");
+
+    Assertions.assertThat(proposal(OTHER_PROPOSAL_ID).voteResult()).isNull();
+  }
+
+  /** The votes can be counted once the minimum number of votes have been cast. */
+  @ContractTest(previous = "deploy")
+  void countAtMinimumNumberOfVotes() {
+    blockchain.sendAction(
+        account1, votingSimple, ZkVotingSimple.openProposal(OTHER_PROPOSAL_ID, 10000, 3));
+    castVote(account1, OTHER_PROPOSAL_ID, 1);
+    castVote(account2, OTHER_PROPOSAL_ID, 0);
+    castVote(account3, OTHER_PROPOSAL_ID, 1);
+    // Votes on another proposal do not count towards the minimum
+    castVote(account4, PROPOSAL_ID, 1);
+
+    blockchain.waitForBlockProductionTime(10500);
+    blockchain.sendAction(
+        account1, votingSimple, ZkVotingSimple.startVoteCounting(OTHER_PROPOSAL_ID));
+
+    Assertions.assertThat(proposal(OTHER_PROPOSAL_ID).voteResult())
+        .isEqualTo(new ZkVotingSimple.VoteResult(2, 1, true));
+  }
+
+  /** Only the owner can open proposals. */
+  @ContractTest(previous = "deploy")
+  void nonOwnerCannotOpenProposal() {
+    byte[] openProposal = ZkVotingSimple.openProposal(OTHER_PROPOSAL_ID, 10000, 1);
+
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(account2, votingSimple, openProposal))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Only the owner can open proposals");
+  }
+
+  /** A proposal id cannot be reused. */
+  @ContractTest(previous = "deploy")
+  void cannotOpenExistingProposal() {
+    byte[] openProposal = ZkVotingSimple.openProposal(PROPOSAL_ID, 10000, 1);
+
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(account1, votingSimple, openProposal))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("Proposal with id 1 already exists");
+  }
+
+  /** A user cannot vote on a proposal that has not been opened. */
+  @ContractTest(previous = "deploy")
+  void cannotVoteOnUnknownProposal() {
+    Assertions.assertThatThrownBy(
+            () ->
+                blockchain.sendSecretInput(
+                    votingSimple,
+                    account1,
+                    createSecretIntInput(1),
+                    secretInputRpc(OTHER_PROPOSAL_ID)))
+        .isInstanceOf(SecretInputFailureException.class)
+        .hasMessageContaining("No proposal with id 2");
+  }
+
+  /** Participation of a voter can be queried, while the content of the vote stays secret. */
+  @ContractTest(previous = "deploy")
+  void participationIsQueryable() {
+    castVote(account2, PROPOSAL_ID, 1);
+
+    Assertions.assertThat(didVote(PROPOSAL_ID, account2)).isTrue();
+    Assertions.assertThat(didVote(PROPOSAL_ID, account4)).isFalse();
+
+    Assertions.assertThat(proposal(PROPOSAL_ID).voteResult()).isNull();
+    Assertions.assertThat(zkNodes.getPendingOpens(votingSimple)).isEmpty();
+  }
+
+  /** Participation cannot be queried for a proposal that has not been opened. */
+  @ContractTest(previous = "deploy")
+  void didVoteOnUnknownProposal() {
+    byte[] didVote = ZkVotingSimple.didVote(OTHER_PROPOSAL_ID, account1);
+    Assertions.assertThatThrownBy(() -> blockchain.sendAction(account2, votingSimple, didVote))
+        .isInstanceOf(ActionFailureException.class)
+        .hasMessageContaining("No proposal with id 2");
+  }
+
+  private boolean didVote(int proposalId, BlockchainAddress voter) {
+    return ReturnData.of(
+            blockchain.sendAction(
+                account3, votingSimple, ZkVotingSimple.didVote(proposalId, voter)))
+        .readBoolean();
   }
 
   private ZkVotingSimple.ContractState votingState() {
     return new ZkVotingSimple(getStateClient(), votingSimple).getState().openState();
   }
 
-  byte[] secretInputRpc() {
-    return new byte[] {0x40};
+  private void castVote(BlockchainAddress voter, int proposalId, int vote) {
+    blockchain.sendSecretInput(
+        votingSimple, voter, createSecretIntInput(vote), secretInputRpc(proposalId));
+  }
+
+  private ZkVotingSimple.Proposal proposal(int proposalId) {
+    return votingState().proposals().get(proposalId);
+  }
+
+  byte[] secretInputRpc(int proposalId) {
+    return SafeDataOutputStream.serialize(
+        stream -> {
+          stream.writeByte(0x40);
+          stream.writeInt(proposalId);
+        });
   }
 
   CompactBitArray createSecretIntInput(int secret) {
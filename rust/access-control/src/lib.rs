@@ -7,6 +7,7 @@ use crate::SecurityLevelImpl::{Admin, ModeratorA, ModeratorB, User};
 use create_type_spec_derive::CreateTypeSpec;
 use pbc_contract_common::address::Address;
 use pbc_contract_common::context::ContractContext;
+use pbc_contract_common::events::EventGroup;
 use pbc_contract_common::sorted_vec_map::SortedVecMap;
 use pbc_traits::ReadWriteState;
 use read_write_rpc_derive::ReadWriteRPC;
@@ -92,6 +93,42 @@ pub struct ContractState {
     access_map: AccessControlMap<SecurityLevelImpl>,
     description: ProtectedData<SecurityLevelImpl, String>,
     currently_held_by: ProtectedData<SecurityLevelImpl, Option<Address>>,
+    protected_items: SortedVecMap<String, ProtectedData<SecurityLevelImpl, String>>,
+}
+
+impl ContractState {
+    /// Gets the protected item with the given key, panicking if it does not exist.
+    fn get_protected_item_mut(
+        &mut self,
+        key: &String,
+    ) -> &mut ProtectedData<SecurityLevelImpl, String> {
+        self.protected_items
+            .get_mut(key)
+            .unwrap_or_else(|| panic!("No protected data with key '{key}'"))
+    }
+}
+
+/// The kind of change of custody of the object.
+#[derive(PartialEq, Eq, CreateTypeSpec, ReadWriteRPC, Debug, Copy, Clone)]
+pub enum CustodyEventKind {
+    /// The object was borrowed.
+    #[discriminant(0)]
+    Borrowed {},
+    /// The object was returned.
+    #[discriminant(1)]
+    Returned {},
+}
+
+/// An event recording that the object changed hands, returned by [`borrow_object`] and
+/// [`return_object`].
+#[derive(CreateTypeSpec, ReadWriteRPC, Debug)]
+pub struct CustodyEvent {
+    /// Whether the object was borrowed or returned.
+    kind: CustodyEventKind,
+    /// The user who borrowed or returned the object.
+    user: Address,
+    /// The block production time at which the object changed hands.
+    block_time: i64,
 }
 
 /// Data that is protected by the access control system.
@@ -101,6 +138,27 @@ pub struct ProtectedData<SecurityLevelT: SecurityLevel, E> {
     data: E,
 }
 
+/// A single update of a user's level, used in [`batch_update_user_levels`].
+#[derive(CreateTypeSpec, ReadWriteRPC, Debug)]
+pub struct UserLevelUpdate {
+    /// The user to update.
+    user: Address,
+    /// The new level of the user.
+    new_level: SecurityLevelImpl,
+}
+
+/// A user together with their level, as returned by [`all_levels`].
+#[derive(CreateTypeSpec, ReadWriteRPC, Debug)]
+pub struct UserLevel {
+    /// The user.
+    user: Address,
+    /// The level of the user.
+    level: SecurityLevelImpl,
+}
+
+/// The maximum number of users returned by a single call to [`all_levels`].
+const MAX_LEVELS_PER_PAGE: usize = 100;
+
 /// Map from account addresses to user levels
 #[derive(CreateTypeSpec, ReadWriteState)]
 pub struct AccessControlMap<SecurityLevelT: SecurityLevel> {
@@ -118,6 +176,7 @@ impl<SecurityLevelT: SecurityLevel + Clone + Debug> AccessControlMap<SecurityLev
 
     /// Update a user's level to a new level. The sender of the action can only update users
     /// whose level is below their own, and only update to levels below or equal to their own.
+    /// There must always be at least one user with the highest level left.
     pub fn update_user_level(
         &mut self,
         sender: &Address,
@@ -139,6 +198,13 @@ impl<SecurityLevelT: SecurityLevel + Clone + Debug> AccessControlMap<SecurityLev
             new_level
         );
         self.map.insert(user, new_level);
+        assert!(
+            self.map
+                .values()
+                .any(|level| *level == SecurityLevelT::HIGHEST_LEVEL),
+            "Cannot remove the last user with level '{:?}'",
+            SecurityLevelT::HIGHEST_LEVEL
+        );
     }
 }
 
@@ -191,6 +257,7 @@ pub fn initialize(ctx: ContractContext, description: String) -> ContractState {
             level: User {},
             data: None,
         },
+        protected_items: SortedVecMap::new(),
     }
 }
 
@@ -211,8 +278,15 @@ pub fn update_description(
 
 /// Borrow the object. Can only borrow the object if it is not already lent out, and if the
 /// borrower's level is greater or equal to the level of the object (Default User).
+///
+/// ## Return Value
+///
+/// The [`CustodyEvent`] recording the borrow.
 #[action(shortname = 0x02)]
-pub fn borrow_object(ctx: ContractContext, mut state: ContractState) -> ContractState {
+pub fn borrow_object(
+    ctx: ContractContext,
+    mut state: ContractState,
+) -> (ContractState, Vec<EventGroup>) {
     assert!(
         state.currently_held_by.data.is_none(),
         "Object is already lent out"
@@ -221,13 +295,25 @@ pub fn borrow_object(ctx: ContractContext, mut state: ContractState) -> Contract
         state.access_map.get_user_level(&ctx.sender),
         Some(ctx.sender),
     );
-    state
+    let event = CustodyEvent {
+        kind: CustodyEventKind::Borrowed {},
+        user: ctx.sender,
+        block_time: ctx.block_production_time,
+    };
+    (state, vec![EventGroup::with_return_data(event)])
 }
 
 /// Return the borrowed object to the contract. Only the user who has borrowed the object can
 /// return it.
+///
+/// ## Return Value
+///
+/// The [`CustodyEvent`] recording the return.
 #[action(shortname = 0x03)]
-pub fn return_object(ctx: ContractContext, mut state: ContractState) -> ContractState {
+pub fn return_object(
+    ctx: ContractContext,
+    mut state: ContractState,
+) -> (ContractState, Vec<EventGroup>) {
     assert!(
         state
             .currently_held_by
@@ -236,7 +322,12 @@ pub fn return_object(ctx: ContractContext, mut state: ContractState) -> Contract
         "Only the user who has borrowed the object can return it"
     );
     state.currently_held_by.data = None;
-    state
+    let event = CustodyEvent {
+        kind: CustodyEventKind::Returned {},
+        user: ctx.sender,
+        block_time: ctx.block_production_time,
+    };
+    (state, vec![EventGroup::with_return_data(event)])
 }
 
 /// Update the string level stored in state. Only Admin can update levels.
@@ -266,7 +357,8 @@ pub fn update_borrow_level(
 }
 
 /// Update a user's level. A user can only update levels of other users, whose level is lower than
-/// their own, and only to a new level that is lower or equal to their own.
+/// their own, and only to a new level that is lower or equal to their own. The last Admin cannot be
+/// demoted.
 #[action(shortname = 0x06)]
 pub fn update_user_level(
     ctx: ContractContext,
@@ -279,3 +371,120 @@ pub fn update_user_level(
         .update_user_level(&ctx.sender, user, new_level);
     state
 }
+
+/// Update the levels of several users at once. The updates are applied in order, each with the
+/// same checks as [`update_user_level`]. If any update is not allowed, none of them are applied.
+#[action(shortname = 0x0A)]
+pub fn batch_update_user_levels(
+    ctx: ContractContext,
+    mut state: ContractState,
+    updates: Vec<UserLevelUpdate>,
+) -> ContractState {
+    for update in updates {
+        state
+            .access_map
+            .update_user_level(&ctx.sender, update.user, update.new_level);
+    }
+    state
+}
+
+/// Create a new protected item with the given key, protected by the given level. Only Admin can
+/// create protected items, and the key must not already be in use.
+#[action(shortname = 0x07)]
+pub fn create_protected(
+    ctx: ContractContext,
+    mut state: ContractState,
+    key: String,
+    level: SecurityLevelImpl,
+    initial_data: String,
+) -> ContractState {
+    let sender_level = state.access_map.get_user_level(&ctx.sender);
+    assert_eq!(
+        sender_level,
+        SecurityLevelImpl::HIGHEST_LEVEL,
+        "Only '{:?}' can create protected data",
+        SecurityLevelImpl::HIGHEST_LEVEL
+    );
+    assert!(
+        !state.protected_items.contains_key(&key),
+        "Protected data with key '{key}' already exists"
+    );
+    state.protected_items.insert(
+        key,
+        ProtectedData {
+            level,
+            data: initial_data,
+        },
+    );
+    state
+}
+
+/// Update the data of the protected item with the given key. Can only update the data if level is
+/// greater or equal to the level of the item.
+#[action(shortname = 0x08)]
+pub fn update_protected(
+    ctx: ContractContext,
+    mut state: ContractState,
+    key: String,
+    data: String,
+) -> ContractState {
+    let user_level = state.access_map.get_user_level(&ctx.sender);
+    state
+        .get_protected_item_mut(&key)
+        .update_data(user_level, data);
+    state
+}
+
+/// Update the level protecting the item with the given key. Only Admin can update levels.
+#[action(shortname = 0x09)]
+pub fn set_protected_level(
+    ctx: ContractContext,
+    mut state: ContractState,
+    key: String,
+    level: SecurityLevelImpl,
+) -> ContractState {
+    let user_level = state.access_map.get_user_level(&ctx.sender);
+    state
+        .get_protected_item_mut(&key)
+        .update_level(user_level, level);
+    state
+}
+
+/// List the levels of the users in the access map, ordered by address. Only Admin can list the
+/// levels.
+///
+/// At most [`MAX_LEVELS_PER_PAGE`] users are returned, starting from the user at index `offset`.
+/// Larger maps can be listed by calling the action again with an increased offset, until fewer
+/// than [`MAX_LEVELS_PER_PAGE`] users are returned.
+///
+/// ## Return Value
+///
+/// A [`UserLevel`] for each of the listed users.
+#[action(shortname = 0x0B)]
+pub fn all_levels(
+    ctx: ContractContext,
+    state: ContractState,
+    offset: u32,
+) -> (ContractState, Vec<EventGroup>) {
+    let sender_level = state.access_map.get_user_level(&ctx.sender);
+    assert_eq!(
+        sender_level,
+        SecurityLevelImpl::HIGHEST_LEVEL,
+        "Only '{:?}' can list all user levels",
+        SecurityLevelImpl::HIGHEST_LEVEL
+    );
+
+    let levels: Vec<UserLevel> = state
+        .access_map
+        .map
+        .iter()
+        .skip(offset as usize)
+        .take(MAX_LEVELS_PER_PAGE)
+        .map(|(user, level)| UserLevel {
+            user: *user,
+            level: *level,
+        })
+        .collect();
+
+    (state, vec![EventGroup::with_return_data(levels)])
+}
@@ -9,6 +9,9 @@ use create_type_spec_derive::CreateTypeSpec;
 use pbc_contract_common::address::Address;
 use pbc_contract_common::avl_tree_map::AvlTreeMap;
 use pbc_contract_common::context::ContractContext;
+use pbc_contract_common::events::EventGroup;
+use pbc_contract_common::sorted_vec_map::SortedVecSet;
+use read_write_rpc_derive::ReadWriteRPC;
 use read_write_state_derive::ReadWriteState;
 
 /// The DNS (Domain Name System) contract contains
@@ -28,6 +31,18 @@ pub struct DnsEntry {
     address: Address,
     /// The owner of the domain.
     owner: Address,
+    /// An address allowed to update the address of the domain on behalf of the owner.
+    resolver: Option<Address>,
+}
+
+/// An event describing a change to a domain in the DNS, returned by the actions changing domains.
+/// The event is only return data for the caller of the action, so others cannot subscribe to it.
+#[derive(CreateTypeSpec, ReadWriteRPC)]
+pub struct DomainEvent {
+    /// The domain that was changed.
+    domain: String,
+    /// The new address of the domain, or `None` if the domain was removed.
+    address: Option<Address>,
 }
 
 /// The state of the DNS.
@@ -36,6 +51,14 @@ pub struct DnsState {
     /// A map associating the domains with their respective DNS entry.
     /// Used for saving and retrieving what address corresponds to a given domain, and who owns it.
     records: AvlTreeMap<String, DnsEntry>,
+    /// Pending ownership transfers, mapping domains to the address they have been offered to.
+    pending_offers: AvlTreeMap<String, Address>,
+    /// The number of registered domains.
+    domain_count: u64,
+    /// The deployer of the DNS, who manages the reserved names.
+    deployer: Address,
+    /// Names that cannot be registered as domains.
+    reserved_names: SortedVecSet<String>,
 }
 
 impl DnsState {
@@ -54,31 +77,80 @@ impl DnsState {
             );
 
             self.records.remove(domain);
+            self.pending_offers.remove(domain);
+            self.domain_count -= 1;
         } else {
             panic!("Could not find domain.")
         };
     }
+
+    /// Assert that the sender owns the given domain.
+    fn assert_domain_owner(&self, domain: &String, sender: Address) {
+        let entry = self.search_domain(domain).expect("Could not find domain.");
+        assert_eq!(
+            entry.owner, sender,
+            "Only the owner of the domain can transfer it. Owner: {}, Sender: {}",
+            entry.owner, sender
+        );
+    }
+
+    /// Assert that the sender is the deployer of the DNS.
+    fn assert_deployer(&self, sender: Address) {
+        assert_eq!(
+            self.deployer, sender,
+            "Only the deployer of the DNS can manage reserved names. Deployer: {}, Sender: {}",
+            self.deployer, sender
+        );
+    }
+}
+
+/// Create the event group returning a [`DomainEvent`] for a change to the given domain.
+fn domain_event(domain: String, address: Option<Address>) -> Vec<EventGroup> {
+    vec![EventGroup::with_return_data(DomainEvent { domain, address })]
+}
+
+/// Validate that a domain only consists of ASCII letters, digits, `-` and `.`, and normalize it
+/// to lowercase, such that domains differing only in case resolve to the same entry.
+fn normalize_domain(domain: &str) -> String {
+    assert!(
+        !domain.is_empty()
+            && domain
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.'),
+        "Invalid domain '{}'. Domains may only contain letters, digits, '-' and '.'",
+        domain
+    );
+    domain.to_ascii_lowercase()
 }
 
 /// Initialize the DNS.
 ///
 /// # Arguments
 ///
-/// * `_ctx` - the contract context containing information about the sender and the blockchain.
+/// * `ctx` - the contract context containing information about the sender and the blockchain.
+/// * `reserved_names` - names that cannot be registered as domains.
 ///
 /// # Returns
 ///
 /// The initial state of the DNS.
 ///
 #[init]
-pub fn initialize(ctx: ContractContext) -> DnsState {
+pub fn initialize(ctx: ContractContext, reserved_names: Vec<String>) -> DnsState {
+    let mut reserved = SortedVecSet::new();
+    for name in reserved_names {
+        reserved.insert(normalize_domain(&name));
+    }
     DnsState {
         records: AvlTreeMap::new(),
+        pending_offers: AvlTreeMap::new(),
+        domain_count: 0,
+        deployer: ctx.sender,
+        reserved_names: reserved,
     }
 }
 
 /// Register a domain to a blockchain address, as
-/// long as the domain is not taken.
+/// long as the domain is not taken or reserved.
 ///
 /// # Arguments
 ///
@@ -89,7 +161,8 @@ pub fn initialize(ctx: ContractContext) -> DnsState {
 ///
 /// # Returns
 ///
-/// The updated state reflecting the updated DNS.
+/// The updated state reflecting the updated DNS, and a [`DomainEvent`] with the address of the
+/// domain as return data.
 ///
 #[action(shortname = 0x01)]
 pub fn register_domain(
@@ -97,17 +170,25 @@ pub fn register_domain(
     mut state: DnsState,
     domain: String,
     address: Address,
-) -> DnsState {
+) -> (DnsState, Vec<EventGroup>) {
+    let domain = normalize_domain(&domain);
+    assert!(
+        !state.reserved_names.contains(&domain),
+        "Domain '{}' is reserved",
+        domain
+    );
     let entry = state.search_domain(&domain);
     assert!(entry.is_none(), "Domain already registered");
 
     let new_entry = DnsEntry {
         address,
         owner: ctx.sender,
+        resolver: None,
     };
 
-    state.records.insert(domain, new_entry);
-    state
+    state.records.insert(domain.clone(), new_entry);
+    state.domain_count += 1;
+    (state, domain_event(domain, Some(address)))
 }
 
 /// Lookup a domain in the register.
@@ -127,11 +208,27 @@ pub fn register_domain(
 #[get(shortname = 0x02)]
 pub fn lookup(ctx: ContractContext, state: &DnsState, domain: String) -> Address {
     state
-        .search_domain(&domain)
+        .search_domain(&normalize_domain(&domain))
         .expect("No address found with the given domain")
         .address
 }
 
+/// Get the number of registered domains.
+///
+/// # Arguments
+///
+/// * `ctx` - the contract context containing information about the sender and blockchain.
+/// * `state` - the current state of the DNS.
+///
+/// # Returns
+///
+/// The number of domains currently registered in the DNS.
+///
+#[get(shortname = 0x08)]
+pub fn domain_count(ctx: ContractContext, state: &DnsState) -> u64 {
+    state.domain_count
+}
+
 /// Remove a domain from the register.
 /// Only the owner of the domain can remove it.
 /// Will fail if domain is not registered.
@@ -144,16 +241,22 @@ pub fn lookup(ctx: ContractContext, state: &DnsState, domain: String) -> Address
 ///
 /// # Returns
 ///
-/// The updated state reflecting the updated DNS after removing the domain if it was allowed.
+/// The updated state reflecting the updated DNS after removing the domain if it was allowed, and
+/// a [`DomainEvent`] without an address as return data.
 ///
 #[action(shortname = 0x03)]
-pub fn remove_domain(ctx: ContractContext, mut state: DnsState, domain: String) -> DnsState {
+pub fn remove_domain(
+    ctx: ContractContext,
+    mut state: DnsState,
+    domain: String,
+) -> (DnsState, Vec<EventGroup>) {
+    let domain = normalize_domain(&domain);
     state.remove_domain(&domain, ctx.sender);
-    state
+    (state, domain_event(domain, None))
 }
 
 /// Update the address of a registered domain.
-/// Only the owner of the domain can update it.
+/// Only the owner or the resolver of the domain can update it.
 ///
 /// # Arguments
 ///
@@ -165,7 +268,7 @@ pub fn remove_domain(ctx: ContractContext, mut state: DnsState, domain: String)
 /// # Returns
 ///
 /// The updated state reflecting the updated DNS after updating the domain, if it was
-/// registered and allowed.
+/// registered and allowed, and a [`DomainEvent`] with the new address as return data.
 ///
 #[action(shortname = 0x04)]
 pub fn update_domain(
@@ -173,23 +276,208 @@ pub fn update_domain(
     mut state: DnsState,
     domain: String,
     new_address: Address,
-) -> DnsState {
+) -> (DnsState, Vec<EventGroup>) {
+    let domain = normalize_domain(&domain);
     if let Some(entry) = state.search_domain(&domain) {
-        assert_eq!(
-            entry.owner, ctx.sender,
-            "Only the owner of the domain can modify it. Owner: {}, Sender: {}",
-            entry.owner, ctx.sender
+        assert!(
+            entry.owner == ctx.sender || entry.resolver == Some(ctx.sender),
+            "Only the owner or the resolver of the domain can modify it. Owner: {}, Sender: {}",
+            entry.owner,
+            ctx.sender
         );
 
         state.records.remove(&domain);
 
         let new_entry = DnsEntry {
             address: new_address,
-            owner: ctx.sender,
+            owner: entry.owner,
+            resolver: entry.resolver,
         };
-        state.records.insert(domain, new_entry);
+        state.records.insert(domain.clone(), new_entry);
     } else {
         panic!("Could not find domain.")
     };
+    (state, domain_event(domain, Some(new_address)))
+}
+
+/// Offer the ownership of a domain to a new owner.
+/// Only the owner of the domain can offer it. The ownership is not transferred until the new
+/// owner claims the domain, such that a domain offered to a wrong address is not lost.
+/// Replaces any existing offer for the domain.
+///
+/// # Arguments
+///
+/// * `ctx` - the contract context containing information about the sender and the blockchain.
+/// * `state` - the current state of the DNS.
+/// * `domain` - the domain to offer.
+/// * `new_owner` - the address that can claim the domain.
+///
+/// # Returns
+///
+/// The updated state containing the pending offer.
+///
+#[action(shortname = 0x05)]
+pub fn offer_domain(
+    ctx: ContractContext,
+    mut state: DnsState,
+    domain: String,
+    new_owner: Address,
+) -> DnsState {
+    let domain = normalize_domain(&domain);
+    state.assert_domain_owner(&domain, ctx.sender);
+    state.pending_offers.insert(domain, new_owner);
+    state
+}
+
+/// Claim a domain that has been offered to the sender.
+/// Transfers the ownership of the domain to the sender, while keeping its address.
+/// Any resolver set by the previous owner is removed.
+///
+/// # Arguments
+///
+/// * `ctx` - the contract context containing information about the sender and the blockchain.
+/// * `state` - the current state of the DNS.
+/// * `domain` - the domain to claim.
+///
+/// # Returns
+///
+/// The updated state where the sender owns the domain.
+///
+#[action(shortname = 0x06)]
+pub fn claim_domain(ctx: ContractContext, mut state: DnsState, domain: String) -> DnsState {
+    let domain = normalize_domain(&domain);
+    let offered_to = state
+        .pending_offers
+        .get(&domain)
+        .expect("No pending offer for the domain.");
+    assert_eq!(
+        offered_to, ctx.sender,
+        "Only the offered address can claim the domain. Offered to: {}, Sender: {}",
+        offered_to, ctx.sender
+    );
+
+    let entry = state.search_domain(&domain).unwrap();
+    state.pending_offers.remove(&domain);
+    state.records.insert(
+        domain,
+        DnsEntry {
+            address: entry.address,
+            owner: ctx.sender,
+            resolver: None,
+        },
+    );
+    state
+}
+
+/// Cancel a pending offer of a domain.
+/// Only the owner of the domain can cancel the offer.
+///
+/// # Arguments
+///
+/// * `ctx` - the contract context containing information about the sender and the blockchain.
+/// * `state` - the current state of the DNS.
+/// * `domain` - the domain to cancel the offer for.
+///
+/// # Returns
+///
+/// The updated state without the pending offer.
+///
+#[action(shortname = 0x07)]
+pub fn cancel_offer(ctx: ContractContext, mut state: DnsState, domain: String) -> DnsState {
+    let domain = normalize_domain(&domain);
+    state.assert_domain_owner(&domain, ctx.sender);
+    assert!(
+        state.pending_offers.get(&domain).is_some(),
+        "No pending offer for the domain."
+    );
+    state.pending_offers.remove(&domain);
+    state
+}
+
+/// Set the resolver of a domain, which can update the address of the domain on behalf of the
+/// owner. The resolver cannot remove the domain or transfer its ownership.
+/// Only the owner of the domain can set the resolver.
+///
+/// # Arguments
+///
+/// * `ctx` - the contract context containing information about the sender and the blockchain.
+/// * `state` - the current state of the DNS.
+/// * `domain` - the domain to set the resolver for.
+/// * `resolver` - the new resolver of the domain, or `None` to remove the current resolver.
+///
+/// # Returns
+///
+/// The updated state where the domain has the given resolver.
+///
+#[action(shortname = 0x09)]
+pub fn set_resolver(
+    ctx: ContractContext,
+    mut state: DnsState,
+    domain: String,
+    resolver: Option<Address>,
+) -> DnsState {
+    let domain = normalize_domain(&domain);
+    let entry = state.search_domain(&domain).expect("Could not find domain.");
+    assert_eq!(
+        entry.owner, ctx.sender,
+        "Only the owner of the domain can set its resolver. Owner: {}, Sender: {}",
+        entry.owner, ctx.sender
+    );
+
+    state.records.insert(
+        domain,
+        DnsEntry {
+            address: entry.address,
+            owner: entry.owner,
+            resolver,
+        },
+    );
+    state
+}
+
+/// Reserve a name, such that it cannot be registered as a domain.
+/// Only the deployer of the DNS can reserve names. Domains already registered under the name are
+/// not affected.
+///
+/// # Arguments
+///
+/// * `ctx` - the contract context containing information about the sender and the blockchain.
+/// * `state` - the current state of the DNS.
+/// * `name` - the name to reserve.
+///
+/// # Returns
+///
+/// The updated state where the name is reserved.
+///
+#[action(shortname = 0x0A)]
+pub fn add_reserved_name(ctx: ContractContext, mut state: DnsState, name: String) -> DnsState {
+    state.assert_deployer(ctx.sender);
+    state.reserved_names.insert(normalize_domain(&name));
+    state
+}
+
+/// Remove a reserved name, such that it can be registered as a domain again.
+/// Only the deployer of the DNS can remove reserved names.
+///
+/// # Arguments
+///
+/// * `ctx` - the contract context containing information about the sender and the blockchain.
+/// * `state` - the current state of the DNS.
+/// * `name` - the reserved name to remove.
+///
+/// # Returns
+///
+/// The updated state where the name is no longer reserved.
+///
+#[action(shortname = 0x0B)]
+pub fn remove_reserved_name(ctx: ContractContext, mut state: DnsState, name: String) -> DnsState {
+    state.assert_deployer(ctx.sender);
+    let name = normalize_domain(&name);
+    assert!(
+        state.reserved_names.contains(&name),
+        "Name '{}' is not reserved",
+        name
+    );
+    state.reserved_names.remove(&name);
     state
 }
@@ -9,8 +9,8 @@ extern crate pbc_lib;
 mod zk_compute;
 
 use create_type_spec_derive::CreateTypeSpec;
-use pbc_contract_common::address::Address;
-use pbc_contract_common::context::ContractContext;
+use pbc_contract_common::address::{Address, Shortname};
+use pbc_contract_common::context::{CallbackContext, ContractContext};
 use pbc_contract_common::events::EventGroup;
 use pbc_contract_common::sorted_vec_map::{SortedVecMap, SortedVecSet};
 use pbc_contract_common::zk::{SecretVarId, ZkInputDef, ZkState, ZkStateChange};
@@ -19,6 +19,22 @@ use pbc_zk::{Sbu8, SecretBinary};
 use read_write_rpc_derive::ReadWriteRPC;
 use read_write_state_derive::ReadWriteState;
 
+/// Shortname of the MPC-20 `transfer` action.
+const MPC20_TRANSFER_SHORTNAME: u32 = 0x01;
+
+/// Shortname of the MPC-20 `transfer_from` action.
+const MPC20_TRANSFER_FROM_SHORTNAME: u32 = 0x03;
+
+/// The number of lives each player has at the start of a game.
+const STARTING_LIVES: u8 = 6;
+
+/// The minimum number of faces on each die.
+const MIN_DIE_FACES: u8 = 4;
+
+/// The maximum number of faces on each die. Bounded by the reduction of randomness contributions,
+/// which only considers the lowest three bits of each contribution.
+const MAX_DIE_FACES: u8 = 8;
+
 /**
  * Metadata information associated with each individual variable.
  */
@@ -40,6 +56,8 @@ pub struct MiaState {
     nr_of_randomness_contributions: u32,
     // The number of players at the start of the game.
     nr_of_players_at_the_start: u32,
+    // The maximum number of players allowed to be seated in the game.
+    max_nr_of_players: u32,
     // The player currently throwing the dice and declaring a value.
     player_throwing: u32,
     // The current phase the game is in, to determine allowed actions.
@@ -57,9 +75,20 @@ pub struct MiaState {
     // The revealed value of a throw.
     throw_result: Option<DiceThrow>,
     // The announced throw value, where the next announced throw must be higher than, to be eligible.
-    throw_to_beat: DiceThrow,
+    // Is `None` at the start of a round, where any throw can be announced.
+    throw_to_beat: Option<DiceThrow>,
     // The winner of the game.
     winner: Option<Address>,
+    // The MPC-20 token used to pay the entry fee.
+    entry_token: Address,
+    // The entry fee each player must pay into the pot before the game can start.
+    entry_fee: u128,
+    // The players who have paid the entry fee.
+    paid_players: SortedVecSet<Address>,
+    // The total amount of entry fees in the pot, which can be claimed by the winner.
+    pot: u128,
+    // The number of faces on each die. The dice show values from 0 to `die_faces - 1`.
+    die_faces: u8,
 }
 
 impl MiaState {
@@ -83,8 +112,19 @@ impl MiaState {
     }
 
     /// Remove a dead player from the list of players.
+    ///
+    /// The index of the player in turn is adjusted, such that going to the next player afterwards
+    /// selects the first remaining player seated after the current player.
     fn remove_dead_player(&mut self, player: Address) {
-        self.players.retain(|p| player != *p);
+        let Some(removed_index) = self.players.iter().position(|p| player == *p) else {
+            return;
+        };
+        self.players.remove(removed_index);
+
+        if removed_index as u32 <= self.player_throwing {
+            let nr_of_players = self.players.len() as u32;
+            self.player_throwing = (self.player_throwing + nr_of_players - 1) % nr_of_players;
+        }
     }
 
     /// Reduce a players lives by a given integer.
@@ -102,10 +142,51 @@ impl MiaState {
         self.players.len() == 1
     }
 
+    /// Check whether all players have paid the entry fee. Always true if there is no entry fee.
+    fn have_all_players_paid(&self) -> bool {
+        self.entry_fee == 0
+            || self
+                .starting_players
+                .iter()
+                .all(|player| self.paid_players.contains(player))
+    }
+
+    /// Ends the current round, where the loser of the round loses the given number of lives.
+    /// The player after the current player starts the next round, or the game is done if only one
+    /// player remains.
+    fn end_round(&mut self, loser_of_round: Address, lives_lost: u8) {
+        self.reduce_players_life_by(loser_of_round, lives_lost);
+
+        if self.is_player_dead(loser_of_round) {
+            self.remove_dead_player(loser_of_round);
+        }
+
+        self.throw_to_beat = None;
+        self.stated_throw = None;
+
+        if self.is_the_game_finished() {
+            self.game_phase = GamePhase::Done {};
+            self.winner = Some(self.get_winner());
+        } else {
+            self.go_to_next_player();
+            self.game_phase = GamePhase::AddRandomness {};
+        }
+    }
+
     /// Get the last remaining player, the winner.
     fn get_winner(&self) -> Address {
         *self.players.first().unwrap()
     }
+
+    /// Seat all starting players with full lives. Every player must pay the entry fee again.
+    fn reset_players(&mut self) {
+        self.players = self.starting_players.clone();
+        self.player_lives = SortedVecMap::new();
+        for address in &self.starting_players {
+            self.player_lives.insert(*address, STARTING_LIVES);
+        }
+        self.paid_players = SortedVecSet::new();
+    }
 }
 
 /// A throw of two dice.
@@ -116,18 +197,25 @@ pub struct DiceThrow {
 }
 
 impl DiceThrow {
-    /// The value of each die is reduced to be between 0 and 5.
-    fn reduce(&self) -> DiceThrow {
+    /// The value of each die is reduced to be between 0 and `die_faces - 1`.
+    fn reduce(&self, die_faces: u8) -> DiceThrow {
         DiceThrow {
-            d1: self.d1 % 6,
-            d2: self.d2 % 6,
+            d1: self.d1 % die_faces,
+            d2: self.d2 % die_faces,
         }
     }
 
-    /// Checks whether a throw is better than the current dice throw to beat.
-    /// The dice throws are compared based on their associated values.
-    fn better_than_or_equal(self, actual: DiceThrow) -> bool {
-        self.get_throw_score() >= actual.get_throw_score()
+    /// Checks whether a throw is strictly better than the current dice throw to beat.
+    /// The dice throws are compared based on their associated values, such that restating an
+    /// identical value does not beat it.
+    fn better_than(self, throw_to_beat: DiceThrow, die_faces: u8) -> bool {
+        self.get_throw_score(die_faces) > throw_to_beat.get_throw_score(die_faces)
+    }
+
+    /// Checks whether a revealed throw is at least as good as the stated throw.
+    /// A revealed throw that ties the stated throw means the stated throw was truthful.
+    fn better_than_or_equal(self, stated: DiceThrow, die_faces: u8) -> bool {
+        self.get_throw_score(die_faces) >= stated.get_throw_score(die_faces)
     }
 
     /// Checks whether a dice throw is Mia, i.e. is (0,1) or (1,0).
@@ -147,40 +235,70 @@ impl DiceThrow {
 
     /// Get the score of a dice throw.
     /// The throw values are determined such that the highest roll is Mia, then Little Mia,
-    /// followed by the doubles from (5,5) to (0,0), and then all other rolls from (5,4)
-    /// down to (2,1).
-    fn get_throw_score(self) -> u8 {
+    /// followed by the doubles from the highest face down to (0,0), and then all other rolls
+    /// ordered by their highest die, then their lowest die. With six faces, this is the doubles
+    /// from (5,5) to (0,0), and then all other rolls from (5,4) down to (2,1).
+    fn get_throw_score(self, die_faces: u8) -> u16 {
         let mut value = 0;
         if self.is_mia() {
-            value += 128;
+            value += 1 << (die_faces + 1);
         };
         if self.is_little_mia() {
-            value += 64;
+            value += 1 << die_faces;
         };
         if self.is_pair() {
-            value += 32;
+            value += 1 << (die_faces - 1);
         };
-        if (self.d1 == 5) || (self.d2 == 5) {
-            value += 16;
-        }
-        if (self.d1 == 4) || (self.d2 == 4) {
-            value += 8;
-        }
-        if (self.d1 == 3) || (self.d2 == 3) {
-            value += 4;
-        }
-        if (self.d1 == 2) || (self.d2 == 2) {
-            value += 2;
-        }
-        if (self.d1 == 1) || (self.d2 == 1) {
-            value += 1;
+        for face in 1..die_faces {
+            if (self.d1 == face) || (self.d2 == face) {
+                value += 1 << (face - 1);
+            }
         }
         value
     }
+
+    /// Get the category of a dice throw, used to describe the throw to players.
+    fn get_category(self) -> ThrowCategory {
+        if self.is_mia() {
+            ThrowCategory::Mia {}
+        } else if self.is_little_mia() {
+            ThrowCategory::LittleMia {}
+        } else if self.is_pair() {
+            ThrowCategory::Pair {}
+        } else {
+            ThrowCategory::Ordinary {}
+        }
+    }
+}
+
+/// The category of a dice throw. Throws in a higher category always beat throws in a lower one.
+#[derive(ReadWriteRPC, CreateTypeSpec, Debug, PartialEq, Copy, Clone)]
+pub enum ThrowCategory {
+    #[discriminant(0)]
+    /// Any throw that is neither Mia, Little Mia nor a pair.
+    Ordinary {},
+    #[discriminant(1)]
+    /// Both dice show the same value.
+    Pair {},
+    #[discriminant(2)]
+    /// The second highest throw, (0,2) or (2,0).
+    LittleMia {},
+    #[discriminant(3)]
+    /// The highest throw, (0,1) or (1,0).
+    Mia {},
+}
+
+/// The score of a dice throw, as computed by the contract.
+#[derive(ReadWriteRPC, CreateTypeSpec, Debug)]
+pub struct ThrowScore {
+    /// The score of the throw. A throw beats another throw if it has a strictly higher score.
+    score: u16,
+    /// The category of the throw.
+    category: ThrowCategory,
 }
 
 /// The contribution each player must send to make a dice throw. The contributions should be in the
-/// interval \[ 0, 5 \] inclusive. If the contributions are outside this interval,
+/// interval \[ 0, die_faces - 1 \] inclusive. If the contributions are outside this interval,
 /// they are normalized to the interval.
 #[derive(CreateTypeSpec, SecretBinary)]
 pub struct RandomContribution {
@@ -219,7 +337,11 @@ pub enum GamePhase {
 /// # Arguments
 ///
 /// * `_ctx` - the contract context containing information about the sender and the blockchain.
-/// *
+/// * `addresses_to_play` - the players of the game, in seating order.
+/// * `max_nr_of_players` - the maximum number of players that can be seated in the game.
+/// * `entry_token` - the MPC-20 token used to pay the entry fee.
+/// * `entry_fee` - the entry fee each player must pay into the pot. If zero, no fee is required.
+/// * `die_faces` - the number of faces on each die. Must be between 4 and 8, inclusive.
 ///
 /// # Returns
 ///
@@ -230,21 +352,38 @@ pub fn initialize(
     context: ContractContext,
     zk_state: ZkState<SecretVarType>,
     addresses_to_play: Vec<Address>,
+    max_nr_of_players: u32,
+    entry_token: Address,
+    entry_fee: u128,
+    die_faces: u8,
 ) -> (MiaState, Vec<EventGroup>) {
     assert!(
         addresses_to_play.len() >= 3,
         "There must be at least 3 players to play Mia."
     );
+    assert!(
+        addresses_to_play.len() <= max_nr_of_players as usize,
+        "There can be at most {} players in this game of Mia.",
+        max_nr_of_players
+    );
     assert_eq!(
         SortedVecSet::from(addresses_to_play.clone()).len(),
         addresses_to_play.len(),
         "No duplicates in players."
     );
+    assert!(
+        (MIN_DIE_FACES..=MAX_DIE_FACES).contains(&die_faces),
+        "The number of die faces must be between {} and {}, but was {}.",
+        MIN_DIE_FACES,
+        MAX_DIE_FACES,
+        die_faces
+    );
 
     let mut state = MiaState {
         starting_players: addresses_to_play.clone(),
         players: addresses_to_play.clone(),
         nr_of_players_at_the_start: addresses_to_play.len() as u32,
+        max_nr_of_players,
         player_lives: SortedVecMap::new(),
         game_phase: GamePhase::Start {},
         player_throwing: 0,
@@ -253,12 +392,15 @@ pub fn initialize(
         stated_throw: None,
         throw_result: None,
         winner: None,
-        throw_to_beat: DiceThrow { d1: 1, d2: 2 },
+        throw_to_beat: None,
+        entry_token,
+        entry_fee,
+        paid_players: SortedVecSet::new(),
+        pot: 0,
+        die_faces,
     };
 
-    for address in addresses_to_play {
-        state.player_lives.insert(address, 6);
-    }
+    state.reset_players();
 
     (state, vec![])
 }
@@ -284,13 +426,225 @@ pub fn start_round(
         state.players[state.player_throwing as usize], context.sender,
         "Only the player whose turn it is can start the round."
     );
+    assert!(
+        state.have_all_players_paid(),
+        "All players must pay the entry fee before the game can start."
+    );
     state.game_phase = GamePhase::AddRandomness {};
 
     (state, vec![], vec![])
 }
 
+/// Pay the entry fee into the pot, by transferring it from the sender to the contract.
+/// The sender must be a player in the game, and the game must not have started.
+#[action(shortname = 0x06, zk = true)]
+pub fn pay_entry_fee(
+    context: ContractContext,
+    state: MiaState,
+    zk_state: ZkState<SecretVarType>,
+) -> (MiaState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    assert_eq!(
+        state.game_phase,
+        GamePhase::Start {},
+        "The entry fee can only be paid before the game has started."
+    );
+    assert!(state.entry_fee > 0, "No entry fee is required for this game.");
+    assert!(
+        state.starting_players.contains(&context.sender),
+        "Only players in the game can pay the entry fee."
+    );
+    assert!(
+        !state.paid_players.contains(&context.sender),
+        "The entry fee has already been paid."
+    );
+
+    let mut event_group = EventGroup::builder();
+    event_group
+        .call(
+            state.entry_token,
+            Shortname::from_u32(MPC20_TRANSFER_FROM_SHORTNAME),
+        )
+        .argument(context.sender)
+        .argument(context.contract_address)
+        .argument(state.entry_fee)
+        .done();
+    event_group
+        .with_callback_rpc(entry_fee_paid::rpc(context.sender))
+        .with_cost(2000)
+        .done();
+
+    (state, vec![event_group.build()], vec![])
+}
+
+/// Callback for the transfer of an entry fee. If the transfer was successful, the player is marked
+/// as having paid and the fee is added to the pot. If the player had already paid, the fee is
+/// transferred back.
+#[callback(shortname = 0x10, zk = true)]
+pub fn entry_fee_paid(
+    context: ContractContext,
+    callback_context: CallbackContext,
+    mut state: MiaState,
+    zk_state: ZkState<SecretVarType>,
+    player: Address,
+) -> (MiaState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    if !callback_context.success {
+        return (state, vec![], vec![]);
+    }
+
+    if state.paid_players.contains(&player) {
+        let event_group = transfer_tokens(&state, player, state.entry_fee);
+        return (state, vec![event_group], vec![]);
+    }
+
+    state.paid_players.insert(player);
+    state.pot += state.entry_fee;
+    (state, vec![], vec![])
+}
+
+/// Claim the pot. Only the winner can claim the pot, once the game is done.
+/// The stakes of players who lost stay in the pot. If the transfer of the pot fails, the pot is
+/// restored, such that the winner can claim it again.
+#[action(shortname = 0x07, zk = true)]
+pub fn claim_winnings(
+    context: ContractContext,
+    mut state: MiaState,
+    zk_state: ZkState<SecretVarType>,
+) -> (MiaState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    assert_eq!(
+        state.game_phase,
+        GamePhase::Done {},
+        "The winnings can only be claimed when the game is done."
+    );
+    assert_eq!(
+        state.winner,
+        Some(context.sender),
+        "Only the winner can claim the winnings."
+    );
+    assert!(state.pot > 0, "There are no winnings to claim.");
+
+    let winnings = state.pot;
+    state.pot = 0;
+
+    let mut event_group = EventGroup::builder();
+    event_group
+        .call(
+            state.entry_token,
+            Shortname::from_u32(MPC20_TRANSFER_SHORTNAME),
+        )
+        .argument(context.sender)
+        .argument(winnings)
+        .done();
+    event_group
+        .with_callback_rpc(winnings_claimed::rpc(winnings))
+        .with_cost(2000)
+        .done();
+
+    (state, vec![event_group.build()], vec![])
+}
+
+/// Callback for the transfer of the winnings. If the transfer failed, the winnings are put back
+/// into the pot.
+#[callback(shortname = 0x11, zk = true)]
+pub fn winnings_claimed(
+    context: ContractContext,
+    callback_context: CallbackContext,
+    mut state: MiaState,
+    zk_state: ZkState<SecretVarType>,
+    winnings: u128,
+) -> (MiaState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    if !callback_context.success {
+        state.pot += winnings;
+    }
+    (state, vec![], vec![])
+}
+
+/// Restart a finished game with the same starting players.
+///
+/// All players get their lives back, and the game returns to the 'Start' phase with the first
+/// starting player in turn. Any leftover secret variables are deleted. If the game has an entry
+/// fee, every player must pay it again before the new game can start.
+///
+/// Only the winner can restart the game, and only once the winnings have been claimed.
+#[action(shortname = 0x08, zk = true)]
+pub fn restart_game(
+    context: ContractContext,
+    mut state: MiaState,
+    zk_state: ZkState<SecretVarType>,
+) -> (MiaState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    assert_eq!(
+        state.game_phase,
+        GamePhase::Done {},
+        "The game can only be restarted when it is done."
+    );
+    assert_eq!(
+        state.winner,
+        Some(context.sender),
+        "Only the winner can restart the game."
+    );
+    assert_eq!(
+        state.pot, 0,
+        "The winnings must be claimed before the game can be restarted."
+    );
+
+    state.reset_players();
+    state.game_phase = GamePhase::Start {};
+    state.player_throwing = 0;
+    state.nr_of_randomness_contributions = 0;
+    state.throw_result_id = None;
+    state.stated_throw = None;
+    state.throw_result = None;
+    state.throw_to_beat = None;
+    state.winner = None;
+
+    (
+        state,
+        vec![],
+        vec![ZkStateChange::DeleteVariables {
+            variables_to_delete: zk_state
+                .secret_variables
+                .iter()
+                .map(|(variable_id, _)| variable_id)
+                .collect(),
+        }],
+    )
+}
+
+/// Get the score of a dice throw with the number of die faces of the game, such that clients can
+/// rank throws using the same ordering as the contract. The values of the dice are reduced in the
+/// same way as announced throws. The [`ThrowScore`] is returned as return data.
+#[action(shortname = 0x09, zk = true)]
+pub fn score_of(
+    context: ContractContext,
+    state: MiaState,
+    zk_state: ZkState<SecretVarType>,
+    throw: DiceThrow,
+) -> (MiaState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    let reduced_throw = throw.reduce(state.die_faces);
+    let score = ThrowScore {
+        score: reduced_throw.get_throw_score(state.die_faces),
+        category: reduced_throw.get_category(),
+    };
+
+    (state, vec![EventGroup::with_return_data(score)], vec![])
+}
+
+/// Creates the event transferring `amount` entry tokens from the contract to `receiver`.
+fn transfer_tokens(state: &MiaState, receiver: Address, amount: u128) -> EventGroup {
+    let mut event_group = EventGroup::builder();
+    event_group
+        .call(
+            state.entry_token,
+            Shortname::from_u32(MPC20_TRANSFER_SHORTNAME),
+        )
+        .argument(receiver)
+        .argument(amount)
+        .done();
+    event_group.build()
+}
+
 /// Add randomness for the next dice throw.
-/// The sender must be a player in the game to add randomness.
+/// The sender must be a player remaining in the game to add randomness. Players who have lost all
+/// of their lives cannot contribute, such that they cannot stall or influence the round.
 #[zk_on_secret_input(shortname = 0x40, secret_type = "RandomContribution")]
 pub fn add_randomness_to_throw(
     context: ContractContext,
@@ -306,7 +660,11 @@ pub fn add_randomness_to_throw(
         GamePhase::AddRandomness {},
         "Must be in the AddRandomness phase to input secret randomness."
     );
-    assert!(state.starting_players.contains(&context.sender));
+    assert!(
+        state.players.contains(&context.sender),
+        "Only players remaining in the game can add randomness. Sender: {:?}",
+        context.sender
+    );
     assert!(
         zk_state
             .secret_variables
@@ -327,7 +685,7 @@ pub fn add_randomness_to_throw(
 
 /// Automatically called when a variable is confirmed on chain.
 ///
-/// Initializes opening.
+/// Moves to the throw phase once every remaining player has contributed randomness.
 #[zk_on_variable_inputted(shortname = 0x01)]
 fn inputted_variable(
     context: ContractContext,
@@ -335,7 +693,7 @@ fn inputted_variable(
     zk_state: ZkState<SecretVarType>,
     variable_id: SecretVarId,
 ) -> MiaState {
-    if state.nr_of_randomness_contributions == state.nr_of_players_at_the_start - 1 {
+    if state.nr_of_randomness_contributions == state.players.len() as u32 - 1 {
         state.nr_of_randomness_contributions = 0;
         state.game_phase = GamePhase::Throw {};
     } else {
@@ -367,6 +725,7 @@ pub fn throw_dice(
         state,
         vec![],
         vec![zk_compute::compute_dice_throw::start(
+            state.die_faces,
             Some(sum_compute_complete::SHORTNAME),
             &SecretVarType::ThrowResult {},
         )],
@@ -411,7 +770,7 @@ fn sum_compute_complete(
 }
 
 /// Announce a value such that the next player can decide if they believe it or not.
-/// The value must be higher than or equal to the throw to beat.
+/// The value must be strictly higher than the throw to beat, if any.
 #[action(shortname = 0x03, zk = true)]
 fn announce_throw(
     context: ContractContext,
@@ -425,10 +784,12 @@ fn announce_throw(
         "Only the current player can state the value of the dice throw."
     );
 
-    let reduced_dice_value = dice_value.reduce();
+    let reduced_dice_value = dice_value.reduce(state.die_faces);
 
-    if !reduced_dice_value.better_than_or_equal(state.throw_to_beat) {
-        panic!("Stated throw must be better than the last stated throw.")
+    if let Some(throw_to_beat) = state.throw_to_beat {
+        if !reduced_dice_value.better_than(throw_to_beat, state.die_faces) {
+            panic!("Stated throw must be better than the last stated throw.")
+        }
     }
 
     state.stated_throw = Some(dice_value);
@@ -439,6 +800,9 @@ fn announce_throw(
 
 /// The next player believes the stated throw, and continues the round, where the throw to beat is
 /// the stated throw.
+///
+/// Since Mia cannot be beaten, believing a stated Mia means giving up, which ends the round and
+/// costs the believing player one life.
 #[action(shortname = 0x04, zk = true)]
 fn believe(
     context: ContractContext,
@@ -456,10 +820,16 @@ fn believe(
         "Must be in the deciding phase to say believe."
     );
 
-    state.game_phase = GamePhase::AddRandomness {};
-    state.throw_to_beat = state.stated_throw.unwrap();
-    state.stated_throw = None;
-    state.go_to_next_player();
+    let stated_throw = state.stated_throw.unwrap();
+    if stated_throw.reduce(state.die_faces).is_mia() {
+        let believer = *state.next_player();
+        state.end_round(believer, 1);
+    } else {
+        state.game_phase = GamePhase::AddRandomness {};
+        state.throw_to_beat = Some(stated_throw.reduce(state.die_faces));
+        state.stated_throw = None;
+        state.go_to_next_player();
+    }
 
     (
         state,
@@ -519,38 +889,27 @@ fn save_opened_variable(
     let variable_id = opened_variables.first().unwrap();
     let result: DiceThrow = read_opened_variable_data(&zk_state, variable_id).unwrap();
 
-    let result_reduced = result.reduce();
+    let result_reduced = result.reduce(state.die_faces);
 
     let Some(stated_throw) = state.stated_throw else {
         panic!("Could not find a stated throw in state.")
     };
 
-    let stated_throw_reduced = stated_throw.reduce();
+    let stated_throw_reduced = stated_throw.reduce(state.die_faces);
 
-    let loser_of_round = if result.better_than_or_equal(stated_throw_reduced) {
-        *state.next_player()
-    } else {
-        *state.current_player()
-    };
-
-    if stated_throw.is_mia() {
-        state.reduce_players_life_by(loser_of_round, 2);
-    } else {
-        state.reduce_players_life_by(loser_of_round, 1);
-    }
-
-    if state.is_player_dead(loser_of_round) {
-        state.remove_dead_player(loser_of_round);
-    }
+    let loser_of_round =
+        if result_reduced.better_than_or_equal(stated_throw_reduced, state.die_faces) {
+            *state.next_player()
+        } else {
+            *state.current_player()
+        };
 
     state.throw_result = Some(result_reduced);
 
-    if state.is_the_game_finished() {
-        state.game_phase = GamePhase::Done {};
-        state.winner = Some(state.get_winner());
+    if stated_throw_reduced.is_mia() {
+        state.end_round(loser_of_round, 2);
     } else {
-        state.go_to_next_player();
-        state.game_phase = GamePhase::AddRandomness {};
+        state.end_round(loser_of_round, 1);
     }
 
     (
@@ -10,11 +10,15 @@ pub struct RandomnessInput {
 
 /// Perform a zk computation on secret-shared randomness added to make a random dice throw.
 ///
+/// ### Arguments:
+///
+/// * `die_faces`: The number of faces on each die. Contributions are reduced to be below this.
+///
 /// ### Returns:
 ///
 /// The sum of the randomness contributions variables.
 #[zk_compute(shortname = 0x61)]
-pub fn compute_dice_throw() -> RandomnessInput {
+pub fn compute_dice_throw(die_faces: u8) -> RandomnessInput {
     let mut throw = RandomnessInput {
         d1: Sbu8::from(0),
         d2: Sbu8::from(0),
@@ -23,8 +27,8 @@ pub fn compute_dice_throw() -> RandomnessInput {
     for variable_id in secret_variable_ids() {
         let raw_contribution: RandomnessInput = load_sbi::<RandomnessInput>(variable_id);
 
-        let d1_reduced = reduce_contribution(raw_contribution.d1);
-        let d2_reduced = reduce_contribution(raw_contribution.d2);
+        let d1_reduced = reduce_contribution(raw_contribution.d1, die_faces);
+        let d2_reduced = reduce_contribution(raw_contribution.d2, die_faces);
 
         throw.d1 = throw.d1 + d1_reduced;
         throw.d2 = throw.d2 + d2_reduced;
@@ -33,11 +37,11 @@ pub fn compute_dice_throw() -> RandomnessInput {
     throw
 }
 
-/// Reduce the contribution if it is not between 0 and 5.
-fn reduce_contribution(value: Sbu8) -> Sbu8 {
+/// Reduce the contribution if it is not between 0 and `die_faces - 1`.
+fn reduce_contribution(value: Sbu8, die_faces: u8) -> Sbu8 {
     let reduce = value & Sbu8::from(0b111);
-    if reduce >= Sbu8::from(6) {
-        reduce - Sbu8::from(6)
+    if reduce >= Sbu8::from(die_faces) {
+        reduce - Sbu8::from(die_faces)
     } else {
         reduce
     }
@@ -5,11 +5,14 @@
 extern crate pbc_contract_codegen;
 extern crate pbc_contract_common;
 
+use create_type_spec_derive::CreateTypeSpec;
 use pbc_contract_common::address::{Address, AddressType, Shortname};
 use pbc_contract_common::context::{CallbackContext, ContractContext};
 use pbc_contract_common::events::EventGroup;
-use pbc_contract_common::sorted_vec_map::SortedVecMap;
+use pbc_contract_common::sorted_vec_map::{SortedVecMap, SortedVecSet};
 use pbc_traits::WriteRPC;
+use read_write_rpc_derive::ReadWriteRPC;
+use read_write_state_derive::ReadWriteState;
 
 const PUB_DEPLOY_ADDRESS: Address = Address::from_components(
     AddressType::SystemContract,
@@ -19,6 +22,52 @@ const PUB_DEPLOY_ADDRESS: Address = Address::from_components(
     ],
 );
 
+/// Shortname of the MPC-20 `transfer` action.
+const MPC20_TRANSFER_SHORTNAME: u32 = 0x01;
+
+/// Shortname of the MPC-20 `transfer_from` action.
+const MPC20_TRANSFER_FROM_SHORTNAME: u32 = 0x03;
+
+/// Shortname of the `tally` action of the voting contract.
+const VOTING_TALLY_SHORTNAME: u32 = 0x03;
+
+/// Percentage of the eligible voters which must vote for a proposal created by a voter, for the
+/// proposal to not be considered spam. The deposit of a spam proposal is slashed.
+const SPAM_THRESHOLD_PERCENT: u32 = 10;
+
+/// A deposit held by the contract for a proposal created by an eligible voter.
+///
+/// ### Fields:
+///
+/// * `proposer`: [`Address`], the voter who created the proposal and paid the deposit.
+/// * `amount`: [`u128`], the amount of deposit tokens held.
+#[derive(ReadWriteState, CreateTypeSpec)]
+pub struct ProposalDeposit {
+    proposer: Address,
+    amount: u128,
+}
+
+/// Summary of the votes cast on a proposal, as returned by `tally` of the voting contract.
+///
+/// ### Fields:
+///
+/// * `voters`: [`u32`], the number of eligible voters.
+/// * `votes_for`: [`u32`], the number of votes cast for the proposal.
+/// * `result`: [`Option<bool>`], the result of the vote, None until the votes have been counted.
+#[derive(ReadWriteRPC)]
+struct VoteTally {
+    voters: u32,
+    votes_for: u32,
+    result: Option<bool>,
+}
+
+impl VoteTally {
+    /// Whether the proposal got too few votes for it, such that it is considered spam.
+    fn is_spam(&self) -> bool {
+        self.votes_for * 100 < self.voters * SPAM_THRESHOLD_PERCENT
+    }
+}
+
 /// Contract state.
 ///
 /// ### Fields:
@@ -29,6 +78,12 @@ const PUB_DEPLOY_ADDRESS: Address = Address::from_components(
 /// * `voting_contract_wasm`: [`Vec<u8>`], bytes of the voting contract wasm.
 /// * `voting_contract_abi`: [`Vec<u8>`], bytes of the voting contract abi.
 /// * `binder_id`: [`i32`], id of the binder used to deploy the voting contract.
+/// * `deposit_token`: [`Address`], the MPC-20 token that proposal deposits are paid in.
+/// * `proposal_deposit`: [`u128`], the deposit required for a voter to create a proposal.
+/// * `proposal_deposits`: [`SortedVecMap<u64, ProposalDeposit>`], deposits held for proposals
+///   created by voters.
+/// * `failed_proposals`: [`SortedVecSet<u64>`], proposal ids whose voting contract could not be
+///   deployed.
 #[state]
 pub struct MultiVotingState {
     owner: Address,
@@ -37,6 +92,10 @@ pub struct MultiVotingState {
     voting_contract_wasm: Vec<u8>,
     voting_contract_abi: Vec<u8>,
     binder_id: i32,
+    deposit_token: Address,
+    proposal_deposit: u128,
+    proposal_deposits: SortedVecMap<u64, ProposalDeposit>,
+    failed_proposals: SortedVecSet<u64>,
 }
 
 /// Initial function to create the initial state.
@@ -47,6 +106,8 @@ pub struct MultiVotingState {
 /// * `voting_contract_wasm`: [`Vec<u8>`], wasm bytes of a voting contract.
 /// * `voting_contract_abi`: [`Vec<u8>`], abi bytes of a voting contract.
 /// * `binder_id`: [`i32`], id of the binder used to deploy the voting contract.
+/// * `deposit_token`: [`Address`], the MPC-20 token that proposal deposits are paid in.
+/// * `proposal_deposit`: [`u128`], the deposit required for a voter to create a proposal.
 ///
 /// ### Returns:
 /// The initial state of type [`MultiVotingState`].
@@ -56,6 +117,8 @@ pub fn initialize(
     voting_contract_wasm: Vec<u8>,
     voting_contract_abi: Vec<u8>,
     binder_id: i32,
+    deposit_token: Address,
+    proposal_deposit: u128,
 ) -> MultiVotingState {
     let eligible_voters = vec![ctx.sender];
     MultiVotingState {
@@ -65,6 +128,10 @@ pub fn initialize(
         voting_contract_wasm,
         voting_contract_abi,
         binder_id,
+        deposit_token,
+        proposal_deposit,
+        proposal_deposits: SortedVecMap::new(),
+        failed_proposals: SortedVecSet::new(),
     }
 }
 
@@ -149,7 +216,231 @@ pub fn add_voting_contract(
     }
 
     state.voting_contracts.insert(p_id, None);
+    state.failed_proposals.remove(&p_id);
+
+    let event_group = deploy_voting_contract(&ctx, &state, p_id, deadline);
+    (state, vec![event_group])
+}
+
+/// Retries the deployment of a voting contract for a proposal whose previous deployment failed,
+/// reusing the stored voting contract wasm and abi. The voting contract is deployed with the
+/// current eligible voters. Only the owner can retry deployments.
+/// This creates an event to the public deploy contract as well as creates a callback to
+/// `add_voting_contract_callback`.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the action call.
+/// * `state`: [`MultiVotingState`], the state before the call.
+/// * `p_id`: [`u64`], the proposal id whose deployment failed.
+/// * `deadline`: [`i64`], the deadline of the vote in UTC millis.
+///
+/// ### Returns:
+/// The new state of type [`MultiVotingState`].
+#[action]
+pub fn retry_voting_contract(
+    ctx: ContractContext,
+    mut state: MultiVotingState,
+    p_id: u64,
+    deadline: i64,
+) -> (MultiVotingState, Vec<EventGroup>) {
+    assert_eq!(ctx.sender, state.owner, "Only owner can retry contracts");
+    assert!(
+        state.failed_proposals.contains(&p_id),
+        "No failed deployment for proposal id"
+    );
+    if state.voting_contracts.contains_key(&p_id) {
+        panic!("Proposal id already exists");
+    }
+
+    state.failed_proposals.remove(&p_id);
+    state.voting_contracts.insert(p_id, None);
+
+    let event_group = deploy_voting_contract(&ctx, &state, p_id, deadline);
+    (state, vec![event_group])
+}
 
+/// Creates a new proposal with a voting contract on behalf of an eligible voter. The voter must
+/// pay a deposit of `proposal_deposit` deposit tokens, which is held by the contract until the
+/// vote has concluded and the deposit is settled using `settle_proposal_deposit`. The voter must
+/// have approved the contract to transfer the deposit beforehand. The proposal id is reserved
+/// while the deposit is transferred, and the voting contract is deployed in
+/// `proposal_deposit_callback` once the deposit has been received.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the action call.
+/// * `state`: [`MultiVotingState`], the state before the call.
+/// * `p_id`: [`u64`], the proposal id of the new voting contract.
+/// * `deadline`: [`i64`], the deadline of the vote in UTC millis.
+///
+/// ### Returns:
+/// The new state of type [`MultiVotingState`].
+#[action]
+pub fn propose_voting_contract(
+    ctx: ContractContext,
+    mut state: MultiVotingState,
+    p_id: u64,
+    deadline: i64,
+) -> (MultiVotingState, Vec<EventGroup>) {
+    assert!(
+        state.eligible_voters.contains(&ctx.sender),
+        "Only eligible voters can propose contracts"
+    );
+    if state.voting_contracts.contains_key(&p_id) {
+        panic!("Proposal id already exists");
+    }
+
+    state.voting_contracts.insert(p_id, None);
+    state.failed_proposals.remove(&p_id);
+
+    let mut event_group = EventGroup::builder();
+    event_group
+        .call(
+            state.deposit_token,
+            Shortname::from_u32(MPC20_TRANSFER_FROM_SHORTNAME),
+        )
+        .argument(ctx.sender)
+        .argument(ctx.contract_address)
+        .argument(state.proposal_deposit)
+        .done();
+    event_group
+        .with_callback_rpc(proposal_deposit_callback::rpc(p_id, deadline, ctx.sender))
+        .with_cost(2000)
+        .done();
+
+    (state, vec![event_group.build()])
+}
+
+/// Callback for the transfer of a proposal deposit. If the transfer was unsuccessful the reserved
+/// proposal id is released. If it instead was successful, the deposit is recorded and the voting
+/// contract is deployed.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the call.
+/// * `callback_ctx`: [`CallbackContext`], the context of the callback.
+/// * `state`: [`MultiVotingState`], the state before the call.
+/// * `p_id`: [`u64`], the proposal id of the new voting contract.
+/// * `deadline`: [`i64`], the deadline of the vote in UTC millis.
+/// * `proposer`: [`Address`], the voter who created the proposal.
+///
+/// ### Returns:
+/// The new state of type [`MultiVotingState`].
+#[callback(shortname = 0x03)]
+pub fn proposal_deposit_callback(
+    ctx: ContractContext,
+    callback_ctx: CallbackContext,
+    mut state: MultiVotingState,
+    p_id: u64,
+    deadline: i64,
+    proposer: Address,
+) -> (MultiVotingState, Vec<EventGroup>) {
+    if !callback_ctx.results[0].succeeded {
+        state.voting_contracts.remove(&p_id);
+        return (state, vec![]);
+    }
+
+    state.proposal_deposits.insert(
+        p_id,
+        ProposalDeposit {
+            proposer,
+            amount: state.proposal_deposit,
+        },
+    );
+
+    let event_group = deploy_voting_contract(&ctx, &state, p_id, deadline);
+    (state, vec![event_group])
+}
+
+/// Settles the deposit of a proposal created by a voter, once the vote on the proposal has
+/// concluded. The tally of the vote is read from the voting contract, and the deposit is settled
+/// in `settle_proposal_deposit_callback`. Anyone can settle deposits.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the action call.
+/// * `state`: [`MultiVotingState`], the state before the call.
+/// * `p_id`: [`u64`], the proposal id whose deposit is settled.
+///
+/// ### Returns:
+/// The new state of type [`MultiVotingState`].
+#[action]
+pub fn settle_proposal_deposit(
+    ctx: ContractContext,
+    state: MultiVotingState,
+    p_id: u64,
+) -> (MultiVotingState, Vec<EventGroup>) {
+    let Some(Some(voting_address)) = state.voting_contracts.get(&p_id) else {
+        panic!("Proposal has not been deployed");
+    };
+    assert!(
+        state.proposal_deposits.contains_key(&p_id),
+        "No deposit for proposal"
+    );
+
+    let mut event_group = EventGroup::builder();
+    event_group
+        .call(*voting_address, Shortname::from_u32(VOTING_TALLY_SHORTNAME))
+        .done();
+    event_group
+        .with_callback_rpc(settle_proposal_deposit_callback::rpc(p_id))
+        .with_cost(2000)
+        .done();
+
+    (state, vec![event_group.build()])
+}
+
+/// Callback for reading the tally of a proposal whose deposit is settled. The deposit is refunded
+/// to the proposer if at least `SPAM_THRESHOLD_PERCENT` percent of the eligible voters voted for
+/// the proposal. Otherwise the proposal is considered spam, and the deposit is slashed by
+/// transferring it to the owner. Settling fails if the votes have not been counted yet.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the call.
+/// * `callback_ctx`: [`CallbackContext`], the context of the callback.
+/// * `state`: [`MultiVotingState`], the state before the call.
+/// * `p_id`: [`u64`], the proposal id whose deposit is settled.
+///
+/// ### Returns:
+/// The new state of type [`MultiVotingState`].
+#[callback(shortname = 0x04)]
+pub fn settle_proposal_deposit_callback(
+    ctx: ContractContext,
+    callback_ctx: CallbackContext,
+    mut state: MultiVotingState,
+    p_id: u64,
+) -> (MultiVotingState, Vec<EventGroup>) {
+    assert!(
+        callback_ctx.results[0].succeeded,
+        "Unable to read the tally of the proposal"
+    );
+    let tally: VoteTally = callback_ctx.results[0].get_return_data();
+    assert!(tally.result.is_some(), "The votes have not been counted yet");
+
+    let deposit = state
+        .proposal_deposits
+        .remove(&p_id)
+        .expect("No deposit for proposal");
+    let receiver = if tally.is_spam() {
+        state.owner
+    } else {
+        deposit.proposer
+    };
+    let event_group = transfer_deposit(&state, receiver, deposit.amount);
+    (state, vec![event_group])
+}
+
+/// Creates the events deploying a new voting contract for the proposal with the given id, with a
+/// callback to `add_voting_contract_callback`. The address of the new voting contract is computed
+/// from the original transaction hash.
+fn deploy_voting_contract(
+    ctx: &ContractContext,
+    state: &MultiVotingState,
+    p_id: u64,
+    deadline: i64,
+) -> EventGroup {
     let voting_address = Address::from_components(
         AddressType::PublicContract,
         ctx.original_transaction.as_ref()[12..32]
@@ -176,13 +467,45 @@ pub fn add_voting_contract(
         .with_cost(1000)
         .done();
 
-    (state, vec![event_group.build()])
+    event_group.build()
+}
+
+/// Creates the event transferring `amount` deposit tokens from the contract to `receiver`.
+fn transfer_deposit(state: &MultiVotingState, receiver: Address, amount: u128) -> EventGroup {
+    let mut event_group = EventGroup::builder();
+    event_group
+        .call(
+            state.deposit_token,
+            Shortname::from_u32(MPC20_TRANSFER_SHORTNAME),
+        )
+        .argument(receiver)
+        .argument(amount)
+        .done();
+    event_group.build()
+}
+
+/// Releases the proposal id of a proposal whose voting contract could not be deployed, and records
+/// the failure such that the deployment can be retried using `retry_voting_contract`. If a voter
+/// paid a deposit for the proposal, the deposit is refunded.
+fn release_failed_proposal(
+    mut state: MultiVotingState,
+    p_id: u64,
+) -> (MultiVotingState, Vec<EventGroup>) {
+    state.voting_contracts.remove(&p_id);
+    state.failed_proposals.insert(p_id);
+    match state.proposal_deposits.remove(&p_id) {
+        Some(deposit) => {
+            let event_group = transfer_deposit(&state, deposit.proposer, deposit.amount);
+            (state, vec![event_group])
+        }
+        None => (state, vec![]),
+    }
 }
 
 /// Callback for adding a new voting contract. If the deployment was unsuccessful the entry in
-/// `voting_contracts` is deleted. If it instead was successful, an empty invocation is made to
-/// the new contract to check if it really has been deployed. A new callback to
-/// `voting_contract_exists_callback` is also created.
+/// `voting_contracts` is deleted, and any deposit paid for the proposal is refunded. If it instead
+/// was successful, an empty invocation is made to the new contract to check if it really has been
+/// deployed. A new callback to `voting_contract_exists_callback` is also created.
 ///
 /// ### Parameters:
 ///
@@ -198,13 +521,12 @@ pub fn add_voting_contract(
 pub fn add_voting_contract_callback(
     ctx: ContractContext,
     callback_ctx: CallbackContext,
-    mut state: MultiVotingState,
+    state: MultiVotingState,
     p_id: u64,
     voting_address: Address,
 ) -> (MultiVotingState, Vec<EventGroup>) {
     if !callback_ctx.results[0].succeeded {
-        state.voting_contracts.remove(&p_id);
-        (state, vec![])
+        release_failed_proposal(state, p_id)
     } else {
         let mut event_group = EventGroup::builder();
 
@@ -218,7 +540,8 @@ pub fn add_voting_contract_callback(
 }
 
 /// Callback for checking if a voting contract has been deployed successfully. If it is the
-/// address is inserted into `voting_contracts`. If it is not the entry is deleted instead.
+/// address is inserted into `voting_contracts`. If it is not the entry is deleted instead, and any
+/// deposit paid for the proposal is refunded.
 ///
 /// ### Parameters:
 ///
@@ -237,13 +560,13 @@ pub fn voting_contract_exists_callback(
     mut state: MultiVotingState,
     p_id: u64,
     voting_address: Address,
-) -> MultiVotingState {
+) -> (MultiVotingState, Vec<EventGroup>) {
     if !callback_ctx.results[0].succeeded {
-        state.voting_contracts.remove(&p_id);
+        release_failed_proposal(state, p_id)
     } else {
         state.voting_contracts.insert(p_id, Some(voting_address));
+        (state, vec![])
     }
-    state
 }
 
 fn create_voting_init_bytes(proposal_id: u64, voters: &Vec<Address>, deadline: i64) -> Vec<u8> {
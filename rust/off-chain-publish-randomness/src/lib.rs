@@ -17,14 +17,22 @@ use read_write_state_derive::ReadWriteState;
 
 mod task_queue;
 
-use task_queue::{EngineIndex, Task, TaskQueue};
+use task_queue::{EngineIndex, Task, TaskId, TaskQueue};
 
 const BUCKET_ID_COMMIT: &[u8] = b"BUCKET_ID_COMMIT";
 const BUCKET_ID_UPLOAD: &[u8] = b"BUCKET_ID_UPLOAD";
 
+/// Time in milliseconds that must pass after a task is created, before the owner can reset the
+/// round using [`reset_round`].
+const RESET_ROUND_TIMEOUT_MILLIS: i64 = 60 * 60 * 1000;
+
 /// Length of a [`Randomness`].
 const LENGTH_OF_RANDOMNESS: usize = 32;
 
+/// Number of [`RoundProof`]s kept in the state. The proof of a round is replaced by the proof of
+/// the round with an identifier this much higher.
+const MAX_ROUND_PROOFS: usize = 32;
+
 /// A piece of randomness.
 type Randomness = Vec<u8>;
 
@@ -41,13 +49,34 @@ struct TaskCommitToRandomness {}
 
 impl Task<TaskUploadRandomness, Randomness> {
     /// Reconstructs the [`Randomness`] from the shares.
+    ///
+    /// The shares are combined in the canonical order of [`Task::all_completion_data`], that is by
+    /// engine index, such that the reconstruction does not depend on the order in which the
+    /// engines uploaded their shares.
     fn reconstruct(self) -> Option<Randomness> {
-        let mut result = vec![0; LENGTH_OF_RANDOMNESS];
-        for share in self.all_completion_data()? {
-            result = xor_bytes(&result, &share);
-        }
-        Some(result)
+        Some(combine_shares(&self.all_completion_data()?))
+    }
+}
+
+/// Combines the shares uploaded by all engines into a single piece of [`Randomness`].
+///
+/// The shares must be given in order of engine index.
+fn combine_shares(shares: &[Randomness]) -> Randomness {
+    let mut result = vec![0; LENGTH_OF_RANDOMNESS];
+    for share in shares {
+        result = xor_bytes(&result, share);
     }
+    result
+}
+
+/// Derives the randomness for a specific domain from a piece of [`Randomness`].
+///
+/// Consumers using different domains get independent values, even when derived from the same
+/// piece of randomness.
+fn derive_randomness_for_domain(randomness: &Randomness, domain: &[u8]) -> Hash {
+    let mut data = randomness.clone();
+    data.extend_from_slice(domain);
+    Hash::digest(data)
 }
 
 /// XORs all bytes in the two given [`Randomness`] values.
@@ -64,13 +93,49 @@ pub struct EngineConfig {
     endpoint: String,
 }
 
+/// Liveness of an engine, given by the most recent tasks the engine has completed.
+///
+/// Allows operators to detect engines that are lagging behind the others.
+#[derive(ReadWriteState, ReadWriteRPC, CreateTypeSpec, Debug, Clone, Default)]
+pub struct EngineLiveness {
+    /// Identifier of the most recent commit task the engine committed to.
+    last_commit_task_id: Option<u32>,
+    /// Identifier of the most recent upload task the engine uploaded randomness for.
+    last_upload_task_id: Option<u32>,
+}
+
+/// Proof of how the [`Randomness`] of a consumed round was produced.
+///
+/// Allows consumers to verify off-chain that every share matches the commitment of its engine,
+/// and that the XOR of all shares is the delivered randomness.
+#[derive(ReadWriteState, ReadWriteRPC, CreateTypeSpec, Debug, Clone)]
+pub struct RoundProof {
+    /// Identifier of the upload task of the round.
+    round_id: TaskId,
+    /// Commitments of each engine, in the same order as the engine configurations.
+    commitments: Vec<Hash>,
+    /// Shares uploaded by each engine, in the same order as the engine configurations.
+    shares: Vec<Randomness>,
+    /// The [`Randomness`] delivered to the consumer of the round.
+    randomness: Randomness,
+}
+
 /// State of the contract.
 #[state]
 pub struct ContractState {
+    /// Owner of the contract. Allowed to reset stuck rounds.
+    owner: Address,
     /// Engine configurations
     engines: Vec<EngineConfig>,
+    /// Liveness of each engine, at the same index as the engine in `engines`.
+    engine_liveness: Vec<EngineLiveness>,
+    /// Block production time of when the most recent task was created.
+    current_task_created_at: i64,
     commit_queue: TaskQueue<TaskCommitToRandomness, Hash>,
     upload_queue: TaskQueue<TaskUploadRandomness, Randomness>,
+    /// Proofs of consumed rounds, where the proof of a round is at index
+    /// `round_id % MAX_ROUND_PROOFS`.
+    round_proofs: Vec<Option<RoundProof>>,
 }
 
 impl ContractState {
@@ -88,8 +153,9 @@ impl ContractState {
         None
     }
 
-    fn start_generating_more_randomness(&mut self) {
-        self.commit_queue.push_task(TaskCommitToRandomness {})
+    fn start_generating_more_randomness(&mut self, now: i64) {
+        self.commit_queue.push_task(TaskCommitToRandomness {});
+        self.current_task_created_at = now;
     }
 
     /// Get the reconstructed [`Randomness`] value if available.
@@ -98,6 +164,37 @@ impl ContractState {
             .get_task(self.upload_queue.task_id_of_current())
             .and_then(|task| task.reconstruct())
     }
+
+    /// Consumes the reconstructed [`Randomness`], and starts generating the next piece of
+    /// randomness. A [`RoundProof`] of the consumed round is kept in the state.
+    fn consume_reconstructed_randomness(&mut self, now: i64) -> Randomness {
+        let round_id = self.upload_queue.task_id_of_current();
+        let Some((commitments, shares)) = self.upload_queue.get_task(round_id).and_then(|task| {
+            let commitments = task.definition().commitments.clone();
+            Some((commitments, task.all_completion_data()?))
+        }) else {
+            panic!("No randomness available!");
+        };
+
+        let randomness = combine_shares(&shares);
+        self.round_proofs[round_id as usize % MAX_ROUND_PROOFS] = Some(RoundProof {
+            round_id,
+            commitments,
+            shares,
+            randomness: randomness.clone(),
+        });
+
+        self.upload_queue.remove_task(round_id);
+        self.start_generating_more_randomness(now);
+        randomness
+    }
+
+    /// Get the proof of the consumed round with the given identifier, if it is still kept.
+    fn round_proof(&self, round_id: TaskId) -> Option<&RoundProof> {
+        self.round_proofs[round_id as usize % MAX_ROUND_PROOFS]
+            .as_ref()
+            .filter(|proof| proof.round_id == round_id)
+    }
 }
 
 /// Initialize contract with the given engine configurations.
@@ -106,13 +203,17 @@ impl ContractState {
 ///
 /// - `engines`: Configurations for all engines that serve the contract.
 #[init]
-pub fn initialize(_ctx: ContractContext, engines: Vec<EngineConfig>) -> ContractState {
+pub fn initialize(ctx: ContractContext, engines: Vec<EngineConfig>) -> ContractState {
     let mut state = ContractState {
+        owner: ctx.sender,
         commit_queue: TaskQueue::new(BUCKET_ID_COMMIT.into(), engines.len() as u32),
         upload_queue: TaskQueue::new(BUCKET_ID_UPLOAD.into(), engines.len() as u32),
+        engine_liveness: vec![EngineLiveness::default(); engines.len()],
         engines,
+        current_task_created_at: ctx.block_production_time,
+        round_proofs: vec![None; MAX_ROUND_PROOFS],
     };
-    state.start_generating_more_randomness();
+    state.start_generating_more_randomness(ctx.block_production_time);
     state
 }
 
@@ -123,18 +224,65 @@ pub fn initialize(_ctx: ContractContext, engines: Vec<EngineConfig>) -> Contract
 /// The [`Randomness`] generated from all engines.
 #[action(shortname = 0x01)]
 pub fn consume_randomness(
-    _ctx: ContractContext,
+    ctx: ContractContext,
     mut state: ContractState,
 ) -> (ContractState, Vec<EventGroup>) {
-    let Some(randomness) = state.get_reconstructed_randomness() else {
-        panic!("No randomness available!");
-    };
+    let randomness = state.consume_reconstructed_randomness(ctx.block_production_time);
+    (state, vec![EventGroup::with_return_data(randomness)])
+}
+
+/// Consumes the latest piece of [`Randomness`], and returns randomness derived from it for the
+/// given domain.
+///
+/// Contracts consuming randomness with different domains get independent values. The derived
+/// value is the hash of the randomness followed by the domain.
+///
+/// ## RPC Arguments
+///
+/// - `domain`: Domain separator of the consumer.
+///
+/// ## Return Value
+///
+/// The [`Hash`] of the [`Randomness`] generated from all engines, followed by the domain.
+#[action(shortname = 0x07)]
+pub fn consume_randomness_for_domain(
+    ctx: ContractContext,
+    mut state: ContractState,
+    domain: Vec<u8>,
+) -> (ContractState, Vec<EventGroup>) {
+    let randomness = state.consume_reconstructed_randomness(ctx.block_production_time);
+    let derived = derive_randomness_for_domain(&randomness, &domain);
+    (state, vec![EventGroup::with_return_data(derived)])
+}
 
+/// Resets a round of randomness generation that is stuck.
+///
+/// A round can get stuck if an engine never responds to the current task, for example if it has
+/// been permanently removed. Removes the current task from both queues and restarts the
+/// randomness generation with a new commit task.
+///
+/// Can only be called by the owner, and only once [`RESET_ROUND_TIMEOUT_MILLIS`] have passed since
+/// the current task was created.
+#[action(shortname = 0x05)]
+pub fn reset_round(ctx: ContractContext, mut state: ContractState) -> ContractState {
+    assert_eq!(ctx.sender, state.owner, "Only the owner can reset the round");
+    assert!(
+        state.get_reconstructed_randomness().is_none(),
+        "Randomness is available, so the round is not stuck"
+    );
+    assert!(
+        ctx.block_production_time >= state.current_task_created_at + RESET_ROUND_TIMEOUT_MILLIS,
+        "Cannot reset the round before the timeout has passed"
+    );
+
+    state
+        .commit_queue
+        .remove_task(state.commit_queue.task_id_of_current());
     state
         .upload_queue
         .remove_task(state.upload_queue.task_id_of_current());
-    state.start_generating_more_randomness();
-    (state, vec![EventGroup::with_return_data(randomness)])
+    state.start_generating_more_randomness(ctx.block_production_time);
+    state
 }
 
 /// Commit to some [`Randomness`] in the contract.
@@ -159,6 +307,7 @@ pub fn commit_to_randomness(
     state
         .commit_queue
         .mark_completion(engine_index, commit_task_id, randomness_commitment);
+    state.engine_liveness[engine_index as usize].last_commit_task_id = Some(commit_task_id);
 
     let task = state
         .commit_queue
@@ -170,11 +319,50 @@ pub fn commit_to_randomness(
             .upload_queue
             .push_task(TaskUploadRandomness { commitments });
         state.commit_queue.remove_task(commit_task_id);
+        state.current_task_created_at = ctx.block_production_time;
     }
 
     state
 }
 
+/// Replace an earlier commitment to some [`Randomness`] in the contract.
+///
+/// Allows an engine to recover if it lost the [`Randomness`] it committed to, for example if it
+/// crashed before uploading. Can only be called by engines that have already committed to the
+/// task, and only while the task is still waiting for commitments from the other engines. Once the
+/// upload task has been created the commitments can no longer be changed.
+///
+/// ## RPC Arguments
+///
+/// - `commit_task_id`: Identifier of the task.
+/// - `randomness_commitment`: New commitment to some randomness.
+#[action(shortname = 0x04)]
+pub fn recommit_to_randomness(
+    ctx: ContractContext,
+    mut state: ContractState,
+    commit_task_id: u32,
+    randomness_commitment: Hash,
+) -> ContractState {
+    let engine_index = state
+        .engine_index(&ctx.sender)
+        .expect("Caller is not one of the engines");
+
+    let task = state
+        .commit_queue
+        .get_task(commit_task_id)
+        .expect("No such commit task, or the commitments are already being uploaded");
+
+    assert!(
+        task.is_completed_by(engine_index),
+        "Caller has not yet committed to the task"
+    );
+
+    state
+        .commit_queue
+        .mark_completion(engine_index, commit_task_id, randomness_commitment);
+    state
+}
+
 /// Upload [`Randomness`] to the contract.
 ///
 /// Can only be called by engines.
@@ -210,14 +398,55 @@ pub fn upload_randomness(
     state
         .upload_queue
         .mark_completion(engine_index, task_id, randomness);
+    state.engine_liveness[engine_index as usize].last_upload_task_id = Some(task_id);
     state
 }
 
+/// Get the liveness of every engine.
+///
+/// ## Return Value
+///
+/// The [`EngineLiveness`] of each engine, in the same order as the engine configurations.
+#[action(shortname = 0x06)]
+pub fn engine_liveness(
+    _ctx: ContractContext,
+    state: ContractState,
+) -> (ContractState, Vec<EventGroup>) {
+    let engine_liveness = state.engine_liveness.clone();
+    (state, vec![EventGroup::with_return_data(engine_liveness)])
+}
+
+/// Get the proof of a consumed round.
+///
+/// Proofs are only kept for [`MAX_ROUND_PROOFS`] rounds, after which they are replaced by the
+/// proofs of later rounds.
+///
+/// ## RPC Arguments
+///
+/// - `round_id`: Identifier of the upload task of the round.
+///
+/// ## Return Value
+///
+/// The [`RoundProof`] of the round.
+#[action(shortname = 0x08)]
+pub fn prove_round(
+    _ctx: ContractContext,
+    state: ContractState,
+    round_id: u32,
+) -> (ContractState, Vec<EventGroup>) {
+    let proof = state
+        .round_proof(round_id)
+        .cloned()
+        .unwrap_or_else(|| panic!("No proof stored for round {round_id}"));
+    (state, vec![EventGroup::with_return_data(proof)])
+}
+
 /// Solves the off-chain tasks that are currently in the task queues.
 #[off_chain_on_state_change]
 pub fn off_chain_on_state_update(mut ctx: OffChainContext, state: ContractState) {
     if engine_is_assigned_to_contract(&mut ctx, &state) {
         update_commitment(&mut ctx, &state);
+        update_recommitment(&mut ctx, &state);
         update_upload(&mut ctx, &state);
     }
 }
@@ -251,6 +480,34 @@ fn update_commitment(ctx: &mut OffChainContext, state: &ContractState) {
     );
 }
 
+/// Checks whether the commitment of the engine to the current commit task refers to randomness
+/// that the engine does not have, and replaces the commitment if so.
+///
+/// This happens if the engine lost the randomness it committed to, for example by crashing before
+/// the upload. New randomness is generated and stored under the new commitment, such that the
+/// engine can upload it once all engines have committed.
+fn update_recommitment(ctx: &mut OffChainContext, state: &ContractState) -> Option<()> {
+    let engine_index = state.engine_index(&ctx.execution_engine_address)?;
+    let task = state
+        .commit_queue
+        .get_task(state.commit_queue.task_id_of_current())?;
+    let commitment: Hash = task.completion_data_of(engine_index)?.clone();
+    if storage_commit_to_share(ctx).get(&commitment).is_some() {
+        return None;
+    }
+
+    let randomness: Randomness = ctx.get_random_bytes(LENGTH_OF_RANDOMNESS as u32);
+    let new_commitment = Hash::digest(&randomness);
+    storage_commit_to_share(ctx).insert(new_commitment.clone(), randomness);
+
+    ctx.call_contract(recommit_to_randomness::rpc(task.id(), new_commitment))
+        .with_transport_fee_from_rpc()
+        .with_additional_gas(CPU_FEE_COMMIT_TO_RANDOMNESS)
+        .send();
+
+    Some(())
+}
+
 /// Checks the on-chain state for whether there is an unresolved upload task, and solves it.
 ///
 /// This involves loading the randomness that have been committed to, and then sending it to the
@@ -283,3 +540,76 @@ fn storage_commit_to_share(ctx: &mut OffChainContext) -> OffChainStorage<'_, Has
 
 /// Bucket id used to store the shares that have been committed to.
 const BUCKET_ID_COMMITMENTS_TO_SHARE: &[u8] = b"BUCKET_ID_COMMITMENTS_TO_SHARE";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Different domains derive different values from the same randomness.
+    #[test]
+    fn test_different_domains_derive_different_randomness() {
+        let randomness: Randomness = vec![7; LENGTH_OF_RANDOMNESS];
+
+        let first = derive_randomness_for_domain(&randomness, b"first-consumer");
+        let second = derive_randomness_for_domain(&randomness, b"second-consumer");
+
+        assert_ne!(first, second);
+        assert_ne!(first, derive_randomness_for_domain(&randomness, b""));
+    }
+
+    /// The combined randomness is the XOR of all shares.
+    #[test]
+    fn test_combine_shares_xors_all_shares() {
+        let shares: Vec<Randomness> = vec![
+            vec![0b0101; LENGTH_OF_RANDOMNESS],
+            vec![0b0011; LENGTH_OF_RANDOMNESS],
+            vec![0b1000; LENGTH_OF_RANDOMNESS],
+        ];
+
+        assert_eq!(combine_shares(&shares), vec![0b1110; LENGTH_OF_RANDOMNESS]);
+        assert_eq!(combine_shares(&[]), vec![0; LENGTH_OF_RANDOMNESS]);
+    }
+
+    /// The reconstructed randomness does not depend on the order the shares were uploaded in.
+    #[test]
+    fn test_reconstruct_independent_of_upload_order() {
+        let shares: Vec<Randomness> = vec![
+            vec![0b0101; LENGTH_OF_RANDOMNESS],
+            vec![0b0011; LENGTH_OF_RANDOMNESS],
+            vec![0b1000; LENGTH_OF_RANDOMNESS],
+        ];
+        let upload_orders: [[EngineIndex; 3]; 3] = [[0, 1, 2], [2, 1, 0], [1, 2, 0]];
+
+        for upload_order in upload_orders {
+            let mut queue: TaskQueue<TaskUploadRandomness, Randomness> = TaskQueue::new(vec![1], 3);
+            queue.push_task(TaskUploadRandomness {
+                commitments: vec![],
+            });
+            for engine_index in upload_order {
+                queue.mark_completion(engine_index, 1, shares[engine_index as usize].clone());
+            }
+
+            let task = queue.get_task(1).unwrap();
+            assert_eq!(task.all_completion_data(), Some(shares.clone()));
+            assert_eq!(
+                queue.get_task(1).unwrap().reconstruct(),
+                Some(combine_shares(&shares))
+            );
+        }
+    }
+
+    /// The same domain always derives the same value from the same randomness.
+    #[test]
+    fn test_same_domain_derives_same_randomness() {
+        let randomness: Randomness = vec![7; LENGTH_OF_RANDOMNESS];
+
+        assert_eq!(
+            derive_randomness_for_domain(&randomness, b"consumer"),
+            derive_randomness_for_domain(&randomness, b"consumer")
+        );
+        assert_eq!(
+            derive_randomness_for_domain(&randomness, b"consumer"),
+            Hash::digest([randomness.as_slice(), b"consumer"].concat())
+        );
+    }
+}
@@ -21,7 +21,10 @@ pub struct Task<DefinitionT: ReadWriteState, CompletionT: ReadWriteState> {
     id: TaskId,
     /// Definition of the [`Task`].
     definition: DefinitionT,
-    /// Completion data reported by each engine.
+    /// Completion data reported by each engine, indexed by [`EngineIndex`].
+    ///
+    /// The position of the completion data is determined by the engine reporting it, and not by
+    /// the order in which the engines complete the task.
     completion_data: Vec<Option<CompletionT>>,
 }
 
@@ -32,6 +35,10 @@ impl<DefinitionT: ReadWriteState, CompletionT: WriteRPC + ReadWriteState>
     ///
     /// Can be used to check whether the task is completed or not, and then react to the completion
     /// data.
+    ///
+    /// The completion data is ordered canonically by [`EngineIndex`], such that the completion data
+    /// of engine `i` is at index `i`, independently of the order in which the engines completed the
+    /// task. Combinations of the completion data that depend on its order are thus well-defined.
     pub fn all_completion_data(self) -> Option<Vec<CompletionT>> {
         let mut result = vec![];
         for share in self.completion_data {
@@ -45,6 +52,18 @@ impl<DefinitionT: ReadWriteState, CompletionT: WriteRPC + ReadWriteState>
         self.completion_data.iter().all(Option::is_some)
     }
 
+    /// Check whether the given engine have reported completion data for the task.
+    pub fn is_completed_by(&self, engine_index: EngineIndex) -> bool {
+        self.completion_data
+            .get(engine_index as usize)
+            .is_some_and(Option::is_some)
+    }
+
+    /// Get the completion data reported by the given engine, if any.
+    pub fn completion_data_of(&self, engine_index: EngineIndex) -> Option<&CompletionT> {
+        self.completion_data.get(engine_index as usize)?.as_ref()
+    }
+
     /// Get the id of the task.
     pub fn id(&self) -> TaskId {
         self.id
@@ -318,6 +337,43 @@ mod tests {
         );
     }
 
+    /// Completion is tracked for each engine individually, and can be overwritten.
+    #[test]
+    fn task_completed_by_engine() {
+        let mut queue: TaskQueue<Empty, u32> = TaskQueue::new(vec![1, 2, 3], 2);
+
+        queue.push_task(Empty {});
+        assert!(!queue.get_task(1).unwrap().is_completed_by(0));
+        assert!(!queue.get_task(1).unwrap().is_completed_by(1));
+
+        queue.mark_completion(0, 1, 10);
+        assert!(queue.get_task(1).unwrap().is_completed_by(0));
+        assert!(!queue.get_task(1).unwrap().is_completed_by(1));
+
+        queue.mark_completion(0, 1, 11);
+        queue.mark_completion(1, 1, 20);
+        assert_eq!(
+            queue.get_task(1).unwrap().all_completion_data(),
+            Some(vec![11, 20])
+        );
+    }
+
+    /// Completion data is ordered by engine index, independently of the order of completion.
+    #[test]
+    fn task_completion_data_ordered_by_engine() {
+        let mut queue: TaskQueue<Empty, u32> = TaskQueue::new(vec![1, 2, 3], 3);
+
+        queue.push_task(Empty {});
+        queue.mark_completion(2, 1, 30);
+        queue.mark_completion(0, 1, 10);
+        queue.mark_completion(1, 1, 20);
+
+        assert_eq!(
+            queue.get_task(1).unwrap().all_completion_data(),
+            Some(vec![10, 20, 30])
+        );
+    }
+
     /// Tasks can be removed while current
     #[test]
     fn remove_current_task() {
@@ -15,6 +15,7 @@ use matchit::Params;
 use pbc_contract_common::address::Address;
 use pbc_contract_common::avl_tree_map::AvlTreeMap;
 use pbc_contract_common::context::ContractContext;
+use pbc_contract_common::events::EventGroup;
 use pbc_contract_common::off_chain::{
     HttpRequestData, HttpResponseData, OffChainContext, OffChainStorage,
 };
@@ -61,6 +62,26 @@ struct Sharing {
     download_deadline: TimestampMsSinceUnix,
 }
 
+/// On-chain record of a request for downloading a [`Sharing`].
+#[derive(ReadWriteState, ReadWriteRPC, CreateTypeSpec, Debug, Clone)]
+struct DownloadRequest {
+    /// Identifier of the requested sharing.
+    sharing_id: SharingId,
+    /// The user requesting the download.
+    requester: Address,
+    /// The block production time at which the download was requested.
+    requested_at: TimestampMsSinceUnix,
+}
+
+/// Upload progress of a [`Sharing`].
+#[derive(ReadWriteState, ReadWriteRPC, CreateTypeSpec, Debug)]
+struct SharingProgress {
+    /// Number of nodes that have indicated completion of upload.
+    completed_nodes: u32,
+    /// Total number of nodes that must receive a share.
+    total_nodes: u32,
+}
+
 /// Individual secret-share; one part of a [`Sharing`].
 #[derive(ReadWriteState)]
 struct SecretShare {
@@ -116,11 +137,20 @@ impl SecretShare {
 }
 
 impl Sharing {
+    /// Number of nodes that have indicated completion of upload.
+    fn completed_upload_count(&self) -> usize {
+        self.nodes_with_completed_upload
+            .iter()
+            .filter(|x| **x)
+            .count()
+    }
+
     /// Checks whether the authentication required for accessing the [`Sharing`].
     ///
     /// The authentication consists of a ECDSA signature over the secp256k1 curve signed by
     /// the owner of the sharing. The signature is placed in the Authorization header as a
-    /// hex encoded string prefixed with `secp256k1 `.
+    /// hex encoded string prefixed with `secp256k1 `. The signature is only valid for
+    /// `signature_valid_duration_ms` milliseconds after its timestamp.
     ///
     /// The message for checking the signature consists of the following all serialized as rpc.
     ///
@@ -133,6 +163,7 @@ impl Sharing {
         &self,
         request: &HttpRequestData,
         off_chain_context: &OffChainContext,
+        signature_valid_duration_ms: TimestampMsSinceUnix,
     ) -> bool {
         let Some(header) = request.get_header_value("Authorization") else {
             return false;
@@ -148,7 +179,7 @@ impl Sharing {
             .unwrap()
             .as_millis() as TimestampMsSinceUnix;
 
-        if (current_time - credentials.timestamp) > TIMESTAMP_VALID_DURATION_MS {
+        if (current_time - credentials.timestamp) > signature_valid_duration_ms {
             return false;
         }
 
@@ -169,9 +200,10 @@ impl Sharing {
         &self,
         request: &HttpRequestData,
         off_chain_context: &OffChainContext,
+        signature_valid_duration_ms: TimestampMsSinceUnix,
     ) -> Result<(), HttpResponseData> {
         validate_condition_or_produce_http_error(
-            self.is_authenticated(request, off_chain_context),
+            self.is_authenticated(request, off_chain_context, signature_valid_duration_ms),
             401,
             JSON_RESPONSE_UNAUTHORIZED,
         )
@@ -246,12 +278,27 @@ impl Secp256k1Credentials {
 /// State of the contract.
 #[state]
 pub struct ContractState {
+    /// Owner of the contract. Is the only user allowed to add nodes.
+    owner: Address,
     /// Node configurations
     nodes: Vec<NodeConfig>,
     /// Active secret sharings
     secret_sharings: AvlTreeMap<SharingId, Sharing>,
     /// Queue of sharings currently being deleted
     deletion_queue: AvlTreeMap<SharingId, Vec<bool>>,
+    /// Ring buffer of the most recent download requests. Once it holds
+    /// [`MAX_DOWNLOAD_REQUEST_LOG_SIZE`] entries, each new request overwrites the oldest one.
+    download_request_log: Vec<DownloadRequest>,
+    /// Number of download requests logged since deployment, which determines the position of the
+    /// oldest request in `download_request_log`.
+    download_request_count: u64,
+    /// Whether registrations must use distinct commitments for every node.
+    ///
+    /// Legitimate shares for different nodes should differ, so identical commitments indicate a
+    /// misconfigured client sending the same data to several nodes.
+    require_distinct_commitments: bool,
+    /// Number of milliseconds a signed HTTP request is valid for after its timestamp.
+    signature_valid_duration_ms: TimestampMsSinceUnix,
 }
 
 impl ContractState {
@@ -279,6 +326,33 @@ impl ContractState {
                 JSON_RESPONSE_UNKNOWN_SHARING,
             ))
     }
+
+    /// Append a [`DownloadRequest`] to the download request log, overwriting the oldest entry if
+    /// the log is full.
+    fn log_download_request(&mut self, download_request: DownloadRequest) {
+        if self.download_request_log.len() < MAX_DOWNLOAD_REQUEST_LOG_SIZE {
+            self.download_request_log.push(download_request);
+        } else {
+            let oldest_index = self.oldest_download_request_index();
+            self.download_request_log[oldest_index] = download_request;
+        }
+        self.download_request_count += 1;
+    }
+
+    /// Index of the oldest [`DownloadRequest`] in the download request log.
+    fn oldest_download_request_index(&self) -> usize {
+        let log_size = self.download_request_log.len() as u64;
+        self.download_request_count.checked_rem(log_size).unwrap_or(0) as usize
+    }
+
+    /// Get up to `count` of the most recent [`DownloadRequest`]s, oldest first.
+    fn recent_download_requests(&self, count: usize) -> Vec<DownloadRequest> {
+        let log_size = self.download_request_log.len();
+        let oldest_index = self.oldest_download_request_index();
+        (log_size.saturating_sub(count)..log_size)
+            .map(|position| self.download_request_log[(oldest_index + position) % log_size].clone())
+            .collect()
+    }
 }
 
 /// Initialize contract with the given node configurations.
@@ -286,12 +360,33 @@ impl ContractState {
 /// ## RPC Arguments
 ///
 /// - `nodes`: Configurations for all nodes that serve the contract.
+/// - `require_distinct_commitments`: Whether to reject registrations where two share
+///   commitments are identical.
+/// - `signature_valid_duration_ms`: Number of milliseconds a signed HTTP request is valid for.
+///   Must be positive and at most [`MAX_SIGNATURE_VALID_DURATION_MS`].
 #[init]
-pub fn initialize(_ctx: ContractContext, nodes: Vec<NodeConfig>) -> ContractState {
+pub fn initialize(
+    ctx: ContractContext,
+    nodes: Vec<NodeConfig>,
+    require_distinct_commitments: bool,
+    signature_valid_duration_ms: TimestampMsSinceUnix,
+) -> ContractState {
+    assert!(
+        signature_valid_duration_ms > 0
+            && signature_valid_duration_ms <= MAX_SIGNATURE_VALID_DURATION_MS,
+        "Signature validity duration must be between 1 and {} ms, but was {} ms",
+        MAX_SIGNATURE_VALID_DURATION_MS,
+        signature_valid_duration_ms
+    );
     ContractState {
+        owner: ctx.sender,
         nodes,
         secret_sharings: AvlTreeMap::new(),
         deletion_queue: AvlTreeMap::new(),
+        download_request_log: vec![],
+        download_request_count: 0,
+        require_distinct_commitments,
+        signature_valid_duration_ms,
     }
 }
 
@@ -319,6 +414,12 @@ pub fn register_sharing(
         state.nodes.len(),
         "Invalid number of share commitments"
     );
+    if state.require_distinct_commitments {
+        assert!(
+            all_distinct(&share_commitments),
+            "Share commitments must be distinct"
+        );
+    }
 
     let nodes_with_completed_upload = vec![false; state.nodes.len()];
 
@@ -335,6 +436,14 @@ pub fn register_sharing(
     state
 }
 
+/// Checks whether all the given commitments are distinct.
+fn all_distinct(share_commitments: &[Hash]) -> bool {
+    share_commitments
+        .iter()
+        .enumerate()
+        .all(|(index, commitment)| !share_commitments[index + 1..].contains(commitment))
+}
+
 /// Register that the sharing with the given id has been completed for the calling node.
 ///
 /// ## RPC Arguments
@@ -363,6 +472,9 @@ pub fn register_shared(
 
 const DOWNLOAD_PERIOD_DURATION_MS: TimestampMsSinceUnix = 5 * 60 * 1000; // 5 minutes
 
+/// Maximum number of entries kept in [`ContractState::download_request_log`].
+const MAX_DOWNLOAD_REQUEST_LOG_SIZE: usize = 32;
+
 /// Register that the owner of a secret-sharing wishes to download it.
 ///
 /// ## RPC Arguments
@@ -383,20 +495,67 @@ pub fn request_download(
         "Caller is not the owner of the sharing"
     );
     assert_eq!(
-        sharing
-            .nodes_with_completed_upload
-            .iter()
-            .filter(|x| **x)
-            .count(),
+        sharing.completed_upload_count(),
         state.nodes.len(),
         "Shares haven't been uploaded to all nodes yet"
     );
 
     sharing.download_deadline = ctx.block_production_time + DOWNLOAD_PERIOD_DURATION_MS;
     state.secret_sharings.insert(sharing_id, sharing);
+    state.log_download_request(DownloadRequest {
+        sharing_id,
+        requester: ctx.sender,
+        requested_at: ctx.block_production_time,
+    });
     state
 }
 
+/// Get the most recent download requests.
+///
+/// ### RPC Arguments
+///
+/// - `count`: Maximum number of requests to return.
+///
+/// ## Return Value
+///
+/// Up to `count` of the most recent [`DownloadRequest`]s, oldest first.
+#[action(shortname = 0x06)]
+pub fn recent_download_requests(
+    _ctx: ContractContext,
+    state: ContractState,
+    count: u32,
+) -> (ContractState, Vec<EventGroup>) {
+    let recent_requests = state.recent_download_requests(count as usize);
+    (state, vec![EventGroup::with_return_data(recent_requests)])
+}
+
+/// Get the upload progress of a sharing, such that the owner can tell whether all nodes have
+/// received their share before requesting a download.
+///
+/// ### RPC Arguments
+///
+/// - `sharing_id`: Identifier of the sharing.
+///
+/// ## Return Value
+///
+/// The [`SharingProgress`] of the sharing.
+#[action(shortname = 0x09)]
+pub fn sharing_progress(
+    _ctx: ContractContext,
+    state: ContractState,
+    sharing_id: SharingId,
+) -> (ContractState, Vec<EventGroup>) {
+    let sharing = state
+        .secret_sharings
+        .get(&sharing_id)
+        .expect("Unknown sharing");
+    let progress = SharingProgress {
+        completed_nodes: sharing.completed_upload_count() as u32,
+        total_nodes: state.nodes.len() as u32,
+    };
+    (state, vec![EventGroup::with_return_data(progress)])
+}
+
 /// Delete sharing with the given id.
 ///
 /// ### RPC Arguments
@@ -466,6 +625,90 @@ pub fn register_deleted(
     state
 }
 
+/// Add a new node to the node set.
+///
+/// Existing sharings are extended with the new node, but are not considered completely uploaded
+/// until their owner has registered a commitment for the new node's share using
+/// [`register_share_commitment`], and uploaded the share to the new node. Sharings that are
+/// being deleted are never uploaded to the new node, so it is marked as having deleted its share.
+///
+/// ### RPC Arguments
+///
+/// - `node`: Configuration of the node to add.
+#[action(shortname = 0x07)]
+pub fn add_node(ctx: ContractContext, mut state: ContractState, node: NodeConfig) -> ContractState {
+    assert_eq!(
+        ctx.sender, state.owner,
+        "Only the owner of the contract can add nodes"
+    );
+    assert!(
+        state.node_index(&node.address).is_none(),
+        "Node is already part of the node set"
+    );
+    state.nodes.push(node);
+
+    let sharing_ids: Vec<SharingId> = state.secret_sharings.iter().map(|(id, _)| id).collect();
+    for sharing_id in sharing_ids {
+        let mut sharing = state.secret_sharings.get(&sharing_id).unwrap();
+        sharing.nodes_with_completed_upload.push(false);
+        state.secret_sharings.insert(sharing_id, sharing);
+    }
+
+    let deleted_sharing_ids: Vec<SharingId> =
+        state.deletion_queue.iter().map(|(id, _)| id).collect();
+    for sharing_id in deleted_sharing_ids {
+        let mut deletion_status = state.deletion_queue.get(&sharing_id).unwrap();
+        deletion_status.push(true);
+        state.deletion_queue.insert(sharing_id, deletion_status);
+    }
+
+    state
+}
+
+/// Register the commitment for the share of a node added after the sharing was registered.
+///
+/// Commitments are registered in the order the nodes were added, such that the commitment for a
+/// node always has the same index as the node.
+///
+/// ### RPC Arguments
+///
+/// - `sharing_id`: Identifier of the sharing.
+/// - `share_commitment`: Commitment for the share of the next node without a commitment.
+#[action(shortname = 0x08)]
+pub fn register_share_commitment(
+    ctx: ContractContext,
+    mut state: ContractState,
+    sharing_id: SharingId,
+    share_commitment: Hash,
+) -> ContractState {
+    let mut sharing = state
+        .secret_sharings
+        .get(&sharing_id)
+        .expect("Unknown sharing");
+    assert_eq!(
+        ctx.sender, sharing.owner,
+        "Caller is not the owner of the sharing"
+    );
+    assert!(
+        !state.deletion_queue.contains_key(&sharing_id),
+        "Sharing is being deleted"
+    );
+    assert!(
+        sharing.share_commitments.len() < state.nodes.len(),
+        "Sharing already has a commitment for every node"
+    );
+    if state.require_distinct_commitments {
+        assert!(
+            !sharing.share_commitments.contains(&share_commitment),
+            "Share commitments must be distinct"
+        );
+    }
+
+    sharing.share_commitments.push(share_commitment);
+    state.secret_sharings.insert(sharing_id, sharing);
+    state
+}
+
 const BUCKET_KEY_SHARES: [u8; 6] = *b"SHARES";
 
 const JSON_RESPONSE_UNKNOWN_URL: &str = "{ \"error\": \"Invalid URL\" }";
@@ -476,10 +719,15 @@ const JSON_RESPONSE_UNAUTHORIZED: &str = "{ \"error\": \"Unauthorized\" }";
 const JSON_RESPONSE_ALREADY_STORED: &str = "{ \"error\": \"Already stored\" }";
 const JSON_RESPONSE_DEADLINE_PASSED: &str =
     "{ \"error\": \"Download not requested, or download deadline has been passed\" }";
-const JSON_RESPONSE_COMMITMENT_MISMATCH: &str =
-    "{ \"error\": \"User uploaded data doesn't match commitment\" }";
+const JSON_RESPONSE_MISSING_COMMITMENT: &str =
+    "{ \"error\": \"No commitment registered for this node\" }";
+
+/// Number of bytes of the hashes included in the response to an upload not matching its
+/// commitment. Only a prefix is included, which is enough to tell hashes apart when debugging.
+const COMMITMENT_MISMATCH_HASH_PREFIX_LENGTH: usize = 4;
 
-const TIMESTAMP_VALID_DURATION_MS: TimestampMsSinceUnix = 1000 * 60; // 1 minute
+/// Maximum number of milliseconds a signed HTTP request can be configured to be valid for.
+const MAX_SIGNATURE_VALID_DURATION_MS: TimestampMsSinceUnix = 1000 * 60 * 10; // 10 minutes
 
 /// Off-chain receives an HTTP request.
 ///
@@ -548,7 +796,7 @@ fn http_sharing_put(
 ) -> Result<HttpResponseData, HttpResponseData> {
     let sharing_id = parse_sharing_id(params)?;
     let sharing = state.get_sharing(sharing_id)?;
-    sharing.assert_is_authenticated(&request, &ctx)?;
+    sharing.assert_is_authenticated(&request, &ctx, state.signature_valid_duration_ms)?;
 
     let node_index = state.node_index(&ctx.execution_engine_address).unwrap();
 
@@ -556,12 +804,20 @@ fn http_sharing_put(
         return Err(HttpResponseData::new_with_str(400, JSON_RESPONSE_MALFORMED));
     };
 
-    let expected_hash_of_share = sharing.share_commitments.get(node_index).unwrap();
-    validate_condition_or_produce_http_error(
-        &secret_share.hash() == expected_hash_of_share,
-        401,
-        JSON_RESPONSE_COMMITMENT_MISMATCH,
-    )?;
+    let Some(expected_hash_of_share) = sharing.share_commitments.get(node_index) else {
+        return Err(HttpResponseData::new_with_str(
+            409,
+            JSON_RESPONSE_MISSING_COMMITMENT,
+        ));
+    };
+    let computed_hash_of_share = secret_share.hash();
+    if &computed_hash_of_share != expected_hash_of_share {
+        return Err(commitment_mismatch_response(
+            node_index,
+            expected_hash_of_share,
+            &computed_hash_of_share,
+        ));
+    }
 
     let mut storage = secret_share_storage(&mut ctx);
     let existing_data: Option<SecretShare> = storage.get(&sharing_id);
@@ -580,6 +836,26 @@ fn http_sharing_put(
     Ok(HttpResponseData::new_with_str(201, ""))
 }
 
+/// Create the 401 response for an uploaded share that doesn't match its commitment.
+///
+/// The response includes the index of the node, and prefixes of both the expected and the
+/// computed hash of the share, such that the failing upload can be identified. The hashes are
+/// commitments to the share, so they don't leak any of the secret data.
+fn commitment_mismatch_response(
+    node_index: NodeIndex,
+    expected_hash: &Hash,
+    computed_hash: &Hash,
+) -> HttpResponseData {
+    let body = format!(
+        "{{ \"error\": \"User uploaded data doesn't match commitment\", \"node_index\": {}, \
+         \"expected_hash_prefix\": \"{}\", \"computed_hash_prefix\": \"{}\" }}",
+        node_index,
+        hex::encode(&expected_hash.bytes[..COMMITMENT_MISMATCH_HASH_PREFIX_LENGTH]),
+        hex::encode(&computed_hash.bytes[..COMMITMENT_MISMATCH_HASH_PREFIX_LENGTH]),
+    );
+    HttpResponseData::new_with_str(401, &body)
+}
+
 /// Download an existing sharing with the given id. Requires the user to be the owner of the
 /// variable, and to have requested permission by calling [`request_download`].
 ///
@@ -598,7 +874,7 @@ fn http_sharing_get(
 ) -> Result<HttpResponseData, HttpResponseData> {
     let sharing_id = parse_sharing_id(params)?;
     let sharing = state.get_sharing(sharing_id)?;
-    sharing.assert_is_authenticated(&request, &ctx)?;
+    sharing.assert_is_authenticated(&request, &ctx, state.signature_valid_duration_ms)?;
     sharing.assert_download_deadline_not_passed(&ctx)?;
 
     let existing_data: SecretShare = secret_share_storage(&mut ctx)
@@ -0,0 +1,179 @@
+#![doc = include_str!("../README.md")]
+
+#[macro_use]
+extern crate pbc_contract_codegen;
+
+use pbc_contract_common::address::{Address, Shortname};
+use pbc_contract_common::avl_tree_map::AvlTreeMap;
+use pbc_contract_common::context::{CallbackContext, ContractContext};
+use pbc_contract_common::events::EventGroup;
+
+/// The MPC-20 shortname of `transfer_from`.
+const MPC20_TRANSFER_FROM_SHORTNAME: u32 = 0x03;
+
+/// State of the token receiver contract.
+///
+/// ### Fields:
+///
+/// * `owner`: [`Address`], the deployer of the contract, allowed to stop accepting deposits.
+/// * `token`: [`Address`], the token accepted by the contract.
+/// * `accepting`: [`bool`], whether the contract accepts deposits.
+/// * `deposits`: [`AvlTreeMap<Address, u128>`], the number of tokens deposited by each depositor.
+#[state]
+pub struct TokenReceiverState {
+    owner: Address,
+    token: Address,
+    accepting: bool,
+    deposits: AvlTreeMap<Address, u128>,
+}
+
+impl TokenReceiverState {
+    /// Record that `depositor` has deposited `amount` tokens.
+    fn add_deposit(&mut self, depositor: Address, amount: u128) {
+        let deposited = self.deposits.get(&depositor).unwrap_or(0);
+        self.deposits.insert(depositor, deposited + amount);
+    }
+}
+
+/// Initialize a new token receiver, accepting deposits of the given token.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], initial context.
+/// * `token`: [`Address`], the token accepted by the contract.
+///
+/// ### Returns:
+/// The initial state of type [`TokenReceiverState`].
+#[init]
+pub fn initialize(ctx: ContractContext, token: Address) -> TokenReceiverState {
+    TokenReceiverState {
+        owner: ctx.sender,
+        token,
+        accepting: true,
+        deposits: AvlTreeMap::new(),
+    }
+}
+
+/// Set whether the contract accepts deposits. Only the owner can change this.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the action call.
+/// * `state`: [`TokenReceiverState`], the state before the call.
+/// * `accepting`: [`bool`], whether the contract accepts deposits.
+///
+/// ### Returns:
+/// The new state of type [`TokenReceiverState`].
+#[action(shortname = 0x01)]
+pub fn set_accepting(
+    ctx: ContractContext,
+    mut state: TokenReceiverState,
+    accepting: bool,
+) -> TokenReceiverState {
+    assert_eq!(
+        ctx.sender, state.owner,
+        "Only owner can change whether deposits are accepted"
+    );
+    state.accepting = accepting;
+    state
+}
+
+/// Deposit tokens of `depositor` by transferring them to the contract, which must have been
+/// approved to spend them. The sender must be the depositor, or the token calling on behalf of the
+/// depositor from `approve_and_call`. The deposit is recorded in [`deposit_callback`].
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the action call.
+/// * `state`: [`TokenReceiverState`], the state before the call.
+/// * `depositor`: [`Address`], the owner of the deposited tokens.
+/// * `amount`: [`u128`], the number of tokens to deposit.
+///
+/// ### Returns:
+/// The unchanged state, and the event transferring the tokens to the contract.
+#[action(shortname = 0x02)]
+pub fn deposit(
+    ctx: ContractContext,
+    state: TokenReceiverState,
+    depositor: Address,
+    amount: u128,
+) -> (TokenReceiverState, Vec<EventGroup>) {
+    assert!(state.accepting, "Deposits are not accepted");
+    assert!(
+        ctx.sender == depositor || ctx.sender == state.token,
+        "Only the depositor or the token can deposit the tokens of the depositor"
+    );
+
+    let mut event_group = EventGroup::builder();
+    event_group
+        .call(
+            state.token,
+            Shortname::from_u32(MPC20_TRANSFER_FROM_SHORTNAME),
+        )
+        .argument(depositor)
+        .argument(ctx.contract_address)
+        .argument(amount)
+        .done();
+    event_group
+        .with_callback_rpc(deposit_callback::rpc(depositor, amount))
+        .with_cost(2000)
+        .done();
+
+    (state, vec![event_group.build()])
+}
+
+/// Record tokens transferred to the contract by `transfer_and_call` of the token. The tokens are
+/// rejected by failing if the contract does not accept deposits, which reverts the transfer.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the action call.
+/// * `state`: [`TokenReceiverState`], the state before the call.
+/// * `from`: [`Address`], the sender of the tokens.
+/// * `amount`: [`u128`], the number of tokens received.
+/// * `data`: [`Vec<u8>`], additional data of the transfer, unused by this contract.
+///
+/// ### Returns:
+/// The new state of type [`TokenReceiverState`].
+#[action(shortname = 0x30)]
+pub fn on_token_received(
+    ctx: ContractContext,
+    mut state: TokenReceiverState,
+    from: Address,
+    amount: u128,
+    data: Vec<u8>,
+) -> TokenReceiverState {
+    assert!(state.accepting, "Deposits are not accepted");
+    assert_eq!(
+        ctx.sender, state.token,
+        "Only the token can notify the receiver of received tokens"
+    );
+    state.add_deposit(from, amount);
+    state
+}
+
+/// Callback for the transfer of a deposit. If the transfer succeeded, the deposit is recorded.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the callback.
+/// * `callback_ctx`: [`CallbackContext`], the context of the callback.
+/// * `state`: [`TokenReceiverState`], the state before the callback.
+/// * `depositor`: [`Address`], the owner of the deposited tokens.
+/// * `amount`: [`u128`], the number of deposited tokens.
+///
+/// ### Returns:
+/// The new state of type [`TokenReceiverState`].
+#[callback(shortname = 0x01)]
+pub fn deposit_callback(
+    ctx: ContractContext,
+    callback_ctx: CallbackContext,
+    mut state: TokenReceiverState,
+    depositor: Address,
+    amount: u128,
+) -> (TokenReceiverState, Vec<EventGroup>) {
+    if callback_ctx.success {
+        state.add_deposit(depositor, amount);
+    }
+    (state, vec![])
+}
@@ -0,0 +1,1133 @@
+#![doc = include_str!("../README.md")]
+
+#[macro_use]
+extern crate pbc_contract_codegen;
+
+use create_type_spec_derive::CreateTypeSpec;
+use pbc_contract_common::address::{Address, AddressType, Shortname};
+use pbc_contract_common::avl_tree_map::{AvlTreeMap, AvlTreeSet};
+use pbc_contract_common::context::{CallbackContext, ContractContext};
+use pbc_contract_common::events::EventGroup;
+use read_write_rpc_derive::ReadWriteRPC;
+use read_write_state_derive::ReadWriteState;
+
+/// The maximum number of decimals of a token. Larger values make `10^decimals` overflow the
+/// display math of clients, as the total supply is at most `u128::MAX`, which is below `10^39`.
+const MAX_DECIMALS: u8 = 36;
+
+/// The shortname of the action called on contracts receiving tokens from [`transfer_and_call`].
+const ON_TOKEN_RECEIVED_SHORTNAME: u32 = 0x30;
+
+/// The denominator of the transfer fee, such that the fee is given in per mille.
+const PER_MILLE: u128 = 1000;
+
+/// Compute `a * b / c`, rounded down, without overflowing the intermediate product. The result
+/// must fit in a [`u128`], which holds when `b <= c`.
+fn mul_div(a: u128, b: u128, c: u128) -> u128 {
+    // Multiply into a 256-bit product of two 128-bit halves, using 64-bit limbs.
+    let mask = u64::MAX as u128;
+    let (a_high, a_low) = (a >> 64, a & mask);
+    let (b_high, b_low) = (b >> 64, b & mask);
+    let low_low = a_low * b_low;
+    let low_high = a_low * b_high;
+    let middle = (low_low >> 64) + a_high * b_low + (low_high & mask);
+    let low = (middle << 64) | (low_low & mask);
+    let high = a_high * b_high + (middle >> 64) + (low_high >> 64);
+
+    // Divide the product by `c` using binary long division.
+    let mut quotient = 0u128;
+    let mut remainder = 0u128;
+    for bit in (0..256).rev() {
+        let next_bit = if bit >= 128 {
+            (high >> (bit - 128)) & 1
+        } else {
+            (low >> bit) & 1
+        };
+        let overflow = remainder >> 127 == 1;
+        remainder = (remainder << 1) | next_bit;
+        if overflow || remainder >= c {
+            remainder = remainder.wrapping_sub(c);
+            if bit < 128 {
+                quotient |= 1 << bit;
+            }
+        }
+    }
+    quotient
+}
+
+/// Key of an allowance, identifying the owner of the tokens and the spender allowed to move them.
+///
+/// ### Fields:
+///
+/// * `owner`: [`Address`], the owner of the tokens.
+/// * `spender`: [`Address`], the account allowed to transfer the tokens of the owner.
+#[derive(ReadWriteState, CreateTypeSpec, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct AllowedAddress {
+    owner: Address,
+    spender: Address,
+}
+
+/// Tokens assigned to a holder when the token is deployed.
+///
+/// ### Fields:
+///
+/// * `holder`: [`Address`], the account receiving the tokens.
+/// * `amount`: [`u128`], the number of tokens assigned to the holder.
+#[derive(ReadWriteRPC, CreateTypeSpec)]
+pub struct Allocation {
+    holder: Address,
+    amount: u128,
+}
+
+/// Key of a balance at a snapshot, identifying the snapshot and the holder.
+///
+/// ### Fields:
+///
+/// * `snapshot_id`: [`u32`], the id of the snapshot.
+/// * `holder`: [`Address`], the holder of the balance.
+#[derive(ReadWriteState, CreateTypeSpec, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct SnapshotHolder {
+    snapshot_id: u32,
+    holder: Address,
+}
+
+/// A snapshot of the balances, and the dividend distributed among the holders at the snapshot.
+///
+/// ### Fields:
+///
+/// * `total_supply`: [`u128`], the total number of tokens at the snapshot.
+/// * `dividend_pool`: [`u128`], the number of tokens deposited as dividend for the snapshot.
+#[derive(ReadWriteState, CreateTypeSpec)]
+pub struct Snapshot {
+    total_supply: u128,
+    dividend_pool: u128,
+}
+
+/// Fee deducted from transfers and credited to a fee collector.
+///
+/// ### Fields:
+///
+/// * `per_mille`: [`u16`], the fraction of each transfer deducted as fee, in per mille.
+/// * `collector`: [`Address`], the account receiving the fees.
+#[derive(ReadWriteState, ReadWriteRPC, CreateTypeSpec, Clone, Copy)]
+pub struct TransferFee {
+    per_mille: u16,
+    collector: Address,
+}
+
+/// A transfer to a single recipient of [`bulk_transfer_with_events`].
+///
+/// ### Fields:
+///
+/// * `to`: [`Address`], the receiver of the tokens.
+/// * `amount`: [`u128`], the number of tokens to transfer.
+#[derive(ReadWriteRPC, CreateTypeSpec)]
+pub struct Recipient {
+    to: Address,
+    amount: u128,
+}
+
+/// Record of a completed transfer, as returned by [`bulk_transfer_with_events`], and passed to
+/// [`transfer_and_call_callback`].
+///
+/// ### Fields:
+///
+/// * `from`: [`Address`], the sender of the tokens.
+/// * `to`: [`Address`], the receiver of the tokens.
+/// * `amount`: [`u128`], the number of tokens deducted from the sender.
+/// * `fee`: [`u128`], the part of `amount` credited to the fee collector instead of the
+///   receiver.
+#[derive(ReadWriteRPC, CreateTypeSpec)]
+pub struct TransferEvent {
+    from: Address,
+    to: Address,
+    amount: u128,
+    fee: u128,
+}
+
+/// Metadata of the token, as returned by [`metadata`].
+///
+/// ### Fields:
+///
+/// * `name`: [`String`], the name of the token.
+/// * `symbol`: [`String`], the symbol of the token.
+/// * `decimals`: [`u8`], the number of decimals used to display amounts.
+/// * `total_supply`: [`u128`], the total number of tokens.
+#[derive(ReadWriteRPC, CreateTypeSpec)]
+pub struct TokenMetadata {
+    name: String,
+    symbol: String,
+    decimals: u8,
+    total_supply: u128,
+}
+
+/// An amount of tokens split according to the decimals of the token, as returned by
+/// [`format_amount`].
+///
+/// ### Fields:
+///
+/// * `integer_part`: [`u128`], the whole number of tokens, `amount / 10^decimals`.
+/// * `fractional_part`: [`u128`], the remainder, `amount % 10^decimals`.
+/// * `text`: [`String`], the amount written with `decimals` digits after the decimal point, or
+///   without a decimal point if the token has no decimals.
+#[derive(ReadWriteRPC, CreateTypeSpec)]
+pub struct FormattedAmount {
+    integer_part: u128,
+    fractional_part: u128,
+    text: String,
+}
+
+/// State of the token contract.
+///
+/// ### Fields:
+///
+/// * `name`: [`String`], the name of the token.
+/// * `symbol`: [`String`], the symbol of the token.
+/// * `decimals`: [`u8`], the number of decimals used to display amounts.
+/// * `total_supply`: [`u128`], the total number of tokens.
+/// * `balances`: [`AvlTreeMap<Address, u128>`], the balance of each holder.
+/// * `allowed`: [`AvlTreeMap<AllowedAddress, u128>`], the amounts spenders may transfer on behalf
+///   of owners.
+/// * `allowance_expiries`: [`AvlTreeMap<AllowedAddress, i64>`], the block production times at
+///   which allowances expire, for allowances with an expiry.
+/// * `holder_count`: [`u64`], the number of accounts holding a non-zero balance.
+/// * `owner`: [`Address`], the deployer of the token, allowed to configure it.
+/// * `transfer_fee`: [`Option<TransferFee>`], the fee deducted from transfers, if any.
+/// * `guardian`: [`Option<Address>`], the account allowed to pause the token besides the owner,
+///   if any.
+/// * `paused`: [`bool`], whether transfers and burns are paused.
+/// * `snapshots`: [`Vec<Snapshot>`], the snapshots taken by the owner, indexed by their id.
+/// * `snapshot_balances`: [`AvlTreeMap<SnapshotHolder, u128>`], the non-zero balances of the
+///   holders at each snapshot.
+/// * `claimed_dividends`: [`AvlTreeSet<SnapshotHolder>`], the holders who have claimed their
+///   dividend of each snapshot.
+#[state]
+pub struct TokenState {
+    name: String,
+    symbol: String,
+    decimals: u8,
+    total_supply: u128,
+    balances: AvlTreeMap<Address, u128>,
+    allowed: AvlTreeMap<AllowedAddress, u128>,
+    allowance_expiries: AvlTreeMap<AllowedAddress, i64>,
+    holder_count: u64,
+    owner: Address,
+    transfer_fee: Option<TransferFee>,
+    guardian: Option<Address>,
+    paused: bool,
+    snapshots: Vec<Snapshot>,
+    snapshot_balances: AvlTreeMap<SnapshotHolder, u128>,
+    claimed_dividends: AvlTreeSet<SnapshotHolder>,
+}
+
+impl TokenState {
+    /// Get the balance of the given holder.
+    fn balance_of(&self, holder: &Address) -> u128 {
+        self.balances.get(holder).unwrap_or(0)
+    }
+
+    /// Get the amount the spender may transfer on behalf of the owner.
+    fn allowance(&self, owner: Address, spender: Address) -> u128 {
+        self.allowed.get(&AllowedAddress { owner, spender }).unwrap_or(0)
+    }
+
+    /// Get the amount the spender may transfer on behalf of the owner at the block production
+    /// time `now`, which is zero if the allowance has expired.
+    fn unexpired_allowance(&self, owner: Address, spender: Address, now: i64) -> u128 {
+        match self.allowance_expiries.get(&AllowedAddress { owner, spender }) {
+            Some(expires_at) if now >= expires_at => 0,
+            _ => self.allowance(owner, spender),
+        }
+    }
+
+    /// Set the amount the spender may transfer on behalf of the owner, and the block production
+    /// time at which the allowance expires, if any.
+    fn set_allowance(
+        &mut self,
+        owner: Address,
+        spender: Address,
+        amount: u128,
+        expires_at: Option<i64>,
+    ) {
+        let key = AllowedAddress { owner, spender };
+        self.allowed.insert(key, amount);
+        if let Some(expires_at) = expires_at {
+            self.allowance_expiries.insert(key, expires_at);
+        } else {
+            self.allowance_expiries.remove(&key);
+        }
+    }
+
+    /// Set the balance of the given holder, counting the holders with a non-zero balance.
+    fn set_balance(&mut self, holder: Address, new_balance: u128) {
+        let old_balance = self.balance_of(&holder);
+        if old_balance == 0 && new_balance > 0 {
+            self.holder_count += 1;
+        } else if old_balance > 0 && new_balance == 0 {
+            self.holder_count -= 1;
+        }
+        self.balances.insert(holder, new_balance);
+    }
+
+    /// Add `amount` tokens to the balance of the given holder.
+    fn credit(&mut self, holder: Address, amount: u128) {
+        let balance = self.balance_of(&holder);
+        self.set_balance(holder, balance + amount);
+    }
+
+    /// Get the fee deducted from a transfer of `amount` tokens from `from` to `to`. Transfers from
+    /// or to the owner or the fee collector are exempt.
+    fn fee_of(&self, from: Address, to: Address, amount: u128) -> u128 {
+        let fee = match self.transfer_fee {
+            Some(fee) => fee,
+            None => return 0,
+        };
+        let exempt = [self.owner, fee.collector];
+        if exempt.contains(&from) || exempt.contains(&to) {
+            return 0;
+        }
+        let per_mille = fee.per_mille as u128;
+        amount / PER_MILLE * per_mille + amount % PER_MILLE * per_mille / PER_MILLE
+    }
+
+    /// Remove `amount` tokens from the balance of the given holder. `action` names the action
+    /// removing the tokens.
+    fn debit(&mut self, holder: Address, amount: u128, action: &str) {
+        let balance = self.balance_of(&holder);
+        assert!(
+            balance >= amount,
+            "Insufficient funds for {}: {}/{}",
+            action,
+            balance,
+            amount
+        );
+        self.set_balance(holder, balance - amount);
+    }
+
+    /// Get the snapshot with the given id, failing if it does not exist.
+    fn snapshot(&self, snapshot_id: u32) -> &Snapshot {
+        self.snapshots
+            .get(snapshot_id as usize)
+            .unwrap_or_else(|| panic!("Unknown snapshot {}", snapshot_id))
+    }
+
+    /// Fail if transfers and burns are paused.
+    fn assert_not_paused(&self) {
+        assert!(!self.paused, "The token is paused");
+    }
+
+    /// Move `amount` tokens from `from` to `to`. If a transfer fee applies, the fee is deducted
+    /// from the amount and credited to the fee collector. Returns the deducted fee.
+    fn transfer(&mut self, from: Address, to: Address, amount: u128) -> u128 {
+        self.assert_not_paused();
+        self.debit(from, amount, "transfer");
+        let fee = self.fee_of(from, to, amount);
+        if fee > 0 {
+            self.credit(self.transfer_fee.unwrap().collector, fee);
+        }
+        self.credit(to, amount - fee);
+        fee
+    }
+
+    /// Reduce the allowance of `spender` over the tokens of `owner` by `amount`, failing if the
+    /// allowance is insufficient at the block production time `now`. `action` names the action
+    /// spending the allowance.
+    fn spend_allowance(
+        &mut self,
+        owner: Address,
+        spender: Address,
+        amount: u128,
+        now: i64,
+        action: &str,
+    ) {
+        let allowance = self.unexpired_allowance(owner, spender, now);
+        assert!(
+            allowance >= amount,
+            "Insufficient allowance for {}: {}/{}",
+            action,
+            allowance,
+            amount
+        );
+        self.allowed.insert(AllowedAddress { owner, spender }, allowance - amount);
+    }
+
+    /// Destroy `amount` tokens held by `from`, reducing the total supply.
+    fn burn(&mut self, from: Address, amount: u128) {
+        self.assert_not_paused();
+        self.debit(from, amount, "burn");
+        self.total_supply -= amount;
+    }
+}
+
+/// Initialize a new token, distributing the supply according to the initial allocation, or
+/// minting the entire supply to the deployer if the allocation is empty. The deployer becomes the
+/// owner of the token. No transfer fee is deducted until the owner sets one.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], initial context.
+/// * `name`: [`String`], the name of the token.
+/// * `symbol`: [`String`], the symbol of the token.
+/// * `decimals`: [`u8`], the number of decimals used to display amounts. At most
+///   [`MAX_DECIMALS`].
+/// * `total_supply`: [`u128`], the total number of tokens.
+/// * `initial_allocation`: [`Vec<Allocation>`], the tokens assigned to each holder, which must
+///   sum to `total_supply`. If empty, the deployer is assigned the entire supply.
+///
+/// ### Returns:
+/// The initial state of type [`TokenState`].
+#[init]
+pub fn initialize(
+    ctx: ContractContext,
+    name: String,
+    symbol: String,
+    decimals: u8,
+    total_supply: u128,
+    initial_allocation: Vec<Allocation>,
+) -> TokenState {
+    assert!(
+        decimals <= MAX_DECIMALS,
+        "Decimals must be at most {}, but was {}",
+        MAX_DECIMALS,
+        decimals
+    );
+    let mut state = TokenState {
+        name,
+        symbol,
+        decimals,
+        total_supply,
+        balances: AvlTreeMap::new(),
+        allowed: AvlTreeMap::new(),
+        allowance_expiries: AvlTreeMap::new(),
+        holder_count: 0,
+        owner: ctx.sender,
+        transfer_fee: None,
+        guardian: None,
+        paused: false,
+        snapshots: vec![],
+        snapshot_balances: AvlTreeMap::new(),
+        claimed_dividends: AvlTreeSet::new(),
+    };
+    if initial_allocation.is_empty() {
+        state.set_balance(ctx.sender, total_supply);
+    } else {
+        let allocated = initial_allocation
+            .iter()
+            .try_fold(0u128, |sum, allocation| sum.checked_add(allocation.amount));
+        assert_eq!(
+            allocated,
+            Some(total_supply),
+            "Initial allocation must sum to the total supply"
+        );
+        for allocation in initial_allocation {
+            state.credit(allocation.holder, allocation.amount);
+        }
+    }
+    state
+}
+
+/// Transfer tokens from the sender to another account.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the action call.
+/// * `state`: [`TokenState`], the state before the call.
+/// * `to`: [`Address`], the receiver of the tokens.
+/// * `amount`: [`u128`], the number of tokens to transfer.
+///
+/// ### Returns:
+/// The new state of type [`TokenState`].
+#[action(shortname = 0x01)]
+pub fn transfer(
+    ctx: ContractContext,
+    mut state: TokenState,
+    to: Address,
+    amount: u128,
+) -> TokenState {
+    state.transfer(ctx.sender, to, amount);
+    state
+}
+
+/// Transfer tokens from an owner to another account, on behalf of the owner. The sender must have
+/// been approved to transfer at least `amount` tokens of the owner.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the action call.
+/// * `state`: [`TokenState`], the state before the call.
+/// * `from`: [`Address`], the owner of the tokens.
+/// * `to`: [`Address`], the receiver of the tokens.
+/// * `amount`: [`u128`], the number of tokens to transfer.
+///
+/// ### Returns:
+/// The new state of type [`TokenState`].
+#[action(shortname = 0x03)]
+pub fn transfer_from(
+    ctx: ContractContext,
+    mut state: TokenState,
+    from: Address,
+    to: Address,
+    amount: u128,
+) -> TokenState {
+    state.spend_allowance(
+        from,
+        ctx.sender,
+        amount,
+        ctx.block_production_time,
+        "transfer_from",
+    );
+    state.transfer(from, to, amount);
+    state
+}
+
+/// Allow a spender to transfer up to `amount` tokens on behalf of the sender, replacing any
+/// previous allowance. The allowance does not expire.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the action call.
+/// * `state`: [`TokenState`], the state before the call.
+/// * `spender`: [`Address`], the account allowed to transfer the tokens.
+/// * `amount`: [`u128`], the number of tokens the spender may transfer.
+///
+/// ### Returns:
+/// The new state of type [`TokenState`].
+#[action(shortname = 0x05)]
+pub fn approve(
+    ctx: ContractContext,
+    mut state: TokenState,
+    spender: Address,
+    amount: u128,
+) -> TokenState {
+    state.set_allowance(ctx.sender, spender, amount, None);
+    state
+}
+
+/// Get the metadata of the token in a single call.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the action call.
+/// * `state`: [`TokenState`], the current state.
+///
+/// ### Returns:
+/// The unchanged state, and the [`TokenMetadata`] as return data.
+#[action(shortname = 0x10)]
+pub fn metadata(ctx: ContractContext, state: TokenState) -> (TokenState, Vec<EventGroup>) {
+    let metadata = TokenMetadata {
+        name: state.name.clone(),
+        symbol: state.symbol.clone(),
+        decimals: state.decimals,
+        total_supply: state.total_supply,
+    };
+    (state, vec![EventGroup::with_return_data(metadata)])
+}
+
+/// Get the balances of several accounts in a single call. Accounts without tokens have a balance
+/// of zero.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the action call.
+/// * `state`: [`TokenState`], the current state.
+/// * `accounts`: [`Vec<Address>`], the accounts to get the balances of.
+///
+/// ### Returns:
+/// The unchanged state, and the balance of each account, in the same order as `accounts`, as
+/// return data.
+#[action(shortname = 0x11)]
+pub fn balances_of(
+    ctx: ContractContext,
+    state: TokenState,
+    accounts: Vec<Address>,
+) -> (TokenState, Vec<EventGroup>) {
+    let balances: Vec<u128> = accounts
+        .iter()
+        .map(|account| state.balance_of(account))
+        .collect();
+    (state, vec![EventGroup::with_return_data(balances)])
+}
+
+/// Format an amount of tokens according to the decimals of the token, such that clients do not
+/// need to reimplement the formatting.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the action call.
+/// * `state`: [`TokenState`], the current state.
+/// * `amount`: [`u128`], the amount of tokens to format.
+///
+/// ### Returns:
+/// The unchanged state, and the [`FormattedAmount`] as return data.
+#[action(shortname = 0x12)]
+pub fn format_amount(
+    ctx: ContractContext,
+    state: TokenState,
+    amount: u128,
+) -> (TokenState, Vec<EventGroup>) {
+    let unit = 10u128.pow(state.decimals as u32);
+    let integer_part = amount / unit;
+    let fractional_part = amount % unit;
+    let text = if state.decimals == 0 {
+        integer_part.to_string()
+    } else {
+        format!(
+            "{integer_part}.{fractional_part:0width$}",
+            width = state.decimals as usize
+        )
+    };
+    let formatted = FormattedAmount {
+        integer_part,
+        fractional_part,
+        text,
+    };
+    (state, vec![EventGroup::with_return_data(formatted)])
+}
+
+/// Destroy tokens of the sender, reducing the total supply.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the action call.
+/// * `state`: [`TokenState`], the state before the call.
+/// * `amount`: [`u128`], the number of tokens to destroy.
+///
+/// ### Returns:
+/// The new state of type [`TokenState`].
+#[action(shortname = 0x13)]
+pub fn burn(ctx: ContractContext, mut state: TokenState, amount: u128) -> TokenState {
+    state.burn(ctx.sender, amount);
+    state
+}
+
+/// Destroy tokens of an owner on behalf of the owner, reducing the total supply. The sender must
+/// have been approved to spend at least `amount` tokens of the owner, and the allowance is reduced
+/// as in [`transfer_from`].
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the action call.
+/// * `state`: [`TokenState`], the state before the call.
+/// * `owner`: [`Address`], the owner of the tokens.
+/// * `amount`: [`u128`], the number of tokens to destroy.
+///
+/// ### Returns:
+/// The new state of type [`TokenState`].
+#[action(shortname = 0x14)]
+pub fn burn_from(
+    ctx: ContractContext,
+    mut state: TokenState,
+    owner: Address,
+    amount: u128,
+) -> TokenState {
+    state.spend_allowance(
+        owner,
+        ctx.sender,
+        amount,
+        ctx.block_production_time,
+        "burn_from",
+    );
+    state.burn(owner, amount);
+    state
+}
+
+/// Get the number of accounts holding a non-zero balance.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the action call.
+/// * `state`: [`TokenState`], the current state.
+///
+/// ### Returns:
+/// The unchanged state, and the number of holders as return data.
+#[action(shortname = 0x15)]
+pub fn holder_count(ctx: ContractContext, state: TokenState) -> (TokenState, Vec<EventGroup>) {
+    let holder_count = state.holder_count;
+    (state, vec![EventGroup::with_return_data(holder_count)])
+}
+
+/// Set the fee deducted from transfers, replacing any previous fee. Only the owner can set the
+/// fee. Transfers from or to the owner or the fee collector are exempt from the fee.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the action call.
+/// * `state`: [`TokenState`], the state before the call.
+/// * `transfer_fee`: [`Option<TransferFee>`], the new fee, or `None` to remove the fee. The fee is
+///   at most 1000 per mille.
+///
+/// ### Returns:
+/// The new state of type [`TokenState`].
+#[action(shortname = 0x16)]
+pub fn set_transfer_fee(
+    ctx: ContractContext,
+    mut state: TokenState,
+    transfer_fee: Option<TransferFee>,
+) -> TokenState {
+    assert_eq!(
+        ctx.sender, state.owner,
+        "Only owner can set the transfer fee"
+    );
+    if let Some(fee) = transfer_fee {
+        assert!(
+            fee.per_mille as u128 <= PER_MILLE,
+            "Transfer fee must be at most {} per mille, but was {}",
+            PER_MILLE,
+            fee.per_mille
+        );
+    }
+    state.transfer_fee = transfer_fee;
+    state
+}
+
+/// Allow a spender to transfer up to `amount` tokens on behalf of the sender, without expiry, and
+/// call the spender in the same transaction, such that it can immediately pull the tokens using
+/// [`transfer_from`]. If the call fails, the allowance is reverted to its previous value in
+/// [`approve_and_call_callback`].
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the action call.
+/// * `state`: [`TokenState`], the state before the call.
+/// * `spender`: [`Address`], the contract allowed to transfer the tokens.
+/// * `amount`: [`u128`], the number of tokens the spender may transfer.
+/// * `shortname`: [`u32`], the shortname of the action to call on the spender.
+/// * `extra_args`: [`Vec<u8>`], the serialized arguments of the action to call on the spender.
+///
+/// ### Returns:
+/// The new state of type [`TokenState`], and the event calling the spender.
+#[action(shortname = 0x17)]
+pub fn approve_and_call(
+    ctx: ContractContext,
+    mut state: TokenState,
+    spender: Address,
+    amount: u128,
+    shortname: u32,
+    extra_args: Vec<u8>,
+) -> (TokenState, Vec<EventGroup>) {
+    let previous_allowance = state.allowance(ctx.sender, spender);
+    let previous_expires_at = state.allowance_expiries.get(&AllowedAddress {
+        owner: ctx.sender,
+        spender,
+    });
+    state.set_allowance(ctx.sender, spender, amount, None);
+
+    let mut rpc = Shortname::from_u32(shortname).bytes();
+    rpc.extend(extra_args);
+
+    let mut event_group = EventGroup::builder();
+    event_group.call_with_rpc(spender, rpc).done();
+    event_group
+        .with_callback_rpc(approve_and_call_callback::rpc(
+            ctx.sender,
+            spender,
+            previous_allowance,
+            previous_expires_at,
+        ))
+        .with_cost(1000)
+        .done();
+
+    (state, vec![event_group.build()])
+}
+
+/// Callback for the call to the spender in [`approve_and_call`]. If the call failed, the allowance
+/// is reverted to its value before [`approve_and_call`].
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the callback.
+/// * `callback_ctx`: [`CallbackContext`], the context of the callback.
+/// * `state`: [`TokenState`], the state before the callback.
+/// * `owner`: [`Address`], the owner who approved the spender.
+/// * `spender`: [`Address`], the spender that was called.
+/// * `previous_allowance`: [`u128`], the allowance of the spender before the approval.
+/// * `previous_expires_at`: [`Option<i64>`], the expiry of the allowance before the approval.
+///
+/// ### Returns:
+/// The new state of type [`TokenState`].
+#[callback(shortname = 0x01)]
+pub fn approve_and_call_callback(
+    ctx: ContractContext,
+    callback_ctx: CallbackContext,
+    mut state: TokenState,
+    owner: Address,
+    spender: Address,
+    previous_allowance: u128,
+    previous_expires_at: Option<i64>,
+) -> (TokenState, Vec<EventGroup>) {
+    if !callback_ctx.success {
+        state.set_allowance(owner, spender, previous_allowance, previous_expires_at);
+    }
+    (state, vec![])
+}
+
+/// Set the guardian, which can pause the token without being able to unpause it, such that an
+/// operational key can react to emergencies without the privileges of the owner. Only the owner
+/// can set the guardian.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the action call.
+/// * `state`: [`TokenState`], the state before the call.
+/// * `guardian`: [`Option<Address>`], the new guardian, or `None` to remove the guardian.
+///
+/// ### Returns:
+/// The new state of type [`TokenState`].
+#[action(shortname = 0x18)]
+pub fn set_guardian(
+    ctx: ContractContext,
+    mut state: TokenState,
+    guardian: Option<Address>,
+) -> TokenState {
+    assert_eq!(ctx.sender, state.owner, "Only owner can set the guardian");
+    state.guardian = guardian;
+    state
+}
+
+/// Pause transfers and burns of the token. Only the owner or the guardian can pause the token.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the action call.
+/// * `state`: [`TokenState`], the state before the call.
+///
+/// ### Returns:
+/// The new state of type [`TokenState`].
+#[action(shortname = 0x19)]
+pub fn pause(ctx: ContractContext, mut state: TokenState) -> TokenState {
+    assert!(
+        ctx.sender == state.owner || state.guardian == Some(ctx.sender),
+        "Only owner or guardian can pause the token"
+    );
+    state.paused = true;
+    state
+}
+
+/// Resume transfers and burns of the token. Only the owner can unpause the token.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the action call.
+/// * `state`: [`TokenState`], the state before the call.
+///
+/// ### Returns:
+/// The new state of type [`TokenState`].
+#[action(shortname = 0x1A)]
+pub fn unpause(ctx: ContractContext, mut state: TokenState) -> TokenState {
+    assert_eq!(ctx.sender, state.owner, "Only owner can unpause the token");
+    state.paused = false;
+    state
+}
+
+/// Transfer tokens from the sender to several recipients, returning a [`TransferEvent`] for each
+/// transfer for off-chain reconciliation. The batch is atomic: if the total amount exceeds the
+/// balance of the sender, no tokens are transferred.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the action call.
+/// * `state`: [`TokenState`], the state before the call.
+/// * `recipients`: [`Vec<Recipient>`], the receivers and the number of tokens to transfer to each.
+///
+/// ### Returns:
+/// The new state of type [`TokenState`], and a [`TransferEvent`] for each transfer, in the same
+/// order as `recipients`, as return data.
+#[action(shortname = 0x1B)]
+pub fn bulk_transfer_with_events(
+    ctx: ContractContext,
+    mut state: TokenState,
+    recipients: Vec<Recipient>,
+) -> (TokenState, Vec<EventGroup>) {
+    let balance = state.balance_of(&ctx.sender);
+    let total = recipients
+        .iter()
+        .fold(0u128, |sum, recipient| sum.saturating_add(recipient.amount));
+    assert!(
+        balance >= total,
+        "Insufficient funds for bulk transfer: {}/{}",
+        balance,
+        total
+    );
+
+    let events: Vec<TransferEvent> = recipients
+        .into_iter()
+        .map(|recipient| {
+            let fee = state.transfer(ctx.sender, recipient.to, recipient.amount);
+            TransferEvent {
+                from: ctx.sender,
+                to: recipient.to,
+                amount: recipient.amount,
+                fee,
+            }
+        })
+        .collect();
+    (state, vec![EventGroup::with_return_data(events)])
+}
+
+/// Transfer tokens from the sender to another account, and notify the receiver if it is a
+/// contract, by calling its action with shortname `0x30`:
+///
+/// `on_token_received(from: Address, amount: u128, data: Vec<u8>)`
+///
+/// where `amount` is the number of tokens received after any transfer fee. If the receiver
+/// rejects the tokens by failing, the transfer is reverted in [`transfer_and_call_callback`].
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the action call.
+/// * `state`: [`TokenState`], the state before the call.
+/// * `to`: [`Address`], the receiver of the tokens.
+/// * `amount`: [`u128`], the number of tokens to transfer.
+/// * `data`: [`Vec<u8>`], additional data passed on to the receiver.
+///
+/// ### Returns:
+/// The new state of type [`TokenState`], and the event notifying the receiver, if it is a
+/// contract.
+#[action(shortname = 0x1C)]
+pub fn transfer_and_call(
+    ctx: ContractContext,
+    mut state: TokenState,
+    to: Address,
+    amount: u128,
+    data: Vec<u8>,
+) -> (TokenState, Vec<EventGroup>) {
+    let fee = state.transfer(ctx.sender, to, amount);
+    if to.address_type == AddressType::Account {
+        return (state, vec![]);
+    }
+
+    let transfer = TransferEvent {
+        from: ctx.sender,
+        to,
+        amount,
+        fee,
+    };
+    let fee_collector = state.transfer_fee.map(|transfer_fee| transfer_fee.collector);
+    let mut event_group = EventGroup::builder();
+    event_group
+        .call(to, Shortname::from_u32(ON_TOKEN_RECEIVED_SHORTNAME))
+        .argument(ctx.sender)
+        .argument(amount - fee)
+        .argument(data)
+        .done();
+    event_group
+        .with_callback_rpc(transfer_and_call_callback::rpc(transfer, fee_collector))
+        .with_cost(1000)
+        .done();
+
+    (state, vec![event_group.build()])
+}
+
+/// Callback for the notification of the receiver in [`transfer_and_call`]. If the receiver
+/// rejected the tokens, the transfer is reverted, including any transfer fee.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the callback.
+/// * `callback_ctx`: [`CallbackContext`], the context of the callback.
+/// * `state`: [`TokenState`], the state before the callback.
+/// * `transfer`: [`TransferEvent`], the transfer to revert if the receiver rejected the tokens.
+/// * `fee_collector`: [`Option<Address>`], the fee collector at the time of the transfer.
+///
+/// ### Returns:
+/// The new state of type [`TokenState`].
+#[callback(shortname = 0x02)]
+pub fn transfer_and_call_callback(
+    ctx: ContractContext,
+    callback_ctx: CallbackContext,
+    mut state: TokenState,
+    transfer: TransferEvent,
+    fee_collector: Option<Address>,
+) -> (TokenState, Vec<EventGroup>) {
+    if !callback_ctx.success {
+        state.debit(transfer.to, transfer.amount - transfer.fee, "reverting transfer");
+        if let Some(fee_collector) = fee_collector {
+            state.debit(fee_collector, transfer.fee, "reverting transfer");
+        }
+        state.credit(transfer.from, transfer.amount);
+    }
+    (state, vec![])
+}
+
+/// Mint `total_amount` new tokens and distribute them among the current holders, proportional to
+/// their balances. The share of each holder is rounded down, and the remaining tokens are given
+/// to the largest holder, such that exactly `total_amount` tokens are distributed. Only the owner
+/// can airdrop tokens.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the action call.
+/// * `state`: [`TokenState`], the state before the call.
+/// * `total_amount`: [`u128`], the number of tokens to distribute.
+///
+/// ### Returns:
+/// The new state of type [`TokenState`].
+#[action(shortname = 0x1D)]
+pub fn airdrop_proportional(
+    ctx: ContractContext,
+    mut state: TokenState,
+    total_amount: u128,
+) -> TokenState {
+    assert_eq!(ctx.sender, state.owner, "Only owner can airdrop tokens");
+    state.assert_not_paused();
+    assert!(state.total_supply > 0, "Cannot airdrop without holders");
+    let total_supply = state.total_supply;
+    state.total_supply = total_supply
+        .checked_add(total_amount)
+        .expect("Airdrop would overflow the total supply");
+
+    let holders: Vec<(Address, u128)> = state
+        .balances
+        .iter()
+        .filter(|(_, balance)| *balance > 0)
+        .collect();
+    let mut largest_holder = holders[0];
+    let mut distributed = 0;
+    for (holder, balance) in holders {
+        let share = mul_div(total_amount, balance, total_supply);
+        state.credit(holder, share);
+        distributed += share;
+        if balance > largest_holder.1 {
+            largest_holder = (holder, balance);
+        }
+    }
+    state.credit(largest_holder.0, total_amount - distributed);
+    state
+}
+
+/// Take a snapshot of the current balances, against which dividends can be distributed. Only the
+/// owner can take snapshots.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the action call.
+/// * `state`: [`TokenState`], the state before the call.
+///
+/// ### Returns:
+/// The new state of type [`TokenState`], and the id of the snapshot as return data.
+#[action(shortname = 0x1E)]
+pub fn snapshot(ctx: ContractContext, mut state: TokenState) -> (TokenState, Vec<EventGroup>) {
+    assert_eq!(ctx.sender, state.owner, "Only owner can take snapshots");
+    let snapshot_id = state.snapshots.len() as u32;
+    let balances: Vec<(Address, u128)> = state
+        .balances
+        .iter()
+        .filter(|(_, balance)| *balance > 0)
+        .collect();
+    for (holder, balance) in balances {
+        state.snapshot_balances.insert(
+            SnapshotHolder {
+                snapshot_id,
+                holder,
+            },
+            balance,
+        );
+    }
+    state.snapshots.push(Snapshot {
+        total_supply: state.total_supply,
+        dividend_pool: 0,
+    });
+    (state, vec![EventGroup::with_return_data(snapshot_id)])
+}
+
+/// Deposit tokens of the owner as dividend for a snapshot, to be claimed by the holders at the
+/// snapshot using [`claim_dividend`]. The tokens are held by the token contract until claimed.
+/// Only the owner can deposit dividends, and only once for each snapshot.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the action call.
+/// * `state`: [`TokenState`], the state before the call.
+/// * `snapshot_id`: [`u32`], the id of the snapshot.
+/// * `amount`: [`u128`], the number of tokens to distribute.
+///
+/// ### Returns:
+/// The new state of type [`TokenState`].
+#[action(shortname = 0x1F)]
+pub fn deposit_dividend(
+    ctx: ContractContext,
+    mut state: TokenState,
+    snapshot_id: u32,
+    amount: u128,
+) -> TokenState {
+    assert_eq!(ctx.sender, state.owner, "Only owner can deposit dividends");
+    assert!(amount > 0, "Dividend must be positive");
+    assert_eq!(
+        state.snapshot(snapshot_id).dividend_pool,
+        0,
+        "Dividend already deposited for snapshot {}",
+        snapshot_id
+    );
+    state.debit(ctx.sender, amount, "dividend");
+    state.credit(ctx.contract_address, amount);
+    state.snapshots[snapshot_id as usize].dividend_pool = amount;
+    state
+}
+
+/// Claim the dividend of the sender for a snapshot, which is a share of the dividend proportional
+/// to the balance of the sender at the snapshot, rounded down. Each holder can claim once for each
+/// snapshot.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the action call.
+/// * `state`: [`TokenState`], the state before the call.
+/// * `snapshot_id`: [`u32`], the id of the snapshot.
+///
+/// ### Returns:
+/// The new state of type [`TokenState`].
+#[action(shortname = 0x20)]
+pub fn claim_dividend(ctx: ContractContext, mut state: TokenState, snapshot_id: u32) -> TokenState {
+    let snapshot = state.snapshot(snapshot_id);
+    assert!(
+        snapshot.dividend_pool > 0,
+        "No dividend deposited for snapshot {}",
+        snapshot_id
+    );
+    let key = SnapshotHolder {
+        snapshot_id,
+        holder: ctx.sender,
+    };
+    assert!(
+        !state.claimed_dividends.contains(&key),
+        "Dividend already claimed for snapshot {}",
+        snapshot_id
+    );
+    let balance = state
+        .snapshot_balances
+        .get(&key)
+        .unwrap_or_else(|| panic!("No balance at snapshot {}", snapshot_id));
+
+    let dividend = mul_div(snapshot.dividend_pool, balance, snapshot.total_supply);
+    state.claimed_dividends.insert(key);
+    state.debit(ctx.contract_address, dividend, "dividend");
+    state.credit(ctx.sender, dividend);
+    state
+}
+
+/// Allow a spender to transfer up to `amount` tokens on behalf of the sender until the given block
+/// production time, replacing any previous allowance. From the expiry on, [`transfer_from`] and
+/// [`burn_from`] treat the allowance as zero.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context of the action call.
+/// * `state`: [`TokenState`], the state before the call.
+/// * `spender`: [`Address`], the account allowed to transfer the tokens.
+/// * `amount`: [`u128`], the number of tokens the spender may transfer.
+/// * `expires_at`: [`i64`], the block production time in UTC millis at which the allowance
+///   expires.
+///
+/// ### Returns:
+/// The new state of type [`TokenState`].
+#[action(shortname = 0x21)]
+pub fn approve_with_expiry(
+    ctx: ContractContext,
+    mut state: TokenState,
+    spender: Address,
+    amount: u128,
+    expires_at: i64,
+) -> TokenState {
+    state.set_allowance(ctx.sender, spender, amount, Some(expires_at));
+    state
+}
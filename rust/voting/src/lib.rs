@@ -5,9 +5,12 @@
 extern crate pbc_contract_codegen;
 extern crate pbc_contract_common;
 
+use create_type_spec_derive::CreateTypeSpec;
 use pbc_contract_common::address::Address;
 use pbc_contract_common::context::ContractContext;
+use pbc_contract_common::events::EventGroup;
 use pbc_contract_common::sorted_vec_map::{SortedVecMap, SortedVecSet};
+use read_write_rpc_derive::ReadWriteRPC;
 
 /// The state of the vote, which is persisted on-chain.
 #[state]
@@ -29,6 +32,17 @@ pub struct VoteState {
     pub result: Option<bool>,
 }
 
+/// Summary of the votes cast, returned by `tally`.
+#[derive(ReadWriteRPC, CreateTypeSpec)]
+pub struct VoteTally {
+    /// The number of eligible voters.
+    pub voters: u32,
+    /// The number of votes cast for the proposal.
+    pub votes_for: u32,
+    /// The result of the vote, None until the votes has been counted.
+    pub result: Option<bool>,
+}
+
 /// Initialize a new vote for a proposal
 ///
 /// # Arguments
@@ -114,3 +128,25 @@ pub fn count(ctx: ContractContext, mut state: VoteState) -> VoteState {
     state.result = Some(vote_passed);
     state
 }
+
+/// Get a summary of the votes cast. Allows other contracts, such as the contract which deployed
+/// the vote, to act upon the outcome.
+///
+/// # Arguments
+///
+/// * `ctx` - the contract context containing information about the sender and blockchain.
+/// * `state` - the current state of the vote.
+///
+/// # Returns
+///
+/// The unchanged state, and the [`VoteTally`] as return data.
+///
+#[action(shortname = 0x03)]
+pub fn tally(ctx: ContractContext, state: VoteState) -> (VoteState, Vec<EventGroup>) {
+    let tally = VoteTally {
+        voters: state.voters.len() as u32,
+        votes_for: state.votes.values().filter(|vote| **vote).count() as u32,
+        result: state.result,
+    };
+    (state, vec![EventGroup::with_return_data(tally)])
+}
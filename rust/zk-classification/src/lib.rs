@@ -8,12 +8,21 @@ extern crate pbc_lib;
 mod zk_compute;
 
 use pbc_contract_common::address::Address;
+use pbc_contract_common::avl_tree_map::AvlTreeMap;
 use pbc_contract_common::context::ContractContext;
 use pbc_contract_common::events::EventGroup;
 use pbc_contract_common::zk::{SecretVarId, ZkInputDef, ZkState, ZkStateChange};
+use pbc_zk::SecretBinaryFixedSize;
 use read_write_rpc_derive::ReadWriteRPC;
 use read_write_state_derive::ReadWriteState;
 
+/// Number of bits of a secret [`zk_compute::Model`]: 7 internal vertices with an 8-bit feature and
+/// a 16-bit threshold each, followed by 8 leaf vertices with a 1-bit classification each.
+const MODEL_BITS: u32 = <zk_compute::Model as SecretBinaryFixedSize>::BITS;
+
+/// Number of bits of a secret [`zk_compute::Sample`]: 10 values of 16 bits each.
+const SAMPLE_BITS: u32 = <zk_compute::Sample as SecretBinaryFixedSize>::BITS;
+
 /// Secret metadata type information.
 #[derive(ReadWriteState, ReadWriteRPC, Debug)]
 pub enum SecretVarType {
@@ -50,6 +59,8 @@ pub enum SecretVarType {
 pub struct ContractState {
     /// The address of the model owner
     model_owner: Address,
+    /// The inference results that have been opened by their owners, by the id of the result.
+    opened_results: AvlTreeMap<u32, bool>,
 }
 
 /// Contract initialization.
@@ -60,6 +71,7 @@ pub struct ContractState {
 pub fn initialize(context: ContractContext, _zk_state: ZkState<SecretVarType>) -> ContractState {
     ContractState {
         model_owner: context.sender,
+        opened_results: AvlTreeMap::new(),
     }
 }
 
@@ -74,6 +86,9 @@ pub fn initialize(context: ContractContext, _zk_state: ZkState<SecretVarType>) -
 /// contains all leaf vertices with binary classifications. All values stored in the vertices are
 /// secret-shared. The thresholds in each (feature, threshold) pair are multiplied by the corresponding
 /// entry in the scaling conversion vector provided by the model owner before they are secret-shared.
+///
+/// The secret model must be exactly [`MODEL_BITS`] bits, such that it can be loaded as a
+/// [`zk_compute::Model`].
 #[zk_on_secret_input(shortname = 0x40)]
 pub fn add_model(
     context: ContractContext,
@@ -90,11 +105,12 @@ pub fn add_model(
         "Only contract creator can add a model"
     );
 
-    let input_def = ZkInputDef::with_metadata(
+    let input_def = ZkInputDef::with_metadata_and_size(
         Some(inputted_model::SHORTNAME),
         SecretVarType::InputModel {
             scaling: scaling_conversion,
         },
+        MODEL_BITS,
     );
 
     (state, vec![], input_def)
@@ -121,6 +137,9 @@ fn inputted_model(
 /// The sample is added as an array of decimal numbers representing each feature value. The
 /// scaling conversion provided by the model owner is multiplied onto the sample elementwise
 /// before the values are secret-shared.
+///
+/// The secret sample must be exactly [`SAMPLE_BITS`] bits, such that it can be loaded as a
+/// [`zk_compute::Sample`].
 #[zk_on_secret_input(shortname = 0x41)]
 pub fn add_input_sample(
     _context: ContractContext,
@@ -133,12 +152,13 @@ pub fn add_input_sample(
     Vec<EventGroup>,
     ZkInputDef<SecretVarType, zk_compute::Sample>,
 ) {
-    let input_def = ZkInputDef::with_metadata(
+    let input_def = ZkInputDef::with_metadata_and_size(
         Some(inputted_sample::SHORTNAME),
         SecretVarType::InputSample {
             model_id,
             result_receiver,
         },
+        SAMPLE_BITS,
     );
 
     (state, vec![], input_def)
@@ -214,3 +234,64 @@ fn compute_complete(
         panic!("Could not find inference result with the given identifier")
     }
 }
+
+/// Opens the inference result with id `result_id`, making the predicted class public.
+///
+/// Inference results are kept secret by default, and only the owner of a result can choose to
+/// open it. Fails if the sender is not the owner of the result, or if the variable is not an
+/// inference result.
+#[action(shortname = 0x01, zk = true)]
+pub fn open_result(
+    context: ContractContext,
+    state: ContractState,
+    zk_state: ZkState<SecretVarType>,
+    result_id: u32,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    let result_id = SecretVarId::new(result_id);
+    let result = zk_state
+        .get_variable(result_id)
+        .expect("No result with the given identifier");
+    assert!(
+        matches!(result.metadata, SecretVarType::InferenceResult { .. }),
+        "Only inference results can be opened"
+    );
+    assert_eq!(
+        result.owner, context.sender,
+        "Only the owner of the result can open it"
+    );
+
+    (
+        state,
+        vec![],
+        vec![ZkStateChange::OpenVariables {
+            variables: vec![result_id],
+        }],
+    )
+}
+
+/// Saves an opened inference result in the state, and deletes the secret variable.
+///
+/// Automatically called when the owner of a result has opened it.
+#[zk_on_variables_opened]
+fn save_opened_result(
+    _context: ContractContext,
+    mut state: ContractState,
+    zk_state: ZkState<SecretVarType>,
+    opened_variables: Vec<SecretVarId>,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    for result_id in &opened_variables {
+        let result = zk_state.get_variable(*result_id).unwrap();
+        let predicted_class = result.data.as_ref().unwrap()[0] & 1 == 1;
+        state
+            .opened_results
+            .insert(result_id.raw_id, predicted_class);
+    }
+
+    (
+        state,
+        vec![],
+        vec![ZkStateChange::DeleteVariables {
+            variables_to_delete: opened_variables,
+        }],
+    )
+}
@@ -6,9 +6,11 @@ extern crate pbc_contract_codegen;
 extern crate pbc_contract_common;
 extern crate pbc_lib;
 
+use create_type_spec_derive::CreateTypeSpec;
 use pbc_contract_common::address::Address;
 use pbc_contract_common::context::ContractContext;
 use pbc_contract_common::events::EventGroup;
+use pbc_contract_common::sorted_vec_map::SortedVecMap;
 use pbc_contract_common::zk::{SecretVarId, ZkInputDef, ZkState, ZkStateChange};
 use pbc_zk::Sbu8;
 use read_write_rpc_derive::ReadWriteRPC;
@@ -16,44 +18,259 @@ use read_write_state_derive::ReadWriteState;
 
 mod zk_compute;
 
+use zk_compute::MAX_DUPLICATABLE_FILE_LENGTH;
+
 /// Metadata for secret-shared files.
-#[derive(ReadWriteState, ReadWriteRPC, Debug)]
-#[repr(C)]
-pub struct SecretVarMetadata {}
+///
+/// The public metadata of a file is kept in the state, so the variable only refers to the upload
+/// that created it, and whether the file can be shared with multiple owners.
+#[derive(ReadWriteState, ReadWriteRPC, Debug, Clone, Copy)]
+pub struct SecretVarMetadata {
+    /// The id of the upload that created the file.
+    upload_id: u32,
+    /// Whether the file is padded to [`MAX_DUPLICATABLE_FILE_LENGTH`] bytes, such that it can be
+    /// shared with multiple owners.
+    shareable: bool,
+}
+
+/// Public metadata describing a file. The content of the file itself stays secret.
+#[derive(ReadWriteState, ReadWriteRPC, CreateTypeSpec, Debug, Clone)]
+pub struct FileMetadata {
+    /// The name of the file.
+    filename: String,
+    /// The MIME type of the file content.
+    mime_type: String,
+    /// The size of the file in bytes.
+    size: u32,
+}
+
+/// A file together with its public metadata.
+#[derive(ReadWriteRPC, CreateTypeSpec, Debug)]
+pub struct FileEntry {
+    /// The id of the file.
+    file_id: u32,
+    /// The public metadata of the file.
+    metadata: FileMetadata,
+}
+
+/// A file which is being duplicated to multiple new owners.
+#[derive(ReadWriteState, CreateTypeSpec, Debug)]
+pub struct PendingDuplication {
+    /// The id of the file being duplicated.
+    file_id: SecretVarId,
+    /// The owners who have not yet received their copy of the file.
+    remaining_owners: Vec<Address>,
+    /// Whether the original file is kept by its owner once all copies have been made.
+    keep_original: bool,
+}
 
-/// Empty contract state, as all stored files are secret-shared.
+/// Contract state. All stored files are secret-shared, so the state only tracks the public
+/// metadata of the files, and the duplications of files.
 #[state]
-pub struct CollectionState {}
+pub struct CollectionState {
+    /// The files being shared with multiple owners, in the order they were shared. Only one copy
+    /// can be computed at a time, so the first duplication is in progress, while the rest wait for
+    /// it to complete.
+    duplication_queue: Vec<PendingDuplication>,
+    /// The public metadata of every stored file, keyed by the id of the file.
+    file_metadata: SortedVecMap<u32, FileMetadata>,
+    /// The public metadata of files which are being uploaded, keyed by the id of the upload.
+    pending_uploads: SortedVecMap<u32, FileMetadata>,
+    /// The id of the next upload.
+    next_upload_id: u32,
+}
+
+impl CollectionState {
+    /// Asserts that the file with id `file_id` is not currently being duplicated, as the
+    /// duplication depends on the file staying in place until all copies have been made.
+    fn assert_not_being_duplicated(&self, file_id: SecretVarId) {
+        let is_being_duplicated = self
+            .duplication_queue
+            .iter()
+            .any(|duplication| duplication.file_id == file_id);
+        assert!(
+            !is_being_duplicated,
+            "The file is currently being shared with multiple owners."
+        );
+    }
+
+    /// Gets the public metadata of the file with id `file_id`.
+    fn metadata_of(&self, file_id: SecretVarId) -> &FileMetadata {
+        let Some(metadata) = self.file_metadata.get(&file_id.raw_id) else {
+            panic!("No metadata is stored for the file with id {}.", file_id.raw_id)
+        };
+        metadata
+    }
+
+    /// Removes the public metadata of uploads which are no longer pending, as their input was
+    /// rejected before the file was uploaded.
+    fn remove_stale_uploads(&mut self, zk_state: &ZkState<SecretVarMetadata>) {
+        let pending_upload_ids: Vec<u32> = zk_state
+            .pending_inputs
+            .iter()
+            .map(|(_, input)| input.metadata.upload_id)
+            .collect();
+        let stale_upload_ids: Vec<u32> = self
+            .pending_uploads
+            .iter()
+            .map(|(upload_id, _)| *upload_id)
+            .filter(|upload_id| !pending_upload_ids.contains(upload_id))
+            .collect();
+        for upload_id in stale_upload_ids {
+            self.pending_uploads.remove(&upload_id);
+        }
+    }
+}
+
+/// Gets the owner and metadata of the uploaded file with id `file_id`.
+///
+/// Fails if there is no such file, which is also the case while the file is still being uploaded.
+fn uploaded_file(
+    zk_state: &ZkState<SecretVarMetadata>,
+    file_id: SecretVarId,
+) -> (Address, SecretVarMetadata) {
+    let Some(file) = zk_state.get_variable(file_id) else {
+        panic!(
+            "No uploaded file with id {}. The file may still be uploading.",
+            file_id.raw_id
+        )
+    };
+    (file.owner, file.metadata)
+}
 
 /// Initializes contract with empty state.
 #[init(zk = true)]
 pub fn initialize(ctx: ContractContext, zk_state: ZkState<SecretVarMetadata>) -> CollectionState {
-    CollectionState {}
+    CollectionState {
+        duplication_queue: vec![],
+        file_metadata: SortedVecMap::new(),
+        pending_uploads: SortedVecMap::new(),
+        next_upload_id: 1,
+    }
 }
 
 /// Upload a new file with a specific size of `file_length`.
 ///
-/// `file_length` is the size of the file in *bytes*.
-/// Fails if the uploaded file has a different size than `file_length`.
+/// `file_length` is the size of the file in *bytes*. The `filename` and `mime_type` are public,
+/// and are stored in the state together with the size, and are associated with the file once it
+/// has been uploaded.
+///
+/// Fails if the uploaded file has a different size than `file_length`. Files uploaded this way
+/// cannot be shared with multiple owners, see [`add_shareable_file`].
 #[zk_on_secret_input(shortname = 0x42)]
 pub fn add_file(
     context: ContractContext,
     state: CollectionState,
     zk_state: ZkState<SecretVarMetadata>,
     file_length: u32,
+    filename: String,
+    mime_type: String,
+) -> (
+    CollectionState,
+    Vec<EventGroup>,
+    ZkInputDef<SecretVarMetadata, Vec<Sbu8>>,
+) {
+    let metadata = FileMetadata {
+        filename,
+        mime_type,
+        size: file_length,
+    };
+    upload_file(state, &zk_state, metadata, false, file_length * 8)
+}
+
+/// Upload a new file of `file_length` bytes, which can be shared with multiple owners using
+/// [`share_file_with_owners`].
+///
+/// As the output of a ZK computation must have a fixed size, the file must be padded with zeros to
+/// exactly [`MAX_DUPLICATABLE_FILE_LENGTH`] bytes. The padding is not included in `file_length`.
+/// The `filename` and `mime_type` are public, as for [`add_file`].
+///
+/// Fails if `file_length` is more than [`MAX_DUPLICATABLE_FILE_LENGTH`], or if the uploaded file is
+/// not padded to exactly [`MAX_DUPLICATABLE_FILE_LENGTH`] bytes.
+#[zk_on_secret_input(shortname = 0x44)]
+pub fn add_shareable_file(
+    context: ContractContext,
+    state: CollectionState,
+    zk_state: ZkState<SecretVarMetadata>,
+    file_length: u32,
+    filename: String,
+    mime_type: String,
 ) -> (
     CollectionState,
     Vec<EventGroup>,
     ZkInputDef<SecretVarMetadata, Vec<Sbu8>>,
 ) {
-    let input_def = ZkInputDef::with_metadata_and_size(None, SecretVarMetadata {}, file_length * 8);
+    assert!(
+        file_length as usize <= MAX_DUPLICATABLE_FILE_LENGTH,
+        "Only files of at most {MAX_DUPLICATABLE_FILE_LENGTH} bytes can be shared with multiple owners."
+    );
+    let metadata = FileMetadata {
+        filename,
+        mime_type,
+        size: file_length,
+    };
+    upload_file(
+        state,
+        &zk_state,
+        metadata,
+        true,
+        MAX_DUPLICATABLE_FILE_LENGTH as u32 * 8,
+    )
+}
+
+/// Registers an upload of a file with the given public `metadata`, as a secret input of
+/// `bit_length` bits. The metadata of earlier uploads which were rejected is removed.
+fn upload_file(
+    mut state: CollectionState,
+    zk_state: &ZkState<SecretVarMetadata>,
+    metadata: FileMetadata,
+    shareable: bool,
+    bit_length: u32,
+) -> (
+    CollectionState,
+    Vec<EventGroup>,
+    ZkInputDef<SecretVarMetadata, Vec<Sbu8>>,
+) {
+    state.remove_stale_uploads(zk_state);
+
+    let upload_id = state.next_upload_id;
+    state.next_upload_id += 1;
+    state.pending_uploads.insert(upload_id, metadata);
+
+    let input_def = ZkInputDef::with_metadata_and_size(
+        Some(inputted_file::SHORTNAME),
+        SecretVarMetadata {
+            upload_id,
+            shareable,
+        },
+        bit_length,
+    );
     (state, vec![], input_def)
 }
 
+/// Automatically called when a file has been uploaded.
+///
+/// Associates the public metadata of the upload with the file.
+#[zk_on_variable_inputted(shortname = 0x43)]
+fn inputted_file(
+    context: ContractContext,
+    mut state: CollectionState,
+    zk_state: ZkState<SecretVarMetadata>,
+    file_id: SecretVarId,
+) -> CollectionState {
+    let (_, file) = uploaded_file(&zk_state, file_id);
+    let Some(metadata) = state.pending_uploads.remove(&file.upload_id) else {
+        panic!("No metadata is stored for the upload with id {}.", file.upload_id)
+    };
+    state.file_metadata.insert(file_id.raw_id, metadata);
+    state
+}
+
 /// Changes ownership of the secret-shared file with id `file_id`
 /// from the sender to `new_owner`.
 ///
-/// Fails if the sender is not the current owner of the referenced file.
+/// Fails if the sender is not the current owner of the referenced file, or if the file is
+/// currently being duplicated.
 #[action(shortname = 0x03, zk = true)]
 pub fn change_file_owner(
     ctx: ContractContext,
@@ -63,11 +280,12 @@ pub fn change_file_owner(
     new_owner: Address,
 ) -> (CollectionState, Vec<EventGroup>, Vec<ZkStateChange>) {
     let file_id = SecretVarId::new(file_id);
-    let file_owner = zk_state.get_variable(file_id).unwrap().owner;
+    let (file_owner, _) = uploaded_file(&zk_state, file_id);
     assert_eq!(
         file_owner, ctx.sender,
         "Only the owner of the secret file is allowed to change ownership."
     );
+    state.assert_not_being_duplicated(file_id);
 
     (
         state,
@@ -81,20 +299,23 @@ pub fn change_file_owner(
 
 /// Deletes the secret-shared file with id `file_id`.
 ///
-/// Fails if the sender is not the current owner of the secret file.
+/// Fails if the sender is not the current owner of the secret file, or if the file is currently
+/// being duplicated.
 #[action(shortname = 0x05, zk = true)]
 pub fn delete_file(
     ctx: ContractContext,
-    state: CollectionState,
+    mut state: CollectionState,
     zk_state: ZkState<SecretVarMetadata>,
     file_id: u32,
 ) -> (CollectionState, Vec<EventGroup>, Vec<ZkStateChange>) {
     let file_id = SecretVarId::new(file_id);
-    let file_owner = zk_state.get_variable(file_id).unwrap().owner;
+    let (file_owner, _) = uploaded_file(&zk_state, file_id);
     assert_eq!(
         file_owner, ctx.sender,
         "Only the owner of the secret file is allowed to delete it."
     );
+    state.assert_not_being_duplicated(file_id);
+    state.file_metadata.remove(&file_id.raw_id);
 
     (
         state,
@@ -104,3 +325,143 @@ pub fn delete_file(
         }],
     )
 }
+
+/// Shares the secret-shared file with id `file_id` with each of `new_owners`.
+///
+/// The file is re-secret-shared once for every new owner, such that each owner receives their own
+/// copy, which they can transfer or delete independently. If `keep_original` is false, the original
+/// file is deleted once all copies have been made.
+///
+/// Only one copy can be computed at a time, so if other files are being shared, the sharing waits
+/// for them to complete. Until all copies have been made, the file cannot be deleted or
+/// transferred.
+///
+/// Fails if the sender is not the current owner of the referenced file, if the file was not
+/// uploaded using [`add_shareable_file`], or if the file is already being shared.
+#[action(shortname = 0x06, zk = true)]
+pub fn share_file_with_owners(
+    ctx: ContractContext,
+    mut state: CollectionState,
+    zk_state: ZkState<SecretVarMetadata>,
+    file_id: u32,
+    new_owners: Vec<Address>,
+    keep_original: bool,
+) -> (CollectionState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    let file_id = SecretVarId::new(file_id);
+    let (file_owner, file) = uploaded_file(&zk_state, file_id);
+    assert_eq!(
+        file_owner, ctx.sender,
+        "Only the owner of the secret file is allowed to share it."
+    );
+    assert!(
+        !new_owners.is_empty(),
+        "The file must be shared with at least one new owner."
+    );
+    assert!(
+        file.shareable,
+        "Only files uploaded as shareable can be shared with multiple owners."
+    );
+    state.assert_not_being_duplicated(file_id);
+
+    state.duplication_queue.push(PendingDuplication {
+        file_id,
+        remaining_owners: new_owners,
+        keep_original,
+    });
+
+    let zk_state_changes = if state.duplication_queue.len() == 1 {
+        vec![start_duplication(file_id, file)]
+    } else {
+        vec![]
+    };
+    (state, vec![], zk_state_changes)
+}
+
+/// Automatically called when a copy of a file has been computed.
+///
+/// Transfers the copy to the next owner of the first file in the queue. If the file has been
+/// copied for all of its new owners, the sharing is finished by deleting the original file if it
+/// should not be kept, and the next file in the queue is duplicated.
+#[zk_on_compute_complete(shortname = 0x07)]
+fn duplication_complete(
+    context: ContractContext,
+    mut state: CollectionState,
+    zk_state: ZkState<SecretVarMetadata>,
+    output_variables: Vec<SecretVarId>,
+) -> (CollectionState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    let Some(copy_id) = output_variables.first() else {
+        panic!("No copy of the file was produced.")
+    };
+    let duplication = state
+        .duplication_queue
+        .first_mut()
+        .expect("A copy was computed without a pending duplication.");
+    let file_id = duplication.file_id;
+    let new_owner = duplication.remaining_owners.remove(0);
+    let sharing_finished = duplication.remaining_owners.is_empty();
+    let keep_original = duplication.keep_original;
+
+    let mut zk_state_changes = vec![ZkStateChange::TransferVariable {
+        variable: *copy_id,
+        new_owner,
+    }];
+
+    let metadata = state.metadata_of(file_id).clone();
+    state.file_metadata.insert(copy_id.raw_id, metadata);
+
+    if sharing_finished {
+        state.duplication_queue.remove(0);
+        if !keep_original {
+            state.file_metadata.remove(&file_id.raw_id);
+            zk_state_changes.push(ZkStateChange::DeleteVariables {
+                variables_to_delete: vec![file_id],
+            });
+        }
+    }
+
+    if let Some(next) = state.duplication_queue.first() {
+        let (_, original) = uploaded_file(&zk_state, next.file_id);
+        zk_state_changes.push(start_duplication(next.file_id, original));
+    }
+
+    (state, vec![], zk_state_changes)
+}
+
+/// Lists the files owned by `owner`, together with their public metadata.
+///
+/// ## Return Value
+///
+/// A [`FileEntry`] for each file owned by `owner`, ordered by file id.
+#[action(shortname = 0x08, zk = true)]
+pub fn files_of(
+    ctx: ContractContext,
+    state: CollectionState,
+    zk_state: ZkState<SecretVarMetadata>,
+    owner: Address,
+) -> (CollectionState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    let files: Vec<FileEntry> = state
+        .file_metadata
+        .iter()
+        .filter(|(file_id, _)| {
+            zk_state
+                .get_variable(SecretVarId::new(**file_id))
+                .is_some_and(|file| file.owner == owner)
+        })
+        .map(|(file_id, metadata)| FileEntry {
+            file_id: *file_id,
+            metadata: metadata.clone(),
+        })
+        .collect();
+
+    (state, vec![EventGroup::with_return_data(files)], vec![])
+}
+
+/// Starts the computation creating a new copy of the file with id `file_id`, which has the given
+/// `metadata`.
+fn start_duplication(file_id: SecretVarId, metadata: SecretVarMetadata) -> ZkStateChange {
+    zk_compute::duplicate_file::start(
+        file_id,
+        Some(duplication_complete::SHORTNAME),
+        &metadata,
+    )
+}
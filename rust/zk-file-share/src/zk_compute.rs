@@ -1 +1,17 @@
+use pbc_zk::*;
 
+/// Maximum length in bytes of files that can be duplicated.
+///
+/// The output of a ZK computation must have a size known at compile time, so files that can be
+/// duplicated are padded to exactly this length when uploaded.
+pub const MAX_DUPLICATABLE_FILE_LENGTH: usize = 256;
+
+/// Creates a new secret-shared copy of the file with id `file_id`.
+///
+/// ### Returns:
+///
+/// A freshly secret-shared variable with the same contents as the input file.
+#[zk_compute(shortname = 0x61)]
+pub fn duplicate_file(file_id: SecretVarId) -> [Sbu8; MAX_DUPLICATABLE_FILE_LENGTH] {
+    load_sbi::<[Sbu8; MAX_DUPLICATABLE_FILE_LENGTH]>(file_id)
+}
@@ -9,7 +9,7 @@ mod zk_compute;
 use pbc_contract_common::context::ContractContext;
 use pbc_contract_common::events::EventGroup;
 use pbc_contract_common::zk::{SecretVarId, ZkInputDef, ZkState, ZkStateChange};
-use pbc_zk::Sbi32;
+use pbc_zk::{Sbi16, Sbi32, Sbi64, Sbi8};
 use read_write_rpc_derive::ReadWriteRPC;
 use read_write_state_derive::ReadWriteState;
 
@@ -17,17 +17,91 @@ use read_write_state_derive::ReadWriteState;
 #[derive(ReadWriteState, ReadWriteRPC, Debug)]
 struct SecretVarMetadata {}
 
+/// Bit lengths of secret inputs supported by the contract.
+const SUPPORTED_BIT_LENGTHS: [u32; 4] = [8, 16, 32, 64];
+
 /// State of the contract.
 #[state]
 struct ContractState {
-    /// Vector of opened inputs.
-    opened_inputs: Vec<i32>,
+    /// Bit length of the secret inputs accepted by the contract.
+    bit_length: u32,
+    /// Whether the contract opens the running total of the inputs, instead of each input. Every
+    /// total is opened, so each input is revealed as the difference between consecutive totals.
+    running_sum: bool,
+    /// The variable holding the total of all inputs so far, when in running-sum mode.
+    sum_variable_id: Option<SecretVarId>,
+    /// The input currently being added to the total, when in running-sum mode.
+    summing_variable_id: Option<SecretVarId>,
+    /// Inputs waiting to be added to the total, while another input is being added.
+    queued_variable_ids: Vec<SecretVarId>,
+    /// Vector of opened inputs. In running-sum mode, the opened totals.
+    opened_inputs: Vec<i64>,
+}
+
+impl ContractState {
+    /// Asserts that the contract is configured to accept secret inputs of the given bit length.
+    fn assert_input_bit_length(&self, bit_length: u32) {
+        assert_eq!(
+            self.bit_length, bit_length,
+            "Secret input must be {} bits, but was {} bits",
+            self.bit_length, bit_length
+        );
+    }
+
+    /// Starts the computation for the given input, matching the configured bit length. In
+    /// running-sum mode, the computation adds the input to the total of the previous inputs, if
+    /// any.
+    fn start_computation(&mut self, variable_id: SecretVarId) -> ZkStateChange {
+        let on_complete = Some(computation_complete::SHORTNAME);
+        let metadata = &SecretVarMetadata {};
+        if self.running_sum {
+            self.summing_variable_id = Some(variable_id);
+        }
+        match (self.running_sum, self.sum_variable_id) {
+            (true, Some(sum_id)) => match self.bit_length {
+                8 => zk_compute::add_i8::start(sum_id, variable_id, on_complete, metadata),
+                16 => zk_compute::add_i16::start(sum_id, variable_id, on_complete, metadata),
+                64 => zk_compute::add_i64::start(sum_id, variable_id, on_complete, metadata),
+                _ => zk_compute::add::start(sum_id, variable_id, on_complete, metadata),
+            },
+            _ => match self.bit_length {
+                8 => zk_compute::identity_i8::start(variable_id, on_complete, metadata),
+                16 => zk_compute::identity_i16::start(variable_id, on_complete, metadata),
+                64 => zk_compute::identity_i64::start(variable_id, on_complete, metadata),
+                _ => zk_compute::identity::start(variable_id, on_complete, metadata),
+            },
+        }
+    }
 }
 
 /// Initializes contract.
+///
+/// In running-sum mode, the running total is computed with the same bit length as the inputs, and
+/// wraps around on overflow, like two's complement addition. As the total is opened after each
+/// input, opening the totals reveals each input as the difference between consecutive totals.
+///
+/// # Arguments
+/// * `bit_length` the bit length of the secret inputs. Must be 8, 16, 32 or 64.
+/// * `running_sum` whether to open the running total of the inputs, instead of each input.
 #[init(zk = true)]
-fn initialize(ctx: ContractContext, zk_state: ZkState<SecretVarMetadata>) -> ContractState {
+fn initialize(
+    ctx: ContractContext,
+    zk_state: ZkState<SecretVarMetadata>,
+    bit_length: u32,
+    running_sum: bool,
+) -> ContractState {
+    assert!(
+        SUPPORTED_BIT_LENGTHS.contains(&bit_length),
+        "Unsupported bit length {}. Must be one of {:?}",
+        bit_length,
+        SUPPORTED_BIT_LENGTHS
+    );
     ContractState {
+        bit_length,
+        running_sum,
+        sum_variable_id: None,
+        summing_variable_id: None,
+        queued_variable_ids: vec![],
         opened_inputs: vec![],
     }
 }
@@ -40,6 +114,11 @@ fn reset_contract(
     zk_state: ZkState<SecretVarMetadata>,
 ) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
     let new_state = ContractState {
+        bit_length: state.bit_length,
+        running_sum: state.running_sum,
+        sum_variable_id: None,
+        summing_variable_id: None,
+        queued_variable_ids: vec![],
         opened_inputs: vec![],
     };
     let all_variables = zk_state
@@ -58,7 +137,9 @@ fn reset_contract(
     )
 }
 
-/// Adds a secret input variable.
+/// Adds a secret 32-bit input variable.
+///
+/// Only allowed if the contract is configured for 32-bit inputs.
 #[zk_on_secret_input(shortname = 0x40)]
 fn secret_input(
     context: ContractContext,
@@ -69,6 +150,67 @@ fn secret_input(
     Vec<EventGroup>,
     ZkInputDef<SecretVarMetadata, Sbi32>,
 ) {
+    state.assert_input_bit_length(32);
+    let input_def =
+        ZkInputDef::with_metadata(Some(output_variables::SHORTNAME), SecretVarMetadata {});
+
+    (state, vec![], input_def)
+}
+
+/// Adds a secret 8-bit input variable.
+///
+/// Only allowed if the contract is configured for 8-bit inputs.
+#[zk_on_secret_input(shortname = 0x43)]
+fn secret_input_i8(
+    context: ContractContext,
+    state: ContractState,
+    zk_state: ZkState<SecretVarMetadata>,
+) -> (
+    ContractState,
+    Vec<EventGroup>,
+    ZkInputDef<SecretVarMetadata, Sbi8>,
+) {
+    state.assert_input_bit_length(8);
+    let input_def =
+        ZkInputDef::with_metadata(Some(output_variables::SHORTNAME), SecretVarMetadata {});
+
+    (state, vec![], input_def)
+}
+
+/// Adds a secret 16-bit input variable.
+///
+/// Only allowed if the contract is configured for 16-bit inputs.
+#[zk_on_secret_input(shortname = 0x44)]
+fn secret_input_i16(
+    context: ContractContext,
+    state: ContractState,
+    zk_state: ZkState<SecretVarMetadata>,
+) -> (
+    ContractState,
+    Vec<EventGroup>,
+    ZkInputDef<SecretVarMetadata, Sbi16>,
+) {
+    state.assert_input_bit_length(16);
+    let input_def =
+        ZkInputDef::with_metadata(Some(output_variables::SHORTNAME), SecretVarMetadata {});
+
+    (state, vec![], input_def)
+}
+
+/// Adds a secret 64-bit input variable.
+///
+/// Only allowed if the contract is configured for 64-bit inputs.
+#[zk_on_secret_input(shortname = 0x45)]
+fn secret_input_i64(
+    context: ContractContext,
+    state: ContractState,
+    zk_state: ZkState<SecretVarMetadata>,
+) -> (
+    ContractState,
+    Vec<EventGroup>,
+    ZkInputDef<SecretVarMetadata, Sbi64>,
+) {
+    state.assert_input_bit_length(64);
     let input_def =
         ZkInputDef::with_metadata(Some(output_variables::SHORTNAME), SecretVarMetadata {});
 
@@ -76,22 +218,23 @@ fn secret_input(
 }
 
 /// Immediately starts a zk computation when the variable input is completed.
+///
+/// In running-sum mode, an input which arrives while another input is being added to the total is
+/// queued, and added once the new total has been opened.
 #[zk_on_variable_inputted(shortname = 0x41)]
 fn output_variables(
     context: ContractContext,
-    state: ContractState,
+    mut state: ContractState,
     zk_state: ZkState<SecretVarMetadata>,
     variable_id: SecretVarId,
 ) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
-    (
-        state,
-        vec![],
-        vec![zk_compute::identity::start(
-            variable_id,
-            Some(computation_complete::SHORTNAME),
-            &SecretVarMetadata {},
-        )],
-    )
+    if state.summing_variable_id.is_some() {
+        state.queued_variable_ids.push(variable_id);
+        return (state, vec![], vec![]);
+    }
+
+    let computation = state.start_computation(variable_id);
+    (state, vec![], vec![computation])
 }
 
 /// Immediately opens the output variable of the computation.
@@ -112,6 +255,10 @@ fn computation_complete(
 }
 
 /// Saves the opened variable in state and readies another computation.
+///
+/// In running-sum mode, the opened variable holds the new total, which the next input is added to.
+/// The previous total and the input which was added are then deleted, and the next queued input,
+/// if any, is added to the new total.
 #[zk_on_variables_opened]
 fn save_opened_variable(
     context: ContractContext,
@@ -121,18 +268,41 @@ fn save_opened_variable(
 ) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
     let mut new_state = state;
 
-    let result: i32 = read_variable_as_i32(&zk_state, *opened_variables.first().unwrap());
+    let opened_variable_id = *opened_variables.first().unwrap();
+    let result: i64 = read_variable_as_i64(&zk_state, opened_variable_id);
     new_state.opened_inputs.push(result);
 
-    (new_state, vec![], vec![])
+    if !new_state.running_sum {
+        return (new_state, vec![], vec![]);
+    }
+
+    let variables_to_delete = new_state
+        .sum_variable_id
+        .into_iter()
+        .chain(new_state.summing_variable_id.take())
+        .collect();
+    new_state.sum_variable_id = Some(opened_variable_id);
+
+    let mut zk_state_changes = vec![ZkStateChange::DeleteVariables {
+        variables_to_delete,
+    }];
+    if !new_state.queued_variable_ids.is_empty() {
+        let next_variable_id = new_state.queued_variable_ids.remove(0);
+        zk_state_changes.push(new_state.start_computation(next_variable_id));
+    }
+
+    (new_state, vec![], zk_state_changes)
 }
 
-fn read_variable_as_i32(
+/// Reads an opened variable of at most 64 bits as a sign-extended [`i64`].
+fn read_variable_as_i64(
     zk_state: &ZkState<SecretVarMetadata>,
     sum_variable_id: SecretVarId,
-) -> i32 {
+) -> i64 {
     let sum_variable = zk_state.get_variable(sum_variable_id).unwrap();
-    let mut buffer = [0u8; 4];
-    buffer.copy_from_slice(sum_variable.data.as_ref().unwrap().as_slice());
-    <i32>::from_le_bytes(buffer)
+    let data = sum_variable.data.as_ref().unwrap().as_slice();
+    let mut buffer = [0u8; 8];
+    buffer[..data.len()].copy_from_slice(data);
+    let unused_bits = 64 - 8 * data.len() as u32;
+    (<i64>::from_le_bytes(buffer) << unused_bits) >> unused_bits
 }
@@ -6,6 +6,49 @@ pub fn identity(input_id: SecretVarId) -> Sbi32 {
     load_sbi::<Sbi32>(input_id)
 }
 
+/// Creates a new output variable with the same value as the 8-bit input variable.
+#[zk_compute(shortname = 0x62)]
+pub fn identity_i8(input_id: SecretVarId) -> Sbi8 {
+    load_sbi::<Sbi8>(input_id)
+}
+
+/// Creates a new output variable with the same value as the 16-bit input variable.
+#[zk_compute(shortname = 0x63)]
+pub fn identity_i16(input_id: SecretVarId) -> Sbi16 {
+    load_sbi::<Sbi16>(input_id)
+}
+
+/// Creates a new output variable with the same value as the 64-bit input variable.
+#[zk_compute(shortname = 0x64)]
+pub fn identity_i64(input_id: SecretVarId) -> Sbi64 {
+    load_sbi::<Sbi64>(input_id)
+}
+
+/// Creates a new output variable with the sum of the two input variables. The sum wraps around on
+/// overflow, as do the sums of the other bit lengths.
+#[zk_compute(shortname = 0x65)]
+pub fn add(sum_id: SecretVarId, input_id: SecretVarId) -> Sbi32 {
+    load_sbi::<Sbi32>(sum_id) + load_sbi::<Sbi32>(input_id)
+}
+
+/// Creates a new output variable with the sum of the two 8-bit input variables.
+#[zk_compute(shortname = 0x66)]
+pub fn add_i8(sum_id: SecretVarId, input_id: SecretVarId) -> Sbi8 {
+    load_sbi::<Sbi8>(sum_id) + load_sbi::<Sbi8>(input_id)
+}
+
+/// Creates a new output variable with the sum of the two 16-bit input variables.
+#[zk_compute(shortname = 0x67)]
+pub fn add_i16(sum_id: SecretVarId, input_id: SecretVarId) -> Sbi16 {
+    load_sbi::<Sbi16>(sum_id) + load_sbi::<Sbi16>(input_id)
+}
+
+/// Creates a new output variable with the sum of the two 64-bit input variables.
+#[zk_compute(shortname = 0x68)]
+pub fn add_i64(sum_id: SecretVarId, input_id: SecretVarId) -> Sbi64 {
+    load_sbi::<Sbi64>(sum_id) + load_sbi::<Sbi64>(input_id)
+}
+
 test_eq!(identity(SecretVarId::new(1)), 0, [0i32]);
 test_eq!(identity(SecretVarId::new(1)), 9, [9i32]);
 test_eq!(
@@ -18,3 +61,25 @@ test_eq!(
     2_147_483_647i32,
     [2_147_483_647i32]
 );
+test_eq!(identity_i8(SecretVarId::new(1)), -128i8, [-128i8]);
+test_eq!(identity_i8(SecretVarId::new(1)), 127i8, [127i8]);
+test_eq!(identity_i16(SecretVarId::new(1)), -32_768i16, [-32_768i16]);
+test_eq!(
+    identity_i64(SecretVarId::new(1)),
+    -9_223_372_036_854_775_808i64,
+    [-9_223_372_036_854_775_808i64]
+);
+test_eq!(add(SecretVarId::new(1), SecretVarId::new(2)), 15, [10i32, 5i32]);
+test_eq!(add(SecretVarId::new(1), SecretVarId::new(2)), -3, [4i32, -7i32]);
+test_eq!(add_i8(SecretVarId::new(1), SecretVarId::new(2)), 100i8, [60i8, 40i8]);
+test_eq!(add_i8(SecretVarId::new(1), SecretVarId::new(2)), -106i8, [100i8, 50i8]);
+test_eq!(
+    add(SecretVarId::new(1), SecretVarId::new(2)),
+    -2_147_483_648i32,
+    [2_147_483_647i32, 1i32]
+);
+test_eq!(
+    add_i64(SecretVarId::new(1), SecretVarId::new(2)),
+    4_000_000_000i64,
+    [3_000_000_000i64, 1_000_000_000i64]
+);
@@ -67,8 +67,14 @@ struct ContractState {
     owner: Address,
     /// Registered bidders - only registered bidders are allowed to bid.
     registered_bidders: AvlTreeMap<Address, RegisteredBidder>,
+    /// Maximum number of bids that can be placed in the auction.
+    max_num_bidders: u32,
+    /// Time in UTC millis from which bids are no longer accepted and the auction can be started.
+    bidding_deadline_utc_millis: i64,
     /// Whether the auction has already begun?
     auction_begun: bool,
+    /// Whether the auction was cancelled before it began. A cancelled auction never begins.
+    auction_cancelled: bool,
     /// The auction result
     auction_result: Option<AuctionResult>,
 }
@@ -84,12 +90,31 @@ struct AuctionResult {
 /// Initializes contract
 ///
 /// Note that owner is set to whoever initializes the contact.
+///
+/// `max_num_bidders` limits the number of bids, as every bid increases the cost of the auction
+/// computation. Must be at least [`MIN_NUM_BIDDERS`].
+///
+/// `bidding_deadline_utc_millis` is the time in UTC millis at which bidding closes. Bids are only
+/// accepted before the deadline, and the auction can only be started once it has passed, such
+/// that the owner cannot choose when to close the bidding.
 #[init(zk = true)]
-fn initialize(context: ContractContext, zk_state: ZkState<SecretVarMetadata>) -> ContractState {
+fn initialize(
+    context: ContractContext,
+    zk_state: ZkState<SecretVarMetadata>,
+    max_num_bidders: u32,
+    bidding_deadline_utc_millis: i64,
+) -> ContractState {
+    assert!(
+        max_num_bidders >= MIN_NUM_BIDDERS,
+        "Maximum number of bidders must be at least {MIN_NUM_BIDDERS}, but was {max_num_bidders}",
+    );
     ContractState {
         owner: context.sender,
         registered_bidders: AvlTreeMap::new(),
+        max_num_bidders,
+        bidding_deadline_utc_millis,
         auction_begun: false,
+        auction_cancelled: false,
         auction_result: None,
     }
 }
@@ -106,6 +131,7 @@ fn initialize(context: ContractContext, zk_state: ZkState<SecretVarMetadata>) ->
 ///
 /// - Only the sender can add bidders.
 /// - The auction must not already have been started (by calling [`start_auction`].)
+/// - The auction must not have been cancelled (by calling [`cancel_auction`].)
 /// - Bidders must not already be registered.
 #[action(shortname = 0x30, zk = true)]
 fn register_bidders(
@@ -118,6 +144,10 @@ fn register_bidders(
         !state.auction_begun,
         "Cannot register bidders after auction has begun"
     );
+    assert!(
+        !state.auction_cancelled,
+        "Cannot register bidders after auction has been cancelled"
+    );
     assert_eq!(
         context.sender, state.owner,
         "Only the owner can register bidders"
@@ -148,7 +178,10 @@ fn register_bidders(
 ///
 /// - Only the bidders can place bids.
 /// - The auction must not already have been started (by calling [`start_auction`].)
+/// - The auction must not have been cancelled (by calling [`cancel_auction`].)
+/// - The bidding deadline must not have passed.
 /// - Bidders must not already have placed a bid.
+/// - Less than [`ContractState::max_num_bidders`] bids must have been placed.
 #[zk_on_secret_input(shortname = 0x40)]
 fn place_bid(
     context: ContractContext,
@@ -163,6 +196,16 @@ fn place_bid(
         !state.auction_begun,
         "Cannot place bid after auction has begun"
     );
+    assert!(
+        !state.auction_cancelled,
+        "Cannot place bid after auction has been cancelled"
+    );
+    assert!(
+        context.block_production_time < state.bidding_deadline_utc_millis,
+        "Cannot place bid after the bidding deadline. Current time is {} ms UTC, deadline was {} ms UTC",
+        context.block_production_time,
+        state.bidding_deadline_utc_millis,
+    );
 
     // Only bidders that have not already placed bids can bid.
     let Some(mut bidder_info) = state.registered_bidders.get(&context.sender) else {
@@ -173,6 +216,12 @@ fn place_bid(
         "Each bidder is only allowed to place one bid: {:?}",
         context.sender,
     );
+    let amount_of_bids = (zk_state.secret_variables.len() + zk_state.pending_inputs.len()) as u32;
+    assert!(
+        amount_of_bids < state.max_num_bidders,
+        "Cannot place more than {} bids",
+        state.max_num_bidders,
+    );
 
     let input_def = ZkInputDef::with_metadata(None, SecretVarMetadata { is_bid: true });
 
@@ -191,7 +240,8 @@ const NOT_A_BID: SecretVarMetadata = SecretVarMetadata { is_bid: false };
 ///
 /// Requirements:
 /// - Can only be run by the owner.
-/// - The auction must not already have started.
+/// - The auction must not already have started, or have been cancelled.
+/// - The bidding deadline must have passed.
 /// - And at least [`MIN_NUM_BIDDERS`] must have placed their bids.
 ///
 /// The second price auction computation is beyond this call, involving several ZK computation steps.
@@ -205,10 +255,20 @@ fn start_auction(
         !state.auction_begun,
         "Cannot start auction after it has already begun"
     );
+    assert!(
+        !state.auction_cancelled,
+        "Cannot start auction after it has been cancelled"
+    );
     assert_eq!(
         context.sender, state.owner,
         "Only contract owner can start the auction"
     );
+    assert!(
+        context.block_production_time >= state.bidding_deadline_utc_millis,
+        "Cannot start auction before the bidding deadline. Current time is {} ms UTC, deadline is {} ms UTC",
+        context.block_production_time,
+        state.bidding_deadline_utc_millis,
+    );
     let amount_of_bidders = zk_state.secret_variables.len() as u32;
     assert!(
         amount_of_bidders >= MIN_NUM_BIDDERS,
@@ -227,6 +287,57 @@ fn start_auction(
     )
 }
 
+/// Cancels the auction before it has started, for example if too few bidders have registered.
+///
+/// All placed bids are deleted, the registered bidders are cleared, and the auction is marked as
+/// cancelled. The auction never begins, so [`ContractState::auction_begun`] stays false, while
+/// [`ContractState::auction_cancelled`] is set. The contract is marked as done, and no bidders can
+/// be registered and no bids can be placed afterwards.
+///
+/// Requirements:
+/// - Can only be run by the owner.
+/// - The auction must not already have started, or have been cancelled.
+#[action(shortname = 0x02, zk = true)]
+fn cancel_auction(
+    context: ContractContext,
+    mut state: ContractState,
+    zk_state: ZkState<SecretVarMetadata>,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    assert!(
+        !state.auction_begun,
+        "Cannot cancel auction after it has begun"
+    );
+    assert!(
+        !state.auction_cancelled,
+        "Cannot cancel auction after it has been cancelled"
+    );
+    assert_eq!(
+        context.sender, state.owner,
+        "Only contract owner can cancel the auction"
+    );
+
+    let all_bids = zk_state
+        .secret_variables
+        .iter()
+        .chain(zk_state.pending_inputs.iter())
+        .map(|(variable_id, _)| variable_id)
+        .collect();
+
+    state.registered_bidders = AvlTreeMap::new();
+    state.auction_cancelled = true;
+
+    (
+        state,
+        vec![],
+        vec![
+            ZkStateChange::DeleteVariables {
+                variables_to_delete: all_bids,
+            },
+            ZkStateChange::ContractDone,
+        ],
+    )
+}
+
 /// Automatically called when the computation is completed
 ///
 /// The only thing we do is instantly open/declassify the output variables.
@@ -6,7 +6,7 @@ extern crate pbc_contract_common;
 
 use create_type_spec_derive::CreateTypeSpec;
 use pbc_contract_common::address::Address;
-use pbc_contract_common::avl_tree_map::AvlTreeSet;
+use pbc_contract_common::avl_tree_map::{AvlTreeMap, AvlTreeSet};
 use pbc_contract_common::context::ContractContext;
 use pbc_contract_common::events::EventGroup;
 use pbc_contract_common::zk::{CalculationStatus, SecretVarId, ZkInputDef, ZkState, ZkStateChange};
@@ -19,6 +19,8 @@ mod zk_compute;
 #[derive(ReadWriteState, Debug)]
 #[repr(C)]
 struct SecretVarMetadata {
+    /// The proposal that the secret variable belongs to.
+    proposal_id: u32,
     /// The type of the secret variable. Indicates if the variable is a vote or the number of counted for votes
     variable_type: SecretVarType,
 }
@@ -44,78 +46,162 @@ struct VoteResult {
     passed: bool,
 }
 
-/// This contract's state
-#[state]
-struct ContractState {
-    /// Address that deployed the contract
-    owner: Address,
+/// A proposal that can be voted on.
+#[derive(ReadWriteState, CreateTypeSpec)]
+struct Proposal {
     /// When the voting stops; at this point all inputs must have been made, and vote counting can
     /// now begin.
     /// Represented as milliseconds since the epoch.
     deadline_voting_time: i64,
+    /// Minimum number of votes that must have been cast before the votes can be counted. Prevents
+    /// revealing the aggregate of only a few voters.
+    min_votes_to_count: u32,
     /// A tally that holds the number of votes for, the number of votes against,
     /// and a bool indicating whether the vote passed. It is initialized as None and is
     /// eventually updated to Some(VoteResult) after start_vote_counting is called
     vote_result: Option<VoteResult>,
-    /// Maintains the set of voters that have already voted.
-    already_voted: AvlTreeSet<Address>,
 }
 
-/// Initializes contract
-///
-/// # Arguments
-/// * `voting_duration_ms` number of milliseconds from contract initialization where voting is
-/// open
+/// A voter that has voted on a proposal.
+#[derive(ReadWriteState, CreateTypeSpec, PartialEq, Eq, PartialOrd, Ord)]
+struct ProposalVoter {
+    /// The proposal that was voted on.
+    proposal_id: u32,
+    /// The voter.
+    voter: Address,
+}
+
+/// This contract's state
+#[state]
+struct ContractState {
+    /// Address that deployed the contract
+    owner: Address,
+    /// The proposals opened by the owner, identified by their proposal id.
+    proposals: AvlTreeMap<u32, Proposal>,
+    /// Maintains the set of voters that have already voted on each proposal.
+    already_voted: AvlTreeSet<ProposalVoter>,
+}
+
+impl ContractState {
+    /// Gets the proposal with the given id, panicking if it has not been opened.
+    fn get_proposal(&self, proposal_id: u32) -> Proposal {
+        self.proposals
+            .get(&proposal_id)
+            .unwrap_or_else(|| panic!("No proposal with id {proposal_id}"))
+    }
+}
+
+/// Initializes contract without any proposals.
 #[init(zk = true)]
-fn initialize(
-    ctx: ContractContext,
-    _zk_state: ZkState<SecretVarMetadata>,
-    voting_duration_ms: u32,
-) -> ContractState {
-    let deadline_voting_time = ctx.block_production_time + (voting_duration_ms as i64);
+fn initialize(ctx: ContractContext, _zk_state: ZkState<SecretVarMetadata>) -> ContractState {
     ContractState {
         owner: ctx.sender,
-        deadline_voting_time,
-        vote_result: None,
+        proposals: AvlTreeMap::new(),
         already_voted: AvlTreeSet::new(),
     }
 }
 
-/// Casts another vote.
+/// Opens a new proposal for voting. Only the owner can open proposals.
+///
+/// # Arguments
+/// * `proposal_id` identifier of the new proposal
+/// * `voting_duration_ms` number of milliseconds from now where voting on the proposal is open
+/// * `min_votes_to_count` minimum number of votes required before the votes can be counted
+#[action(shortname = 0x02, zk = true)]
+fn open_proposal(
+    context: ContractContext,
+    mut state: ContractState,
+    _zk_state: ZkState<SecretVarMetadata>,
+    proposal_id: u32,
+    voting_duration_ms: u32,
+    min_votes_to_count: u32,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    assert_eq!(context.sender, state.owner, "Only the owner can open proposals");
+    assert!(
+        !state.proposals.contains_key(&proposal_id),
+        "Proposal with id {proposal_id} already exists"
+    );
+
+    let deadline_voting_time = context.block_production_time + (voting_duration_ms as i64);
+    state.proposals.insert(
+        proposal_id,
+        Proposal {
+            deadline_voting_time,
+            min_votes_to_count,
+            vote_result: None,
+        },
+    );
+    (state, vec![], vec![])
+}
+
+/// Casts another vote on the given proposal.
 ///
-/// Can only be used by an address that have not already cast a vote.
+/// Can only be used by an address that have not already cast a vote on the proposal.
 #[zk_on_secret_input(shortname = 0x40)]
 fn add_vote(
     context: ContractContext,
     mut state: ContractState,
     _zk_state: ZkState<SecretVarMetadata>,
+    proposal_id: u32,
 ) -> (
     ContractState,
     Vec<EventGroup>,
     ZkInputDef<SecretVarMetadata, Sbi32>,
 ) {
+    let proposal = state.get_proposal(proposal_id);
     assert!(
-        context.block_production_time < state.deadline_voting_time,
+        context.block_production_time < proposal.deadline_voting_time,
         "Not allowed to vote after the deadline at {} ms UTC, current time is {} ms UTC",
-        state.deadline_voting_time,
+        proposal.deadline_voting_time,
         context.block_production_time,
     );
+    let proposal_voter = ProposalVoter {
+        proposal_id,
+        voter: context.sender,
+    };
     assert!(
-        !state.already_voted.contains(&context.sender),
+        !state.already_voted.contains(&proposal_voter),
         "Each voter is only allowed to send one vote variable. Sender: {:?}",
         context.sender
     );
     let input_def = ZkInputDef::with_metadata(
         None,
         SecretVarMetadata {
+            proposal_id,
             variable_type: SecretVarType::Vote,
         },
     );
-    state.already_voted.insert(context.sender);
+    state.already_voted.insert(proposal_voter);
     (state, vec![], input_def)
 }
 
-/// Allows anybody to start the computation of the vote.
+/// Checks whether the given address has voted on the given proposal, returning the answer as
+/// return data.
+///
+/// Only participation is revealed, which is already public through `already_voted`. The content
+/// of the vote stays secret, as the set of voters holds no information about the individual votes,
+/// and the votes are only ever opened as an aggregate.
+///
+/// # Arguments
+/// * `proposal_id` identifier of the proposal
+/// * `voter` address of the voter to check
+#[action(shortname = 0x03, zk = true)]
+fn did_vote(
+    _context: ContractContext,
+    state: ContractState,
+    _zk_state: ZkState<SecretVarMetadata>,
+    proposal_id: u32,
+    voter: Address,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    state.get_proposal(proposal_id);
+    let voted = state
+        .already_voted
+        .contains(&ProposalVoter { proposal_id, voter });
+    (state, vec![EventGroup::with_return_data(voted)], vec![])
+}
+
+/// Allows anybody to start the computation of the vote on the given proposal, once the deadline
+/// has passed and at least the proposal's minimum number of votes have been cast.
 ///
 /// The vote computation is automatic beyond this call, involving several steps, as described in the module documentation.
 ///
@@ -125,13 +211,32 @@ fn start_vote_counting(
     context: ContractContext,
     state: ContractState,
     zk_state: ZkState<SecretVarMetadata>,
+    proposal_id: u32,
 ) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    let proposal = state.get_proposal(proposal_id);
     assert!(
-        context.block_production_time >= state.deadline_voting_time,
+        context.block_production_time >= proposal.deadline_voting_time,
         "Vote counting cannot start before specified starting time {} ms UTC, current time is {} ms UTC",
-        state.deadline_voting_time,
+        proposal.deadline_voting_time,
         context.block_production_time,
     );
+    assert!(
+        proposal.vote_result.is_none(),
+        "Votes for proposal {proposal_id} have already been counted"
+    );
+    let number_of_votes = zk_state
+        .secret_variables
+        .iter()
+        .filter(|(_, x)| {
+            x.metadata.proposal_id == proposal_id
+                && x.metadata.variable_type == SecretVarType::Vote
+        })
+        .count() as u32;
+    assert!(
+        number_of_votes >= proposal.min_votes_to_count,
+        "Vote counting requires at least {} votes, but proposal {proposal_id} has {number_of_votes}",
+        proposal.min_votes_to_count,
+    );
     assert_eq!(
         zk_state.calculation_state,
         CalculationStatus::Waiting,
@@ -143,8 +248,10 @@ fn start_vote_counting(
         state,
         vec![],
         vec![zk_compute::count_for_votes::start(
+            proposal_id,
             Some(counting_complete::SHORTNAME),
             &SecretVarMetadata {
+                proposal_id,
                 variable_type: SecretVarType::CountedForVotes,
             },
         )],
@@ -172,7 +279,8 @@ fn counting_complete(
 
 /// Automatically called when a variable is opened/declassified.
 ///
-/// We can now read the for and against variables, and compute the result
+/// We can now read the for and against variables, and compute the result of the proposal that
+/// the votes were counted for. The votes and the counted result are deleted afterwards.
 #[zk_on_variables_opened]
 fn open_sum_variable(
     _context: ContractContext,
@@ -185,18 +293,38 @@ fn open_sum_variable(
         1,
         "Unexpected number of output variables"
     );
+    let sum_variable_id = *opened_variables.first().unwrap();
+    let proposal_id = zk_state
+        .get_variable(sum_variable_id)
+        .unwrap()
+        .metadata
+        .proposal_id;
     let votes_for = read_variable_u32_le(&zk_state, opened_variables.first());
-    let total_votes = zk_state
+    let votes: Vec<SecretVarId> = zk_state
         .secret_variables
         .iter()
-        .filter(|(_, x)| x.metadata.variable_type == SecretVarType::Vote)
-        .count();
-    let votes_against = (total_votes as u32) - votes_for;
+        .filter(|(_, x)| {
+            x.metadata.proposal_id == proposal_id
+                && x.metadata.variable_type == SecretVarType::Vote
+        })
+        .map(|(variable_id, _)| variable_id)
+        .collect();
+    let votes_against = (votes.len() as u32) - votes_for;
+
+    let mut proposal = state.get_proposal(proposal_id);
+    proposal.vote_result = Some(determine_result(votes_for, votes_against));
+    state.proposals.insert(proposal_id, proposal);
 
-    let vote_result = determine_result(votes_for, votes_against);
-    state.vote_result = Some(vote_result);
+    let mut variables_to_delete = votes;
+    variables_to_delete.push(sum_variable_id);
 
-    (state, vec![], vec![ZkStateChange::ContractDone])
+    (
+        state,
+        vec![],
+        vec![ZkStateChange::DeleteVariables {
+            variables_to_delete,
+        }],
+    )
 }
 
 /// Reads a variable's data as an u32.
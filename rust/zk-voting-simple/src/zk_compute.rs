@@ -1,19 +1,25 @@
 use pbc_zk::*;
 
 /// Perform a zk computation on secret-shared data to count the number
-/// of for votes (non-zero).
+/// of for votes (non-zero) on the given proposal.
+///
+/// ### Arguments:
+///
+/// * `proposal_id`: The proposal to count votes for. Votes for other proposals are ignored.
 ///
 /// ### Returns:
 ///
 /// The number of accepting votes.
 #[zk_compute(shortname = 0x61)]
-pub fn count_for_votes() -> Sbi32 {
+pub fn count_for_votes(proposal_id: u32) -> Sbi32 {
     // Initialize votes
     let mut votes_for: Sbi32 = Sbi32::from(0);
 
     // Count votes
     for variable_id in secret_variable_ids() {
-        if load_sbi::<Sbi32>(variable_id) != Sbi32::from(0) {
+        if load_metadata::<u32>(variable_id) == proposal_id
+            && load_sbi::<Sbi32>(variable_id) != Sbi32::from(0)
+        {
             votes_for = votes_for + Sbi32::from(1);
         }
     }